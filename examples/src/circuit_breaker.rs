@@ -71,7 +71,7 @@ pub mod inputs {
     #[derive(PartialEq, Debug, Default)]
     pub struct Success;
     /// A failed request.
-    #[derive(PartialEq, Debug, Default)]
+    #[derive(PartialEq, Debug, Default, Clone)]
     pub struct Fail;
 }
 
@@ -80,16 +80,19 @@ pub mod inputs {
     states(states::Closed, states::Open, states::HalfOpen),
     outputs(),
     transitions(
-        (states::Closed, inputs::Success) -> (states::Closed) = handle_count_reset,
+        [hot] (states::Closed, inputs::Success) -> (states::Closed) = handle_count_reset,
         (states::Closed, inputs::Fail)    -> (states::Closed) :  guard_below_threshold = handle_count_increment,
         (states::Closed, inputs::Fail)    -> (states::Open)   :  ! guard_below_threshold = handle_trip_breaker,
 
         (states::Open) -> (states::Open)     :  !guard_timeout,
         (states::Open) -> (states::HalfOpen) :  guard_timeout,
 
-        (states::HalfOpen, inputs::Fail)    -> (states::Open) = handle_setup_timer,
+        [cold] (states::HalfOpen, inputs::Fail) -> (states::Open) = handle_setup_timer,
         (states::HalfOpen, inputs::Success) -> (states::Closed)  // Resumes normal operation
     ),
+    diagram(layout = flowchart),
+    dispatch(table),
+    consume_methods(true),
     derive(Debug)
 )]
 pub struct CircuitBreaker {
@@ -130,6 +133,14 @@ impl CircuitBreaker {
     }
 }
 
+impl Timers for CircuitBreaker {
+    fn visit_timers(&self, state: &Self::State, f: &mut dyn FnMut(&Timer)) {
+        if let Some(open) = state.maybe_open() {
+            f(&open.timer);
+        }
+    }
+}
+
 #[test]
 fn circuit_breaker() {
     let clock = ManualClock::new();
@@ -172,6 +183,531 @@ fn circuit_breaker() {
     assert!(cb.state().is_closed());
 }
 
+#[test]
+#[should_panic(expected = "Invalid transition from Open using input Success")]
+fn circuit_breaker_rejects_through_dispatch_table() {
+    let clock = ManualClock::new();
+    let circuit_breaker = CircuitBreaker {
+        clock: clock.clone_box(),
+        threshold: 0,
+        timeout: TimestampDelta::from_secs(5),
+    };
+    let mut cb = StateMachine::new(circuit_breaker, states::Closed::default());
+    cb.consume(inputs::Fail);
+    assert!(cb.state().is_open());
+    // The table has no function for this state and input.
+    cb.consume(inputs::Success);
+}
+
+#[test]
+fn circuit_breaker_consume_methods() {
+    let clock = ManualClock::new();
+    let circuit_breaker = CircuitBreaker {
+        clock: clock.clone_box(),
+        threshold: 1,
+        timeout: TimestampDelta::from_secs(5),
+    };
+    let mut cb = StateMachine::new(circuit_breaker, states::Closed::default());
+
+    // The guards pick the transition, as with `consume`.
+    cb.consume_fail(inputs::Fail);
+    assert_eq!(cb.state().closed().count, 1);
+    cb.consume_success(inputs::Success);
+    assert_eq!(cb.state().closed().count, 0);
+    cb.consume_fail(inputs::Fail);
+    cb.consume_fail(inputs::Fail);
+    assert!(cb.state().is_open());
+
+    // With an observer, the methods go through `consume`.
+    let observed = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let counter = observed.clone();
+    cb.observe(move |_| {
+        counter.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    });
+    clock.advance_by(TimestampDelta::from_secs(5));
+    cb.step();
+    cb.consume_success(inputs::Success);
+    assert!(cb.state().is_closed());
+    assert_eq!(observed.load(std::sync::atomic::Ordering::Relaxed), 2);
+}
+
+#[test]
+#[should_panic(expected = "Invalid transition from Open using input Success")]
+fn circuit_breaker_consume_method_rejects() {
+    let clock = ManualClock::new();
+    let circuit_breaker = CircuitBreaker {
+        clock: clock.clone_box(),
+        threshold: 0,
+        timeout: TimestampDelta::from_secs(5),
+    };
+    let mut cb = StateMachine::new(circuit_breaker, states::Closed::default());
+    cb.consume_fail(inputs::Fail);
+    cb.consume_success(inputs::Success);
+}
+
+// The transition structure is checked at compile time. Ids follow the order of declaration,
+// with `Failure` and `Nothing` at 0.
+const CLOSED: StateId<CircuitBreaker> = EnumId::new(1);
+const OPEN: StateId<CircuitBreaker> = EnumId::new(2);
+const SUCCESS: InputId<CircuitBreaker> = EnumId::new(1);
+const _: () = assert!(CircuitBreaker::has_transition(CLOSED, SUCCESS));
+const _: () = assert!(!CircuitBreaker::has_transition(OPEN, SUCCESS));
+
+#[test]
+fn circuit_breaker_const_transitions() {
+    let fail = inputs::Fail::enum_id();
+    assert!(CircuitBreaker::has_transition(CLOSED, fail));
+    // Where `Fail` leads depends on the guard.
+    assert_eq!(CircuitBreaker::transition_target(CLOSED, fail), None);
+    assert_eq!(
+        CircuitBreaker::transition_target(CLOSED, SUCCESS),
+        Some((CLOSED, EnumId::new(0)))
+    );
+    assert_eq!(CircuitBreaker::transition_target(OPEN, SUCCESS), None);
+}
+
+#[test]
+fn circuit_breaker_run_until_blocked() {
+    let clock = ManualClock::new();
+    let circuit_breaker = CircuitBreaker {
+        clock: clock.clone_box(),
+        threshold: 0,
+        timeout: TimestampDelta::from_secs(5),
+    };
+    let mut cb = StateMachine::new(circuit_breaker, states::Closed::default());
+
+    // No spontaneous transitions when closed.
+    assert_eq!(cb.run_until_blocked(), 0);
+    cb.consume(inputs::Fail);
+    assert!(cb.state().is_open());
+
+    // The timer did not expire yet, only the self-loop fires.
+    assert_eq!(cb.run_until_blocked(), 1);
+    assert!(cb.state().is_open());
+
+    clock.advance_by(TimestampDelta::from_secs(5));
+    assert_eq!(cb.run_until_blocked(), 1);
+    assert!(cb.state().is_half_open());
+}
+
+#[test]
+fn circuit_breaker_force_state() {
+    let clock = ManualClock::new();
+    let circuit_breaker = CircuitBreaker {
+        clock: clock.clone_box(),
+        threshold: 0,
+        timeout: TimestampDelta::from_secs(5),
+    };
+    let mut cb = StateMachine::new(circuit_breaker, states::Closed::default());
+
+    // Start directly in the half-open state.
+    cb.force_state(states::HalfOpen);
+    assert!(cb.state().is_half_open());
+    cb.consume(inputs::Fail);
+    assert!(cb.state().is_open());
+}
+
+#[test]
+fn circuit_breaker_state_as() {
+    fn failures<T: StateMachineImpl>(sm: &StateMachine<T>) -> Option<u32> {
+        sm.state_as::<states::Closed>().map(|closed| closed.count)
+    }
+
+    let clock = ManualClock::new();
+    let circuit_breaker = CircuitBreaker {
+        clock: clock.clone_box(),
+        threshold: 1,
+        timeout: TimestampDelta::from_secs(5),
+    };
+    let mut cb = StateMachine::new(circuit_breaker, states::Closed::default());
+    cb.consume(inputs::Fail);
+    assert_eq!(failures(&cb), Some(1));
+    cb.consume(inputs::Fail);
+    assert_eq!(failures(&cb), None);
+    assert!(cb.state_as::<states::Open>().is_some());
+}
+
+#[test]
+fn circuit_breaker_with_state_mut() {
+    let clock = ManualClock::new();
+    let circuit_breaker = CircuitBreaker {
+        clock: clock.clone_box(),
+        threshold: 3,
+        timeout: TimestampDelta::from_secs(5),
+    };
+    let mut cb = StateMachine::new(circuit_breaker, states::Closed::default());
+
+    // Forgive the failures without a transition.
+    cb.consume(inputs::Fail);
+    cb.consume(inputs::Fail);
+    assert_eq!(
+        cb.with_state_mut(|closed: &mut states::Closed| closed.count = 0),
+        Some(())
+    );
+    cb.consume(inputs::Fail);
+    cb.consume(inputs::Fail);
+    cb.consume(inputs::Fail);
+    assert_eq!(cb.state().closed().count, 3);
+
+    cb.consume(inputs::Fail);
+    assert!(cb.state().is_open());
+    assert_eq!(
+        cb.with_state_mut(|closed: &mut states::Closed| closed.count),
+        None
+    );
+}
+
+#[test]
+fn circuit_breaker_graph() {
+    use rust_automata::graph::Edge;
+
+    let graph = CircuitBreaker::graph();
+    assert_eq!(graph.name, "CircuitBreaker");
+    assert_eq!(graph.states, ["Failure", "Closed", "Open", "HalfOpen"]);
+    assert_eq!(graph.inputs, ["Nothing", "Success", "Fail"]);
+    assert_eq!(graph.outputs, ["Nothing"]);
+    assert_eq!(graph.transitions.len(), 7);
+    assert_eq!(
+        graph.transitions[2],
+        Edge {
+            from: 1,
+            input: 2,
+            to: 2,
+            output: 0,
+            guard: Some("!guard_below_threshold"),
+            handler: Some("handle_trip_breaker"),
+        }
+    );
+    let open = graph.state_id("Open").unwrap();
+    assert_eq!(graph.edges_from(open).count(), 2);
+}
+
+#[test]
+fn circuit_breaker_plantuml() {
+    assert_eq!(
+        CircuitBreaker::PLANTUML,
+        "@startuml
+[*] --> Closed
+state Closed
+state Open
+state HalfOpen
+Closed --> Closed : Success?\\n/ count_reset
+Closed --> Closed : Fail?\\n[below_threshold]\\n/ count_increment
+Closed --> Open : Fail?\\n[!below_threshold]\\n/ trip_breaker
+Open --> Open : [!timeout]
+Open --> HalfOpen : [timeout]
+HalfOpen --> Open : Fail?\\n/ setup_timer
+HalfOpen --> Closed : Success?
+@enduml
+"
+    );
+}
+
+#[test]
+fn circuit_breaker_dot() {
+    assert_eq!(
+        CircuitBreaker::DOT,
+        r#"digraph CircuitBreaker {
+    rankdir=LR;
+    node [shape=box, style=rounded];
+    __start [shape=point];
+    __start -> Closed;
+    Closed [penwidth=2];
+    Open;
+    HalfOpen;
+    Closed -> Closed [label="Success?\n/ count_reset", tooltip="fn handle_count_reset(&mut self, state: states::Closed, input: inputs::Success) -> states::Closed"];
+    Closed -> Closed [label="Fail?\n[below_threshold]\n/ count_increment", tooltip="fn guard_below_threshold(&self, state: &states::Closed) -> bool\nfn handle_count_increment(&mut self, state: states::Closed, input: inputs::Fail) -> states::Closed"];
+    Closed -> Open [label="Fail?\n[!below_threshold]\n/ trip_breaker", tooltip="fn guard_below_threshold(&self, state: &states::Closed) -> bool\nfn handle_trip_breaker(&mut self, state: states::Closed, input: inputs::Fail) -> states::Open"];
+    Open -> Open [label="[!timeout]", tooltip="fn guard_timeout(&self, state: &states::Open) -> bool"];
+    Open -> HalfOpen [label="[timeout]", tooltip="fn guard_timeout(&self, state: &states::Open) -> bool"];
+    HalfOpen -> Open [label="Fail?\n/ setup_timer", tooltip="fn handle_setup_timer(&mut self, state: states::HalfOpen, input: inputs::Fail) -> states::Open"];
+    HalfOpen -> Closed [label="Success?"];
+}
+"#
+    );
+}
+
+#[test]
+fn circuit_breaker_scxml() {
+    assert_eq!(
+        CircuitBreaker::SCXML,
+        r##"<?xml version="1.0" encoding="UTF-8"?>
+<scxml xmlns="http://www.w3.org/2005/07/scxml" version="1.0" name="CircuitBreaker" initial="Closed">
+  <state id="Closed">
+    <transition event="Success" target="Closed"/>
+    <transition event="Fail" cond="guard_below_threshold" target="Closed"/>
+    <transition event="Fail" cond="!guard_below_threshold" target="Open"/>
+  </state>
+  <state id="Open">
+    <transition cond="!guard_timeout" target="Open"/>
+    <transition cond="guard_timeout" target="HalfOpen"/>
+  </state>
+  <state id="HalfOpen">
+    <transition event="Fail" target="Open"/>
+    <transition event="Success" target="Closed"/>
+  </state>
+</scxml>
+"##
+    );
+}
+
+#[test]
+fn circuit_breaker_html() {
+    let html = CircuitBreaker::HTML;
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains(r#"<li><span data-k="state:Closed">Closed</span> (initial)</li>"#));
+    // Every guard of an expression can be highlighted on its own.
+    assert!(html.contains(r#"<td>!<span data-k="guard:timeout">timeout</span></td>"#));
+    assert!(html.contains(r#"<span data-k="handler:trip_breaker">trip_breaker</span>"#));
+}
+
+#[test]
+fn circuit_breaker_csv() {
+    assert_eq!(
+        CircuitBreaker::CSV,
+        "state,Success,Fail,(no input)
+Closed,Closed,Closed [below_threshold] | Open [!below_threshold],
+Open,,,Open [!timeout] | HalfOpen [timeout]
+HalfOpen,Closed,Open,
+"
+    );
+}
+
+#[test]
+fn circuit_breaker_no_deadlocks() {
+    let graph = CircuitBreaker::graph();
+    assert_eq!(analysis::reachable_states(graph).len(), 3);
+    assert!(analysis::dead_states(graph).is_empty());
+    assert_eq!(analysis::strongly_connected_components(graph).len(), 1);
+}
+
+#[test]
+fn circuit_breaker_dynamic() {
+    use rust_automata::dynamic::{DynamicError, DynamicMachine};
+    use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    // The same definition as above, without the counters and timers.
+    let definition = r"
+        inputs(inputs::Success, inputs::Fail),
+        states(states::Closed, states::Open, states::HalfOpen),
+        outputs(),
+        transitions(
+            [hot] (states::Closed, inputs::Success) -> (states::Closed) = handle_count_reset,
+            (states::Closed, inputs::Fail)    -> (states::Closed) :  guard_below_threshold = handle_count_increment,
+            (states::Closed, inputs::Fail)    -> (states::Open)   :  ! guard_below_threshold,
+
+            (states::Open) -> (states::Open)     :  !guard_timeout,
+            (states::Open) -> (states::HalfOpen) :  guard_timeout,
+
+            (states::HalfOpen, inputs::Fail)    -> (states::Open),
+            (states::HalfOpen, inputs::Success) -> (states::Closed)  // Resumes normal operation
+        ),
+        derive(Debug)
+    ";
+    let mut cb = DynamicMachine::parse(definition).unwrap();
+    assert_eq!(cb.state_name(), "Closed");
+    assert_eq!(
+        cb.consume("Fail"),
+        Err(DynamicError::MissingGuard(
+            "guard_below_threshold".to_string()
+        ))
+    );
+
+    let count = Arc::new(AtomicU32::new(0));
+    let timeout = Arc::new(AtomicBool::new(false));
+    let c = Arc::clone(&count);
+    cb.register_guard("guard_below_threshold", move || {
+        c.load(Ordering::SeqCst) < 1
+    });
+    let c = Arc::clone(&count);
+    cb.register_handler("handle_count_increment", move || {
+        c.fetch_add(1, Ordering::SeqCst);
+    });
+    let c = Arc::clone(&count);
+    cb.register_handler("handle_count_reset", move || c.store(0, Ordering::SeqCst));
+    let t = Arc::clone(&timeout);
+    cb.register_guard("guard_timeout", move || t.load(Ordering::SeqCst));
+
+    assert_eq!(cb.consume("Fail"), Ok("Nothing"));
+    assert_eq!(cb.consume("Fail"), Ok("Nothing"));
+    assert_eq!(cb.state_name(), "Open");
+    assert!(cb.available_input_names().is_empty());
+    assert!(cb.consume("Success").is_err());
+    cb.step().unwrap();
+    assert_eq!(cb.state_name(), "Open");
+    timeout.store(true, Ordering::SeqCst);
+    cb.step().unwrap();
+    assert_eq!(cb.state_name(), "HalfOpen");
+
+    // Reopen the breaker on success as well, without restarting.
+    cb.reload(&definition.replace(
+        "(states::HalfOpen, inputs::Success) -> (states::Closed)",
+        "(states::HalfOpen, inputs::Success) -> (states::Open)",
+    ))
+    .unwrap();
+    cb.consume("Success").unwrap();
+    assert_eq!(cb.state_name(), "Open");
+
+    assert!(cb.reload("states(Closed)").is_err());
+    assert!(DynamicMachine::parse("states(A), transitions((A, I) -> (A))").is_err());
+    assert!(DynamicMachine::parse("states(A) transitions()").is_err());
+}
+
+#[tokio::test(start_paused = true)]
+async fn circuit_breaker_tokio_clock() {
+    use rust_automata::clock::TokioClock;
+
+    let circuit_breaker = CircuitBreaker {
+        clock: Box::new(TokioClock::new()),
+        threshold: 0,
+        timeout: TimestampDelta::from_secs(5),
+    };
+    let mut cb = StateMachine::new(circuit_breaker, states::Closed::default());
+    cb.consume(inputs::Fail);
+    assert!(cb.state().is_open());
+
+    tokio::time::sleep(std::time::Duration::from_secs(4)).await;
+    assert_eq!(cb.advance_time(), 0);
+    assert!(cb.state().is_open());
+    tokio::time::advance(std::time::Duration::from_secs(1)).await;
+    assert_eq!(cb.advance_time(), 1);
+    assert!(cb.state().is_half_open());
+}
+
+#[test]
+fn circuit_breaker_clock_observer() {
+    use rust_automata::shared::SharedStateMachine;
+
+    let clock = ManualClock::new();
+    let circuit_breaker = CircuitBreaker {
+        clock: clock.clone_box(),
+        threshold: 0,
+        timeout: TimestampDelta::from_secs(5),
+    };
+    let cb = SharedStateMachine::new(StateMachine::new(
+        circuit_breaker,
+        states::Closed::default(),
+    ));
+    let observed = cb.clone();
+    clock.on_advance(move |_| {
+        observed.with(|sm| sm.advance_time()).unwrap();
+    });
+
+    cb.consume(inputs::Fail).unwrap();
+    assert_eq!(cb.state_name(), Ok("Open"));
+    clock.advance_by(TimestampDelta::from_secs(3));
+    assert_eq!(cb.state_name(), Ok("Open"));
+    // No need to step the machine after the timeout.
+    clock.advance_to(rust_automata::timestamp::Timestamp::from_secs(5));
+    assert_eq!(cb.state_name(), Ok("HalfOpen"));
+}
+
+#[test]
+fn circuit_breaker_skewed_clock() {
+    use rust_automata::clock::SimulationClock;
+
+    let clock = ManualClock::new();
+    let breaker = |clock: Box<dyn Clock>| {
+        let circuit_breaker = CircuitBreaker {
+            clock,
+            threshold: 0,
+            timeout: TimestampDelta::from_secs(5),
+        };
+        let mut cb = StateMachine::new(circuit_breaker, states::Closed::default());
+        cb.consume(inputs::Fail);
+        cb
+    };
+    let mut exact = breaker(clock.clone_box());
+    // A component whose clock runs 25% fast.
+    let mut fast = breaker(Box::new(
+        SimulationClock::new(clock.clone_box()).with_drift(0.25),
+    ));
+
+    clock.advance_by(TimestampDelta::from_secs(4));
+    assert_eq!(exact.advance_time(), 0);
+    assert_eq!(fast.advance_time(), 1);
+    assert!(exact.state().is_open());
+    assert!(fast.state().is_half_open());
+}
+
+#[test]
+fn circuit_breaker_advance_time() {
+    let clock = ManualClock::new();
+    let circuit_breaker = CircuitBreaker {
+        clock: clock.clone_box(),
+        threshold: 0,
+        timeout: TimestampDelta::from_secs(5),
+    };
+    let mut cb = StateMachine::new(circuit_breaker, states::Closed::default());
+    cb.consume(inputs::Fail);
+
+    // The timer did not expire, so nothing fires.
+    assert_eq!(cb.advance_time(), 0);
+    assert!(cb.state().is_open());
+
+    clock.advance_by(TimestampDelta::from_secs(5));
+    assert_eq!(cb.advance_time(), 1);
+    assert!(cb.state().is_half_open());
+}
+
+#[test]
+fn circuit_breaker_scheduler() {
+    use rust_automata::scheduler::Scheduler;
+    use rust_automata::timestamp::Timestamp;
+
+    let mut scheduler = Scheduler::new(ManualClock::new());
+    let breakers = [5, 8].map(|secs| {
+        let circuit_breaker = CircuitBreaker {
+            clock: scheduler.clock().clone_box(),
+            threshold: 0,
+            timeout: TimestampDelta::from_secs(secs),
+        };
+        let handle = scheduler.bus().register(StateMachine::new(
+            circuit_breaker,
+            states::Closed::default(),
+        ));
+        scheduler.bus().subscribe::<_, inputs::Fail>(handle);
+        scheduler.schedule(handle);
+        handle
+    });
+    assert_eq!(scheduler.next_deadline(), None);
+
+    // Trip both breakers.
+    assert_eq!(scheduler.bus().publish(inputs::Fail), Ok(2));
+    assert_eq!(scheduler.run_enabled(), Ok(0));
+
+    assert_eq!(scheduler.advance(), Ok(Some(Timestamp::from_secs(5))));
+    assert!(scheduler.bus().machine(breakers[0]).state().is_half_open());
+    assert!(scheduler.bus().machine(breakers[1]).state().is_open());
+
+    assert_eq!(scheduler.advance(), Ok(Some(Timestamp::from_secs(8))));
+    assert!(scheduler.bus().machine(breakers[1]).state().is_half_open());
+    assert_eq!(scheduler.advance(), Ok(None));
+}
+
+#[test]
+fn circuit_breaker_scheduler_paused_timer() {
+    use rust_automata::scheduler::Scheduler;
+
+    let mut scheduler = Scheduler::new(ManualClock::new());
+    let circuit_breaker = CircuitBreaker {
+        clock: scheduler.clock().clone_box(),
+        threshold: 0,
+        timeout: TimestampDelta::from_secs(5),
+    };
+    let mut cb = StateMachine::new(circuit_breaker, states::Closed::default());
+    cb.consume(inputs::Fail);
+    cb.with_state_mut(|open: &mut states::Open| open.timer.pause());
+    let handle = scheduler.bus().register(cb);
+    scheduler.schedule(handle);
+
+    // The paused timer never times out, so there is nothing to advance to.
+    assert_eq!(scheduler.next_deadline(), None);
+    assert_eq!(scheduler.advance(), Ok(None));
+    assert!(scheduler.bus().machine(handle).state().is_open());
+}
+
 /// An example of a web-server route that uses the circuit breaker.
 ///
 /// See `faulty_route` test for example usage.
@@ -0,0 +1,98 @@
+//! An automatic door that closes itself.
+//!
+//! Pushing the door opens it, and the handler generates a `Close` input,
+//! so the door closes again when the queue is processed with the generated inputs.
+//!
+//! Check out the [`Door`] struct for the state machine diagram.
+use rust_automata::*;
+
+/// All the states of the door.
+pub mod states {
+    #[derive(Default, Debug)]
+    pub struct Closed;
+    #[derive(Default, Debug)]
+    pub struct Open;
+}
+
+/// All the inputs of the door.
+pub mod inputs {
+    #[derive(Default, Debug)]
+    pub struct Push;
+    #[derive(Default, Debug)]
+    pub struct Close;
+}
+
+/// All the outputs of the door.
+pub mod outputs {
+    #[derive(Default, Debug)]
+    pub struct Beep;
+}
+
+#[state_machine(
+    inputs(inputs::Push, inputs::Close),
+    states(states::Closed, states::Open),
+    outputs(outputs::Beep),
+    transitions(
+        (states::Closed, inputs::Push)  -> (states::Open) = handle_open,
+        (states::Open,   inputs::Close) -> (states::Closed, outputs::Beep)
+    ),
+    derive(Debug)
+)]
+#[derive(Default)]
+pub struct Door {
+    generated: Vec<inputs::Close>,
+}
+
+impl Door {
+    fn handle_open(&mut self, _: states::Closed, _: inputs::Push) -> states::Open {
+        self.generated.push(inputs::Close);
+        states::Open
+    }
+}
+
+impl GeneratesInputs for Door {
+    fn take_generated(&mut self, f: &mut dyn FnMut(Self::Input, i32)) {
+        for close in self.generated.drain(..) {
+            f(close.into(), 0);
+        }
+    }
+}
+
+#[test]
+fn door_closes_itself() {
+    let mut door = StateMachine::new(Door::default(), states::Closed);
+    door.enqueue(inputs::Push);
+    let outputs = door.process_queue_with_generated().unwrap();
+    assert_eq!(outputs.len(), 1);
+    assert!(outputs[0].is_beep());
+    assert!(door.state().is_closed());
+    assert_eq!(door.queue_len(), 0);
+}
+
+#[test]
+fn door_generated_inputs_wait() {
+    let mut door = StateMachine::new(Door::default(), states::Closed);
+    door.enqueue(inputs::Push);
+    // The plain queue leaves the generated input with the handler.
+    assert!(door.process_queue().unwrap().is_empty());
+    assert!(door.state().is_open());
+
+    assert_eq!(door.process_queue_with_generated().unwrap().len(), 1);
+    assert!(door.state().is_closed());
+}
+
+#[test]
+fn door_rejected_input() {
+    let mut door = StateMachine::new(Door::default(), states::Closed);
+    door.enqueue(inputs::Push);
+    door.enqueue(inputs::Push);
+    door.enqueue(inputs::Close);
+
+    // The open door cannot be pushed again, the input behind it stays queued.
+    let (index, outputs) = door.process_queue().unwrap_err();
+    assert_eq!(index, 1);
+    assert!(outputs.is_empty());
+    assert_eq!(door.queue_len(), 1);
+    assert_eq!(door.process_queue().unwrap().len(), 1);
+    assert!(door.state().is_closed());
+}
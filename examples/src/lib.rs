@@ -1,4 +1,6 @@
 pub mod circuit_breaker;
+pub mod door;
 pub mod lock;
 pub mod simple;
 pub mod vikings;
+pub mod workflow;
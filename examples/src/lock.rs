@@ -41,8 +41,10 @@ pub mod outputs {
         (states::Closed, inputs::Key)   -> (states::Open,   outputs::Click),
         (states::Open,   inputs::Drill) -> (states::Broken),
         (states::Closed, inputs::Drill) -> (states::Broken)
-    )
+    ),
+    diagram(theme = dark, rankdir = TB, hide_outputs, show_failure)
 )]
+#[derive(Default)]
 pub struct Lock;
 
 #[test]
@@ -63,3 +65,388 @@ fn locking_replay() {
     assert!(!lock.can_consume::<inputs::Key>());
     assert!(!lock.can_consume::<inputs::Drill>());
 }
+
+#[test]
+fn locking_queue() {
+    let mut lock = StateMachine::new(Lock, states::Open);
+    lock.enqueue(inputs::Key);
+    lock.enqueue(inputs::Key);
+    lock.enqueue(inputs::Drill);
+    assert_eq!(lock.queue_len(), 3);
+    // Nothing happens until the queue is processed.
+    assert!(lock.state().is_open());
+
+    let Ok(outputs) = lock.process_queue() else {
+        panic!("an input was rejected");
+    };
+    assert_eq!(outputs.len(), 2);
+    assert!(outputs.iter().all(|o| o.is_click()));
+    assert_eq!(lock.queue_len(), 0);
+    assert!(lock.state().is_broken());
+}
+
+#[test]
+fn locking_queue_priority() {
+    let mut lock = StateMachine::new(Lock, states::Open);
+    lock.enqueue(inputs::Drill);
+    lock.enqueue_with_priority(inputs::Key, 1);
+    lock.enqueue_with_priority(inputs::Key, 1);
+    assert_eq!(lock.queue_len(), 3);
+
+    // Both keys are turned before the drill breaks the lock.
+    let Ok(outputs) = lock.process_queue() else {
+        panic!("an input was rejected");
+    };
+    assert_eq!(outputs.len(), 2);
+    assert!(lock.state().is_broken());
+}
+
+#[test]
+fn locking_pump() {
+    use std::collections::VecDeque;
+
+    type Input = <Lock as StateMachineImpl>::Input;
+    let mut lock = StateMachine::new(Lock, states::Open);
+    let mut buffer: VecDeque<Input> = VecDeque::new();
+    buffer.push_back(inputs::Key.into());
+    buffer.push_back(inputs::Key.into());
+    assert_eq!(lock.pump(&mut buffer), Ok(2));
+    assert!(lock.state().is_open());
+
+    // The next tick finds nothing to do.
+    assert_eq!(lock.pump(&mut buffer), Ok(0));
+
+    buffer.extend([inputs::Drill.into(), inputs::Key.into(), inputs::Key.into()]);
+    let rejected = lock.pump(&mut buffer).unwrap_err();
+    assert_eq!((rejected.index, rejected.input), (1, "Key"));
+    assert!(lock.state().is_broken());
+    assert_eq!(buffer.len(), 1);
+
+    let (tx, mut rx) = std::sync::mpsc::channel();
+    tx.send(inputs::Drill).unwrap();
+    let mut lock = StateMachine::new(Lock, states::Open);
+    assert_eq!(lock.pump(&mut rx), Ok(1));
+}
+
+#[test]
+fn locking_output_sink() {
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut lock = StateMachine::new(Lock, states::Open);
+    lock.set_output_sink(move |output| tx.send(output).unwrap());
+    lock.consume(inputs::Key);
+    lock.consume_iter([inputs::Key]).unwrap();
+    lock.consume(inputs::Drill);
+
+    // Only the clicks arrive, the drill produces nothing.
+    let outputs: Vec<_> = rx.try_iter().collect();
+    assert_eq!(outputs.len(), 2);
+    assert!(outputs.iter().all(|o| o.is_click()));
+
+    lock.clear_output_sink();
+    assert!(rx.try_recv().is_err());
+}
+
+#[test]
+fn locking_available_inputs() {
+    let mut lock = StateMachine::new(Lock, states::Open);
+    assert_eq!(
+        lock.available_inputs(),
+        vec![inputs::Key::enum_id(), inputs::Drill::enum_id()]
+    );
+    let transitions = lock.available_transitions();
+    assert_eq!(transitions.len(), 2);
+    assert_eq!(transitions[0].input, inputs::Key::enum_id());
+    assert_eq!(transitions[0].to_state, states::Closed::enum_id());
+    assert_eq!(transitions[0].output, outputs::Click::enum_id());
+
+    lock.consume(inputs::Drill);
+    assert!(lock.available_inputs().is_empty());
+}
+
+#[test]
+fn locking_names() {
+    let mut lock = StateMachine::new(Lock, states::Open);
+    assert_eq!(lock.state_name(), "Open");
+    let t = lock.available_transitions()[0];
+    assert_eq!(StateMachine::<Lock>::input_name(t.input), "Key");
+    assert_eq!(StateMachine::<Lock>::output_name(t.output), "Click");
+    lock.consume(inputs::Key);
+    assert_eq!(lock.state_name(), "Closed");
+}
+
+#[test]
+fn locking_all_symbols() {
+    let names: Vec<_> = Lock::ALL_STATES.iter().map(|(_, name)| *name).collect();
+    assert_eq!(names, ["Open", "Closed", "Broken"]);
+    assert_eq!(Lock::ALL_INPUTS[1], (inputs::Drill::enum_id(), "Drill"));
+    assert_eq!(Lock::ALL_OUTPUTS, [(outputs::Click::enum_id(), "Click")]);
+
+    // Coverage of the states reached by a walk through the machine.
+    let mut lock = StateMachine::new(Lock, states::Open);
+    let mut visited = vec![lock.state().enum_id()];
+    for input in [inputs::Key, inputs::Key] {
+        lock.consume(input);
+        visited.push(lock.state().enum_id());
+    }
+    let unvisited: Vec<_> = Lock::ALL_STATES
+        .iter()
+        .filter(|(id, _)| !visited.contains(id))
+        .map(|(_, name)| *name)
+        .collect();
+    assert_eq!(unvisited, ["Broken"]);
+}
+
+#[test]
+fn locking_state_ids() {
+    use std::collections::HashMap;
+
+    let mut lock = StateMachine::new(Lock, states::Open);
+    let mut visits: HashMap<StateId<Lock>, usize> = HashMap::new();
+    for _ in 0..3 {
+        *visits.entry(lock.state_id()).or_default() += 1;
+        lock.consume(inputs::Key);
+    }
+    assert_eq!(visits[&states::Open::enum_id()], 2);
+    assert_eq!(visits[&states::Closed::enum_id()], 1);
+
+    let id: StateId<Lock> = lock.state_id();
+    assert_eq!(id.name(), "Closed");
+    assert_eq!(id.to_string(), "Closed");
+    let input: InputId<Lock> = inputs::Drill::enum_id();
+    assert_eq!(input.name(), "Drill");
+}
+
+#[test]
+fn locking_default() {
+    let mut lock = StateMachine::<Lock>::new_default();
+    assert!(lock.state().is_open());
+    lock.consume(inputs::Key);
+
+    let lock: StateMachine<Lock> = Default::default();
+    assert!(lock.state().is_open());
+}
+
+#[test]
+fn locking_is_in() {
+    fn is_broken<T>(sm: &StateMachine<T>) -> bool
+    where
+        T: StateMachineImpl,
+        states::Broken: Enumerated<T::State>,
+    {
+        sm.is_in::<states::Broken>()
+    }
+
+    let mut lock = StateMachine::new(Lock, states::Open);
+    assert!(lock.is_in::<states::Open>());
+    assert!(!is_broken(&lock));
+    lock.consume(inputs::Drill);
+    assert!(is_broken(&lock));
+}
+
+#[test]
+fn locking_relay_opt() {
+    let mut lock = StateMachine::new(Lock, states::Open);
+    let sound: Option<outputs::Click> = lock.relay_opt(inputs::Key);
+    assert!(sound.is_some());
+    // Drilling does not make a click.
+    let sound: Option<outputs::Click> = lock.relay_opt(inputs::Drill);
+    assert!(sound.is_none());
+    assert!(lock.state().is_broken());
+}
+
+#[test]
+fn locking_shared() {
+    use rust_automata::shared::{PoisonedLock, SharedStateMachine};
+
+    let lock = SharedStateMachine::new(StateMachine::new(Lock, states::Open));
+    let threads: Vec<_> = (0..4)
+        .map(|_| {
+            let lock = lock.clone();
+            std::thread::spawn(move || {
+                for _ in 0..10 {
+                    let _: outputs::Click = lock.relay(inputs::Key).unwrap();
+                }
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    assert_eq!(lock.state_name(), Ok("Open"));
+
+    // A panic during an invalid transition poisons the lock.
+    lock.consume(inputs::Drill).unwrap();
+    assert_eq!(lock.can_consume::<inputs::Key>(), Ok(false));
+    let poisoner = lock.clone();
+    assert!(std::thread::spawn(move || poisoner.consume(inputs::Key))
+        .join()
+        .is_err());
+    assert_eq!(lock.state_name(), Err(PoisonedLock));
+}
+
+#[test]
+fn locking_batch() {
+    let mut lock = StateMachine::new(Lock, states::Open);
+    let mut outputs = Vec::new();
+    assert!(lock
+        .relay_iter([inputs::Key, inputs::Key], &mut outputs)
+        .is_ok());
+    assert_eq!(outputs.len(), 2);
+    assert!(lock.state().is_open());
+
+    // The second drill is rejected, since the lock is already broken.
+    let err = lock
+        .consume_iter([inputs::Drill, inputs::Drill, inputs::Drill])
+        .unwrap_err();
+    assert_eq!(err.index, 1);
+    assert_eq!(err.state, "Broken");
+    assert_eq!(err.input, "Drill");
+    assert!(lock.state().is_broken());
+}
+
+#[tokio::test]
+async fn locking_async() {
+    use rust_automata::actor::AsyncStateMachine;
+    let mut lock = AsyncStateMachine::spawn(StateMachine::new(Lock, states::Open), 4);
+    lock.send(inputs::Key).await.unwrap();
+    assert!(lock.recv().await.unwrap().is_click());
+
+    let sender = lock.sender();
+    sender.send(inputs::Key.into()).await.unwrap();
+    assert!(lock.recv().await.unwrap().is_click());
+
+    // Drilling produces nothing, and the second drill is rejected without killing the task.
+    lock.send(inputs::Drill).await.unwrap();
+    lock.send(inputs::Drill).await.unwrap();
+
+    let lock = lock.shutdown().await;
+    assert!(lock.state().is_broken());
+}
+
+#[test]
+fn locking_stream() {
+    use futures::{executor::block_on, SinkExt, StreamExt};
+    use rust_automata::stream::StateMachineStream;
+
+    let mut lock = StateMachineStream::new(StateMachine::new(Lock, states::Open));
+    block_on(async {
+        lock.send(inputs::Key).await.unwrap();
+        lock.send(inputs::Key).await.unwrap();
+        lock.send(inputs::Drill).await.unwrap();
+        // The lock is broken, the key is rejected.
+        assert!(lock.send(inputs::Key).await.is_err());
+        SinkExt::<inputs::Key>::close(&mut lock).await.unwrap();
+
+        let outputs: Vec<_> = (&mut lock).collect().await;
+        assert_eq!(outputs.len(), 2);
+    });
+    assert!(lock.machine().state().is_broken());
+}
+
+#[test]
+fn locking_random_walk() {
+    use rand::{rngs::StdRng, SeedableRng};
+    let mut rng = StdRng::seed_from_u64(42);
+    let mut lock = StateMachine::new(Lock, states::Open);
+    let trace = lock.simulate_random(&mut rng, 100);
+    // Every walk eventually breaks the lock, after which no input is enabled.
+    assert!(lock.state().is_broken());
+    assert!(trace.len() < 100);
+    assert_eq!(trace.last().unwrap().to_state, states::Broken::enum_id());
+    for (prev, next) in trace.iter().zip(trace.iter().skip(1)) {
+        assert_eq!(prev.to_state, next.from_state);
+    }
+}
+
+#[test]
+fn locking_deadlocks() {
+    let graph = Lock::graph();
+    // Once broken, the lock stays broken.
+    assert_eq!(
+        analysis::dead_states(graph),
+        [states::Broken::enum_id().index()]
+    );
+    assert_eq!(
+        analysis::strongly_connected_components(graph),
+        [
+            vec![states::Broken::enum_id().index()],
+            vec![
+                states::Open::enum_id().index(),
+                states::Closed::enum_id().index()
+            ]
+        ]
+    );
+}
+
+#[test]
+fn locking_diagram_options() {
+    assert_eq!(
+        Lock::DOT,
+        r##"digraph Lock {
+    rankdir=TB;
+    bgcolor="#1e1e1e";
+    node [color=white, fontcolor=white];
+    edge [color=white, fontcolor=white];
+    node [shape=box, style=rounded];
+    __start [shape=point];
+    __start -> Open;
+    Open [penwidth=2];
+    Closed;
+    Broken;
+    Open -> Closed [label="Key?"];
+    Closed -> Open [label="Key?"];
+    Open -> Broken [label="Drill?"];
+    Closed -> Broken [label="Drill?"];
+    Failure [color=red, fontcolor=red];
+    Broken -> Failure [label="Key?\nDrill?", style=dashed, color=red];
+}
+"##
+    );
+}
+
+#[test]
+fn locking_scxml() {
+    assert!(Lock::SCXML.contains(
+        r##"    <transition event="Key" target="Closed">
+      <send event="Click" target="#_parent"/>
+    </transition>"##
+    ));
+    assert!(Lock::SCXML.contains(r#"<state id="Broken"/>"#));
+}
+
+#[test]
+fn locking_d2() {
+    assert_eq!(
+        Lock::D2,
+        r#"direction: down
+vars: {d2-config: {theme-id: 200}}
+__start: "" {shape: circle; width: 12; height: 12}
+__start -> Open
+Open: {style.bold: true}
+Closed
+Broken
+Open -> Closed: "Key?"
+Closed -> Open: "Key?"
+Open -> Broken: "Drill?"
+Closed -> Broken: "Drill?"
+Broken -> Failure: "Key?\nDrill?" {style.stroke-dash: 3}
+"#
+    );
+}
+
+#[test]
+fn locking_diagram_consts() {
+    assert_eq!(internal_Lock::LOCK_DOT, Lock::DOT);
+    assert!(
+        internal_Lock::LOCK_MERMAID.starts_with("%%{init: {'theme': 'dark'}}%%\nstateDiagram-v2\n")
+    );
+}
+
+#[test]
+fn locking_drawio() {
+    let xml = Lock::DRAWIO;
+    assert!(xml.starts_with("<mxfile>\n  <diagram name=\"Lock\" id=\"Lock\">\n"));
+    assert!(xml.contains(r#"<mxCell id="Open" value="Open" style="rounded=1;whiteSpace=wrap;html=0;fontStyle=1;" vertex="1" parent="1">"#));
+    assert!(xml.contains(r#"value="Drill?" style="edgeStyle=orthogonalEdgeStyle;curved=1;html=0;" edge="1" parent="1" source="Open" target="Broken">"#));
+    assert!(xml.contains(r#"<mxCell id="failure_Broken" value="Key?&#xa;Drill?""#));
+}
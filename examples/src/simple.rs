@@ -12,8 +12,9 @@ use rust_automata::*;
         (S3) -> (S2, O1),
     ),
     generate_structs(true),
-    derive(Debug, PartialEq),
+    derive(Debug, PartialEq, Clone),
 )]
+#[derive(Clone)]
 pub struct Example;
 
 #[test]
@@ -32,3 +33,314 @@ fn simple_example() {
     assert!(m.state().is_s2());
     assert_eq!(output, O1);
 }
+
+#[test]
+fn simple_fire_wrapped_symbols() {
+    let mut m = StateMachine::new(Example, S1);
+    let output = m.fire(I1.into());
+    assert!(output.is_o1());
+    assert!(m.state().is_s2());
+}
+
+#[test]
+fn simple_invalid_transition_leaves_failure() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    let mut m = StateMachine::new(Example, S1);
+    assert!(catch_unwind(AssertUnwindSafe(|| m.consume(I2))).is_err());
+    assert!(m.state().is_failure());
+}
+
+#[test]
+fn simple_consume_slice() {
+    let mut m = StateMachine::new(Example, S1);
+    m.consume_slice(&[I1.into(), I2.into(), I1.into()]);
+    assert!(m.state().is_s1());
+
+    let outputs = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let sink = outputs.clone();
+    m.set_output_sink(move |output| sink.lock().unwrap().push(output));
+    m.consume_slice(&[I1.into(), I2.into()]);
+    assert!(m.state().is_s3());
+    assert_eq!(*outputs.lock().unwrap(), [O1.into(), O2.into()]);
+
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    m.clear_output_sink();
+    let inputs = [I1.into(), I2.into()];
+    assert!(catch_unwind(AssertUnwindSafe(|| m.consume_slice(&inputs))).is_err());
+    assert!(m.state().is_failure());
+}
+
+#[test]
+fn simple_symbol_names() {
+    let m = StateMachine::new(Example, S1);
+    assert_eq!(m.state().to_string(), "S1");
+    assert_eq!(m.state().enum_id().name(), "S1");
+    let input: <Example as StateMachineImpl>::Input = I2.into();
+    assert_eq!(input.to_string(), "I2");
+    assert!(input.maybe_i1().is_none());
+    assert_eq!(input.i2(), &I2);
+}
+
+#[test]
+#[should_panic(expected = "No such symbol like s2")]
+fn simple_missing_symbol() {
+    let m = StateMachine::new(Example, S1);
+    m.state().s2();
+}
+
+#[test]
+fn simple_no_deadlocks() {
+    let graph = Example::graph();
+    assert_eq!(analysis::reachable_states(graph), [1, 2, 3]);
+    assert!(analysis::dead_states(graph).is_empty());
+    assert_eq!(
+        analysis::strongly_connected_components(graph),
+        [vec![1, 2, 3]]
+    );
+}
+
+#[test]
+fn simple_can_transition() {
+    let mut m = StateMachine::new(Example, S1);
+    assert!(m.can_consume::<I1>());
+    assert!(!m.can_consume::<I2>());
+    assert_eq!(Example.can_transition(m.state(), EnumId::new(99)), None);
+}
+
+#[test]
+#[should_panic(expected = "EnumId out of range of u16")]
+fn simple_enum_id_out_of_range() {
+    let _: StateId<Example> = EnumId::new(u16::MAX as usize + 1);
+}
+
+#[test]
+fn simple_compact_enums() {
+    use std::mem::size_of;
+    assert_eq!(size_of::<<Example as StateMachineImpl>::State>(), 1);
+    assert_eq!(size_of::<<Example as StateMachineImpl>::Input>(), 1);
+    assert_eq!(size_of::<StateId<Example>>(), 2);
+}
+
+#[test]
+fn simple_machine_size() {
+    use std::mem::size_of;
+    // The queue, hooks, undo history and checkpoints are boxed, so they cost a single pointer until used.
+    assert_eq!(size_of::<StateMachine<Example>>(), 2 * size_of::<usize>());
+}
+
+#[test]
+fn simple_undo() {
+    let mut m = StateMachine::new(Example, S1);
+    assert!(!m.undo());
+    m.enable_undo(2);
+    m.consume(I1);
+    m.consume(I2);
+    let _: O1 = m.produce();
+    assert!(m.state().is_s2());
+    assert_eq!(m.undo_len(), 2);
+
+    assert!(m.undo());
+    assert!(m.state().is_s3());
+    assert!(m.undo());
+    assert!(m.state().is_s2());
+    // Only the last two transitions are remembered.
+    assert!(!m.undo());
+    assert!(m.state().is_s2());
+}
+
+#[test]
+fn simple_undo_depth_zero() {
+    let mut m = StateMachine::new(Example, S1);
+    m.enable_undo(0);
+    m.consume(I1);
+    m.consume(I2);
+    assert_eq!(m.undo_len(), 0);
+    assert!(!m.undo());
+    assert!(m.state().is_s3());
+}
+
+#[test]
+fn simple_builder() {
+    use std::sync::{Arc, Mutex};
+
+    let trace = Arc::new(Mutex::new(Vec::new()));
+    let observed = Arc::clone(&trace);
+    let mut m = StateMachine::builder()
+        .initial(S1)
+        .data(Example)
+        .observer(move |t| observed.lock().unwrap().push(t.to_state.name()))
+        .history(1)
+        .build();
+    m.consume(I1);
+    m.consume(I2);
+    assert_eq!(*trace.lock().unwrap(), ["S2", "S3"]);
+
+    assert!(m.undo());
+    assert!(m.state().is_s2());
+    assert!(!m.undo());
+}
+
+#[test]
+fn simple_recorder() {
+    use rust_automata::recorder::Recorder;
+
+    let mut recorder = Recorder::new(StateMachine::new(Example, S1), 2);
+    recorder.fire(I1).unwrap();
+    recorder.fire(I2).unwrap();
+    recorder.fire(I1).unwrap();
+    recorder.fire(I1).unwrap();
+    recorder.fire(I2).unwrap();
+    // Rejected inputs are not recorded.
+    assert!(recorder.fire(I2).is_err());
+    assert_eq!(recorder.len(), 5);
+    assert_eq!(recorder.transitions()[2].to_state.name(), "S1");
+
+    assert!(recorder.seek(0).unwrap().state().is_s1());
+    assert!(recorder.seek(2).unwrap().state().is_s3());
+    assert!(recorder.seek(3).unwrap().state().is_s1());
+    assert!(recorder.seek(5).unwrap().state().is_s3());
+    assert!(recorder.seek(6).is_none());
+
+    // A reconstructed machine continues on its own.
+    let mut m = recorder.seek(1).unwrap();
+    m.consume(I2);
+    assert!(m.state().is_s3());
+    assert!(recorder.machine().state().is_s3());
+}
+
+#[test]
+fn simple_sequence_diagram() {
+    use rust_automata::recorder::Recorder;
+
+    let mut recorder = Recorder::new(StateMachine::new(Example, S1), 1);
+    recorder.fire(I1).unwrap();
+    recorder.fire(I2).unwrap();
+    recorder.fire(I1).unwrap();
+    assert_eq!(
+        recorder.sequence_diagram(),
+        "sequenceDiagram
+    participant Environment
+    participant Example
+    Note over Example: S1
+    Environment->>Example: I1
+    Example-->>Environment: O1
+    Note over Example: S2
+    Environment->>Example: I2
+    Example-->>Environment: O2
+    Note over Example: S3
+    Environment->>Example: I1
+    Example-->>Environment: O1
+    Note over Example: S1
+"
+    );
+}
+
+/// The same states and inputs, with parallel transitions drawn as one edge.
+#[state_machine(
+    inputs(I1, I2),
+    states(S1, S2, S3),
+    outputs(O1, O2),
+    transitions(
+        (S1, I1) -> (S2, O1),
+        (S1, I2) -> (S2),
+        (S2, I2) -> (S3, O2),
+        (S3, I1) -> (S1, O1),
+    ),
+    diagram(merge_edges),
+    generate_getters(false),
+)]
+pub struct Merged;
+
+#[test]
+fn simple_merged_edges() {
+    assert_eq!(
+        Merged::DOT,
+        r#"digraph Merged {
+    rankdir=LR;
+    node [shape=box, style=rounded];
+    __start [shape=point];
+    __start -> S1;
+    S1 [penwidth=2];
+    S2;
+    S3;
+    S1 -> S2 [label="I1? O1!\nI2?"];
+    S2 -> S3 [label="I2?\nO2!"];
+    S3 -> S1 [label="I1?\nO1!"];
+}
+"#
+    );
+    assert!(Merged::PLANTUML.contains("S1 --> S2 : I1? O1!\\nI2?\n"));
+}
+
+#[cfg(test)]
+mod properties {
+    use super::*;
+    use proptest::prelude::*;
+    use rust_automata::model;
+    use rust_automata::strategy::{accepted_inputs, any_input};
+
+    type Output = <Example as StateMachineImpl>::Output;
+    type Input = <Example as StateMachineImpl>::Input;
+
+    /// A hand-written implementation of the example machine.
+    struct Manual {
+        state: u8,
+        // Make the S3 -> S1 transition silent.
+        bug: bool,
+    }
+
+    impl model::SystemUnderTest<Example> for Manual {
+        fn apply(&mut self, input: &Input) -> Output {
+            let (state, output) = match (self.state, input.is_i1()) {
+                (1, true) => (2, O1.into()),
+                (2, false) => (3, O2.into()),
+                (3, true) if self.bug => (1, Output::nothing()),
+                (3, true) => (1, O1.into()),
+                _ => panic!("not accepted by the model"),
+            };
+            self.state = state;
+            output
+        }
+    }
+
+    #[test]
+    fn manual_implementation_matches_model() {
+        let test = model::ModelTest::new(
+            || StateMachine::new(Example, S1),
+            || Manual {
+                state: 1,
+                bug: false,
+            },
+        );
+        assert!(test.run().is_ok());
+
+        let test = model::ModelTest::new(
+            || StateMachine::new(Example, S1),
+            || Manual {
+                state: 1,
+                bug: true,
+            },
+        );
+        match test.run() {
+            Err(proptest::test_runner::TestError::Fail(_, inputs)) => {
+                // Shrunk to the shortest sequence reaching the bug.
+                assert_eq!(inputs.len(), 3);
+                assert!(inputs[2].is_i1());
+            }
+            other => panic!("unexpected result {other:?}"),
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn accepted_inputs_are_accepted(inputs in accepted_inputs(|| StateMachine::new(Example, S1), 20)) {
+            let mut m = StateMachine::new(Example, S1);
+            prop_assert!(m.consume_iter(inputs).is_ok());
+        }
+
+        #[test]
+        fn any_input_is_not_nothing(input in any_input::<Example>()) {
+            prop_assert!(input.is_i1() || input.is_i2());
+        }
+    }
+}
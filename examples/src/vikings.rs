@@ -13,10 +13,10 @@ use rust_automata::{clock::*, timestamp::*, *};
 
 pub mod events {
     /// Viking tries to grab the torch
-    #[derive(Default)]
+    #[derive(Default, Clone)]
     pub struct Take;
     /// Viking puts the torch down
-    #[derive(Default)]
+    #[derive(Default, Clone)]
     pub struct Release;
 }
 
@@ -73,15 +73,15 @@ impl Viking {
 }
 
 pub mod torch_states {
-    #[derive(Default)]
+    #[derive(Default, Clone)]
     pub struct Free;
-    #[derive(Default)]
+    #[derive(Default, Clone)]
     pub struct One;
-    #[derive(Default)]
+    #[derive(Default, Clone)]
     pub struct Two;
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 enum TorchSide {
     Unsafe,
     Safe,
@@ -98,8 +98,10 @@ enum TorchSide {
         // Someone puts the torch down
         (torch_states::Two,  events::Release) -> (torch_states::One),
         (torch_states::One, events::Release)  -> (torch_states::Free) = switch_side,
-    )
+    ),
+    derive(Clone)
 )]
+#[derive(Clone)]
 pub struct Torch {
     side: TorchSide,
 }
@@ -191,3 +193,146 @@ fn vikings_successfully_cross() {
     assert!(torch.state().is_free());
     assert_eq!(torch.data().side, TorchSide::Safe);
 }
+
+#[test]
+fn viking_drain_outputs() {
+    let clock = ManualClock::new();
+    let mut viking = Viking::fsm(&clock, TimestampDelta::from_minutes(5));
+
+    let outputs: Vec<_> = viking.drain_outputs().collect();
+    assert_eq!(outputs.len(), 1);
+    assert!(outputs[0].is_take());
+    assert!(viking.state().is_crossing_to_safe());
+    assert_eq!(viking.drain_outputs().count(), 0);
+
+    // Arrives on the safe side and immediately heads back.
+    clock.advance_by(TimestampDelta::from_minutes(5));
+    let mut outputs = viking.drain_outputs();
+    assert!(outputs.next().unwrap().is_release());
+    assert!(outputs.next().unwrap().is_take());
+    assert!(outputs.next().is_none());
+    drop(outputs);
+    assert!(viking.state().is_crossing_to_unsafe());
+}
+
+#[test]
+fn torch_into_parts() {
+    let mut torch = Torch::fsm();
+    torch.consume(events::Take);
+    torch.consume(events::Release);
+    let (data, state) = torch.into_parts();
+    assert_eq!(data.side, TorchSide::Safe);
+    assert!(state.is_free());
+}
+
+#[test]
+fn torch_checkpoints() {
+    let mut torch = Torch::fsm();
+    torch.checkpoint_with_data("unsafe");
+    torch.consume(events::Take);
+    torch.checkpoint("taken");
+    torch.consume(events::Release);
+    assert_eq!(torch.data().side, TorchSide::Safe);
+
+    // Only the state is restored.
+    assert!(torch.rollback_to("taken"));
+    assert!(torch.state().is_one());
+    assert_eq!(torch.data().side, TorchSide::Safe);
+
+    // Both the state and the data are restored, any number of times.
+    for _ in 0..2 {
+        assert!(torch.rollback_to("unsafe"));
+        assert!(torch.state().is_free());
+        assert_eq!(torch.data().side, TorchSide::Unsafe);
+        torch.consume(events::Take);
+    }
+
+    assert!(torch.discard_checkpoint("unsafe"));
+    assert!(!torch.rollback_to("unsafe"));
+}
+
+#[test]
+fn vikings_no_deadlocks() {
+    assert!(analysis::dead_states(Viking::graph()).is_empty());
+    assert!(analysis::dead_states(Torch::graph()).is_empty());
+    assert_eq!(analysis::reachable_states(Torch::graph()).len(), 3);
+}
+
+#[test]
+fn vikings_pipeline() {
+    use events::*;
+    use rust_automata::pipeline::connect;
+
+    let clock = ManualClock::new();
+    let mut v_fast = Viking::fsm(&clock, TimestampDelta::from_minutes(5));
+    let mut v_slow = Viking::fsm(&clock, TimestampDelta::from_minutes(10));
+    let mut v_third = Viking::fsm(&clock, TimestampDelta::from_minutes(20));
+    let mut torch = Torch::fsm();
+    fn step(
+        viking: &mut StateMachine<Viking>,
+        torch: &mut StateMachine<Torch>,
+    ) -> Result<(), RejectedInput> {
+        connect(viking, torch)
+            .forward::<Take>()
+            .forward::<Release>()
+            .step()
+    }
+
+    assert_eq!(step(&mut v_fast, &mut torch), Ok(()));
+    assert_eq!(step(&mut v_slow, &mut torch), Ok(()));
+    assert!(torch.state().is_two());
+
+    // The torch rejects a third viking, who stays on the unsafe side.
+    let rejected = step(&mut v_third, &mut torch).unwrap_err();
+    assert_eq!((rejected.state, rejected.input), ("Two", "Take"));
+    assert!(v_third.state().is_unsafe_side());
+
+    // The vikings are still crossing.
+    assert!(step(&mut v_fast, &mut torch).is_err());
+    clock.advance_by(TimestampDelta::from_minutes(10));
+    assert_eq!(step(&mut v_fast, &mut torch), Ok(()));
+    assert_eq!(step(&mut v_slow, &mut torch), Ok(()));
+
+    assert!(v_fast.state().is_safe_side());
+    assert!(v_slow.state().is_safe_side());
+    assert!(torch.state().is_free());
+    assert_eq!(torch.data().side, TorchSide::Safe);
+}
+
+#[test]
+fn vikings_bus() {
+    use events::*;
+    use rust_automata::bus::Bus;
+
+    let clock = ManualClock::new();
+    let mut bus = Bus::new();
+    let torch = bus.register(Torch::fsm());
+    bus.subscribe::<_, Take>(torch);
+    bus.subscribe::<_, Release>(torch);
+    let vikings: Vec<_> = [5, 10]
+        .map(|minutes| {
+            let viking = bus.register(Viking::fsm(&clock, TimestampDelta::from_minutes(minutes)));
+            bus.publishes::<_, Take>(viking);
+            bus.publishes::<_, Release>(viking);
+            viking
+        })
+        .into();
+
+    for &viking in &vikings {
+        assert_eq!(bus.step(viking), Ok(1));
+    }
+    assert!(bus.machine(torch).state().is_two());
+
+    clock.advance_by(TimestampDelta::from_minutes(10));
+    for &viking in &vikings {
+        assert_eq!(bus.step(viking), Ok(1));
+        assert!(bus.machine(viking).state().is_safe_side());
+    }
+    assert!(bus.machine(torch).state().is_free());
+    assert_eq!(bus.machine(torch).data().side, TorchSide::Safe);
+
+    // A third grab of the torch is rejected.
+    bus.publish(Take).unwrap();
+    bus.publish(Take).unwrap();
+    assert_eq!(bus.publish(Take).unwrap_err().state, "Two");
+}
@@ -0,0 +1,222 @@
+//! A document review workflow that survives restarts.
+//!
+//! A document is drafted, submitted for review, and then either approved or sent
+//! back for another revision. Reviews can take days, so the machine persists its
+//! state after every transition and is restored when the service starts again.
+use rust_automata::*;
+use serde::{Deserialize, Serialize};
+
+/// All the states of the document.
+pub mod states {
+    use super::*;
+
+    #[derive(Default, Debug, PartialEq, Serialize, Deserialize)]
+    pub struct Draft {
+        pub revision: u32,
+    }
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    pub struct InReview {
+        pub revision: u32,
+    }
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    pub struct Approved {
+        pub revision: u32,
+    }
+}
+
+/// All the inputs of the workflow.
+pub mod inputs {
+    use super::*;
+
+    #[derive(Default, Debug, Serialize, Deserialize)]
+    pub struct Submit;
+    #[derive(Default, Debug, Serialize, Deserialize)]
+    pub struct Approve;
+    #[derive(Default, Debug, Serialize, Deserialize)]
+    pub struct Reject;
+}
+
+#[state_machine(
+    inputs(inputs::Submit, inputs::Approve, inputs::Reject),
+    states(states::Draft, states::InReview, states::Approved),
+    outputs(),
+    transitions(
+        (states::Draft,    inputs::Submit)  -> (states::InReview) = handle_submit,
+        (states::InReview, inputs::Approve) -> (states::Approved) = handle_approve,
+        (states::InReview, inputs::Reject)  -> (states::Draft)    = handle_reject,
+    ),
+    terminal(states::Approved),
+    groups(Open: [states::Draft, states::InReview]),
+    derive(Debug, serde::Serialize, serde::Deserialize)
+)]
+pub struct Review;
+
+impl Review {
+    fn handle_submit(&mut self, draft: states::Draft, _: inputs::Submit) -> states::InReview {
+        states::InReview {
+            revision: draft.revision,
+        }
+    }
+    fn handle_approve(&mut self, review: states::InReview, _: inputs::Approve) -> states::Approved {
+        states::Approved {
+            revision: review.revision,
+        }
+    }
+    fn handle_reject(&mut self, review: states::InReview, _: inputs::Reject) -> states::Draft {
+        states::Draft {
+            revision: review.revision + 1,
+        }
+    }
+}
+
+#[cfg(test)]
+fn temp_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("rust-automata-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn workflow_persists_on_transition() {
+    use rust_automata::persistence::FileBackend;
+
+    let backend = FileBackend::new(temp_dir("workflow")).unwrap();
+    assert!(StateMachine::load(Review, &backend, "doc-1")
+        .unwrap()
+        .is_none());
+
+    let mut review = StateMachine::new(Review, states::Draft::default());
+    review
+        .persist_on_transition(backend.clone(), "doc-1")
+        .unwrap();
+    review.consume(inputs::Submit);
+    review.consume(inputs::Reject);
+    review.consume(inputs::Submit);
+    drop(review);
+
+    // The service restarts.
+    let mut review = StateMachine::load(Review, &backend, "doc-1")
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        review.state().in_review(),
+        &states::InReview { revision: 1 }
+    );
+    review.consume(inputs::Approve);
+    assert!(review.state().is_approved());
+}
+
+#[test]
+fn workflow_rejects_keys_outside_the_directory() {
+    use rust_automata::persistence::{FileBackend, Persistence};
+    use std::io::ErrorKind;
+
+    let mut backend = FileBackend::new(temp_dir("workflow-keys")).unwrap();
+    for key in ["", "..", "../doc-1", "docs/doc-1", "docs\\doc-1"] {
+        let error = backend.save(key, b"{}").unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput, "{key}");
+        assert_eq!(
+            backend.load(key).unwrap_err().kind(),
+            ErrorKind::InvalidInput
+        );
+    }
+    backend.save("doc.v1", b"{}").unwrap();
+    assert_eq!(backend.load("doc.v1").unwrap().as_deref(), Some(&b"{}"[..]));
+}
+
+#[test]
+fn workflow_restore_rejects_other_machines() {
+    let review = StateMachine::new(Review, states::Draft::default());
+    let snapshot = String::from_utf8(review.snapshot().unwrap()).unwrap();
+    let schema = Review::graph().schema;
+    assert_eq!(
+        snapshot,
+        format!(r#"{{"machine":"Review","schema":{schema},"state":{{"Draft":{{"revision":0}}}}}}"#)
+    );
+
+    let snapshot = snapshot.replace("Review", "Lock");
+    assert!(StateMachine::restore(Review, snapshot.as_bytes()).is_err());
+}
+
+#[test]
+fn workflow_migrates_old_snapshots() {
+    use rust_automata::persistence::StoredState;
+
+    // Taken when the review state was called `Pending` and had a `rev` field.
+    let old = br#"{"machine":"Review","schema":1,"state":{"Pending":{"rev":2}}}"#;
+    assert!(StateMachine::restore(Review, old).is_err());
+
+    let review = StateMachine::restore_with(Review, old, |state| match state.name.as_str() {
+        "Pending" => Ok(StoredState {
+            name: "InReview".to_string(),
+            payload: serde_json::json!({ "revision": state.payload["rev"] }),
+        }),
+        _ => Ok(state),
+    })
+    .unwrap();
+    assert_eq!(
+        review.state().in_review(),
+        &states::InReview { revision: 2 }
+    );
+
+    // Snapshots of the current definition are not migrated.
+    let current = review.snapshot().unwrap();
+    let review = StateMachine::restore_with(Review, &current, |_| unreachable!()).unwrap();
+    assert!(review.state().is_in_review());
+}
+
+#[test]
+fn workflow_registry() {
+    use crate::lock::{self, Lock};
+    use rust_automata::dyn_machine::{ConsumeError, DynStateMachine};
+
+    let mut registry: Vec<Box<dyn DynStateMachine>> = vec![
+        Box::new(StateMachine::new(Review, states::Draft::default())),
+        Box::new(StateMachine::new(Lock, lock::states::Open)),
+    ];
+    assert_eq!(registry[0].name(), "Review");
+    assert_eq!(registry[1].available_input_names(), ["Key", "Drill"]);
+
+    assert_eq!(
+        registry[0].consume_by_name("Submit", Some(Box::new(inputs::Submit))),
+        Ok("Nothing")
+    );
+    assert_eq!(registry[0].state_name(), "InReview");
+    assert_eq!(registry[1].consume_by_name("Key", None), Ok("Click"));
+    assert_eq!(registry[1].state_name(), "Closed");
+
+    assert_eq!(
+        registry[0].consume_by_name("Approve", Some(Box::new(42))),
+        Err(ConsumeError::PayloadMismatch("Approve"))
+    );
+    assert_eq!(
+        registry[0].consume_by_name("Open", None),
+        Err(ConsumeError::UnknownInput("Open".to_string()))
+    );
+    assert!(matches!(
+        registry[0].consume_by_name("Submit", None),
+        Err(ConsumeError::Rejected(_))
+    ));
+    assert_eq!(registry[0].state_name(), "InReview");
+}
+
+#[test]
+fn workflow_terminal_state() {
+    assert!(Review::DOT.contains("    Draft [penwidth=2];\n"));
+    assert!(Review::DOT.contains("    Approved [shape=doublecircle];\n"));
+    assert!(Review::SCXML.contains(r#"  <final id="Approved"/>"#));
+    assert!(Review::PLANTUML.contains("Approved --> [*]\n"));
+}
+
+#[test]
+fn workflow_groups() {
+    assert!(Review::DOT.contains(
+        r#"    Approved [shape=doublecircle];
+    subgraph cluster_Open {
+        label="Open";
+        Draft [penwidth=2];
+        InReview;
+    }
+"#
+    ));
+}
@@ -0,0 +1,89 @@
+use crate::parser;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+
+#[cfg(any(feature = "csv", feature = "diagram-files"))]
+fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// The machine as a state×input matrix in CSV.
+///
+/// There is a row per state and a column per input, plus an `(no input)` column if the machine
+/// has transitions without an input. A cell holds the transitions the input takes from the
+/// state, as `Target [guard] Output!`, separated by ` | `. An empty cell means the input is
+/// not accepted.
+#[cfg(any(feature = "csv", feature = "diagram-files"))]
+pub fn source(m: &parser::MachineAttr) -> String {
+    use crate::parser::guard_expr_to_string;
+    use crate::util;
+    use crate::GUARD_PREFIX;
+    use std::collections::HashMap;
+    use std::fmt::Write;
+
+    let mut columns: Vec<_> = m.inputs.iter().map(Some).collect();
+    let mut header: Vec<_> = m.inputs.iter().map(|i| util::last(i).to_string()).collect();
+    if m.transitions.iter().any(|tr| tr.input.is_none()) {
+        columns.push(None);
+        header.push("(no input)".to_string());
+    }
+
+    let mut cells: HashMap<_, Vec<_>> = HashMap::new();
+    for tr in &m.transitions {
+        let cell = (util::key(&tr.from_state), tr.input.as_ref().map(util::key));
+        cells.entry(cell).or_default().push(tr);
+    }
+
+    let mut csv = String::new();
+    header.insert(0, "state".to_string());
+    let header: Vec<_> = header.iter().map(|h| escape(h)).collect();
+    writeln!(csv, "{}", header.join(",")).unwrap();
+    for state in &m.states {
+        let mut row = vec![escape(&util::last(state).to_string())];
+        for column in &columns {
+            let cell = (util::key(state), column.map(util::key));
+            let cell: Vec<_> = cells
+                .get(&cell)
+                .into_iter()
+                .flatten()
+                .map(|tr| {
+                    let mut target = util::last(&tr.to_state).to_string();
+                    if let Some(ref g) = tr.guard {
+                        let guard = guard_expr_to_string(g, &|path| {
+                            util::key(path).replace(GUARD_PREFIX, "")
+                        });
+                        write!(target, " [{guard}]").unwrap();
+                    }
+                    if let Some(ref out) = tr.output {
+                        write!(target, " {}!", util::last(out)).unwrap();
+                    }
+                    target
+                })
+                .collect();
+            row.push(escape(&cell.join(" | ")));
+        }
+        writeln!(csv, "{}", row.join(",")).unwrap();
+    }
+    csv
+}
+
+/// `Machine::CSV`, the transition matrix.
+#[cfg(feature = "csv")]
+pub fn items(m: &parser::MachineAttr, machine: &syn::Ident) -> TokenStream2 {
+    let csv = source(m);
+    quote! {
+        impl #machine {
+            /// The transitions as a state×input matrix in CSV.
+            pub const CSV: &'static str = #csv;
+        }
+    }
+}
+
+#[cfg(not(feature = "csv"))]
+pub fn items(_: &parser::MachineAttr, _: &syn::Ident) -> TokenStream2 {
+    quote!()
+}
@@ -0,0 +1,85 @@
+use crate::parser;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+
+/// The D2 diagram.
+#[cfg(any(feature = "d2", feature = "diagram-files"))]
+pub fn source(m: &parser::MachineAttr) -> String {
+    use crate::annotations::{edge_label_lines, edges, is_terminal, unhandled_inputs};
+    use crate::util;
+    use std::fmt::Write;
+
+    let quoted = |lines: &[String]| format!("\"{}\"", lines.join("\\n").replace('"', "\\\""));
+    let state_ids: Vec<_> = m.states.iter().map(util::last).collect();
+    let mut d2 = String::new();
+    let direction = match m.diagram.rankdir.as_ref().map(|r| r.to_string()).as_deref() {
+        Some("TB") => "down",
+        Some("BT") => "up",
+        Some("RL") => "left",
+        _ => "right",
+    };
+    writeln!(d2, "direction: {direction}").unwrap();
+    if m.diagram
+        .theme
+        .as_ref()
+        .is_some_and(|theme| theme == "dark")
+    {
+        writeln!(d2, "vars: {{d2-config: {{theme-id: 200}}}}").unwrap();
+    }
+    writeln!(d2, "__start: \"\" {{shape: circle; width: 12; height: 12}}").unwrap();
+    writeln!(d2, "__start -> {}", state_ids.first().unwrap()).unwrap();
+    for (i, (id, path)) in state_ids.iter().zip(&m.states).enumerate() {
+        match (i, is_terminal(m, path)) {
+            (_, true) => writeln!(d2, "{id}: {{style.double-border: true}}").unwrap(),
+            (0, false) => writeln!(d2, "{id}: {{style.bold: true}}").unwrap(),
+            _ => writeln!(d2, "{id}").unwrap(),
+        }
+    }
+    for edge in edges(m) {
+        let from = util::last(&edge[0].from_state);
+        let to = util::last(&edge[0].to_state);
+        let lines = edge_label_lines(&edge, &m.diagram);
+        if lines.is_empty() {
+            writeln!(d2, "{from} -> {to}").unwrap();
+        } else {
+            writeln!(d2, "{from} -> {to}: {}", quoted(&lines)).unwrap();
+        }
+    }
+    for (from, inputs) in unhandled_inputs(m) {
+        let lines: Vec<_> = inputs.iter().map(|i| format!("{i}?")).collect();
+        let label = quoted(&lines);
+        writeln!(d2, "{from} -> Failure: {label} {{style.stroke-dash: 3}}").unwrap();
+    }
+    d2
+}
+
+#[cfg(feature = "d2")]
+pub fn attr(m: &parser::MachineAttr) -> TokenStream2 {
+    let mut doc = vec!["```d2".to_string()];
+    doc.extend(source(m).lines().map(str::to_string));
+    doc.push("```".to_string());
+    doc.push(String::new());
+    quote! { #( #[doc = #doc] )* }
+}
+
+/// `Machine::D2`, the diagram as a standalone D2 source.
+#[cfg(feature = "d2")]
+pub fn items(m: &parser::MachineAttr, machine: &syn::Ident) -> TokenStream2 {
+    let d2 = source(m);
+    quote! {
+        impl #machine {
+            /// The state diagram as a D2 source.
+            pub const D2: &'static str = #d2;
+        }
+    }
+}
+
+#[cfg(not(feature = "d2"))]
+pub fn attr(_: &parser::MachineAttr) -> TokenStream2 {
+    quote!()
+}
+
+#[cfg(not(feature = "d2"))]
+pub fn items(_: &parser::MachineAttr, _: &syn::Ident) -> TokenStream2 {
+    quote!()
+}
@@ -0,0 +1,150 @@
+use crate::parser;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+
+/// The Graphviz digraph.
+#[cfg(any(feature = "dot", feature = "diagram-files"))]
+pub fn source(m: &parser::MachineAttr, machine: &syn::Ident) -> String {
+    use crate::annotations::{
+        edge_label_lines, edges, guard_paths, guard_signature, handler_signature, is_terminal,
+        ungrouped_states, unhandled_inputs,
+    };
+    use crate::util;
+    use std::fmt::Write;
+
+    let state_ids: Vec<_> = m.states.iter().map(util::last).collect();
+    let mut dot = String::new();
+    writeln!(dot, "digraph {machine} {{").unwrap();
+    let rankdir = m
+        .diagram
+        .rankdir
+        .as_ref()
+        .map_or("LR".to_string(), |r| r.to_string());
+    writeln!(dot, "    rankdir={rankdir};").unwrap();
+    if m.diagram
+        .theme
+        .as_ref()
+        .is_some_and(|theme| theme == "dark")
+    {
+        writeln!(dot, "    bgcolor=\"#1e1e1e\";").unwrap();
+        writeln!(dot, "    node [color=white, fontcolor=white];").unwrap();
+        writeln!(dot, "    edge [color=white, fontcolor=white];").unwrap();
+    }
+    writeln!(dot, "    node [shape=box, style=rounded];").unwrap();
+    writeln!(dot, "    __start [shape=point];").unwrap();
+    writeln!(dot, "    __start -> {};", state_ids.first().unwrap()).unwrap();
+    let node = |dot: &mut String, indent: &str, path: &syn::Path| {
+        let id = util::last(path);
+        if is_terminal(m, path) {
+            writeln!(dot, "{indent}{id} [shape=doublecircle];").unwrap();
+        } else if util::key(path) == util::key(&m.states[0]) {
+            writeln!(dot, "{indent}{id} [penwidth=2];").unwrap();
+        } else {
+            writeln!(dot, "{indent}{id};").unwrap();
+        }
+    };
+    for path in ungrouped_states(m) {
+        node(&mut dot, "    ", path);
+    }
+    for group in &m.groups {
+        writeln!(dot, "    subgraph cluster_{} {{", group.name).unwrap();
+        writeln!(dot, "        label=\"{}\";", group.name).unwrap();
+        for path in &group.states {
+            node(&mut dot, "        ", path);
+        }
+        writeln!(dot, "    }}").unwrap();
+    }
+    for edge in edges(m) {
+        let from = util::last(&edge[0].from_state);
+        let to = util::last(&edge[0].to_state);
+        let label = edge_label_lines(&edge, &m.diagram)
+            .join("\\n")
+            .replace('"', "\\\"");
+        // The signatures of the guards and handlers to implement, shown on hover in SVGs
+        let mut signatures = Vec::new();
+        for tr in &edge {
+            let guards = tr.guard.as_ref().map_or(Vec::new(), guard_paths);
+            let guards = guards.into_iter().map(|g| guard_signature(g, tr));
+            for signature in guards.chain(handler_signature(tr)) {
+                if !signatures.contains(&signature) {
+                    signatures.push(signature);
+                }
+            }
+        }
+        if signatures.is_empty() {
+            writeln!(dot, "    {from} -> {to} [label=\"{label}\"];").unwrap();
+        } else {
+            let tooltip = signatures.join("\\n");
+            writeln!(
+                dot,
+                "    {from} -> {to} [label=\"{label}\", tooltip=\"{tooltip}\"];"
+            )
+            .unwrap();
+        }
+    }
+    let unhandled = unhandled_inputs(m);
+    if !unhandled.is_empty() {
+        writeln!(dot, "    Failure [color=red, fontcolor=red];").unwrap();
+    }
+    for (from, inputs) in unhandled {
+        let label = inputs.iter().map(|i| format!("{i}?")).collect::<Vec<_>>();
+        let label = label.join("\\n");
+        writeln!(
+            dot,
+            "    {from} -> Failure [label=\"{label}\", style=dashed, color=red];"
+        )
+        .unwrap();
+    }
+    writeln!(dot, "}}").unwrap();
+    dot
+}
+
+#[cfg(feature = "dot")]
+pub fn attr(m: &parser::MachineAttr, machine: &syn::Ident) -> TokenStream2 {
+    let mut doc = vec!["```dot".to_string()];
+    doc.extend(source(m, machine).lines().map(str::to_string));
+    doc.push("```".to_string());
+    doc.push(String::new());
+    quote! { #( #[doc = #doc] )* }
+}
+
+/// `Machine::DOT`, the diagram as a standalone Graphviz source.
+#[cfg(feature = "dot")]
+pub fn items(m: &parser::MachineAttr, machine: &syn::Ident) -> TokenStream2 {
+    let dot = source(m, machine);
+    quote! {
+        impl #machine {
+            /// The state diagram as a Graphviz source.
+            pub const DOT: &'static str = #dot;
+        }
+    }
+}
+
+/// `<MACHINE>_DOT` in the internal module, the same as `Machine::DOT`.
+#[cfg(feature = "dot")]
+pub fn constant(m: &parser::MachineAttr, machine: &syn::Ident) -> TokenStream2 {
+    let name = quote::format_ident!(
+        "{}_DOT",
+        crate::util::snake(machine).to_string().to_uppercase()
+    );
+    let dot = source(m, machine);
+    quote! {
+        /// The Graphviz state diagram of the machine.
+        pub const #name: &str = #dot;
+    }
+}
+
+#[cfg(not(feature = "dot"))]
+pub fn constant(_: &parser::MachineAttr, _: &syn::Ident) -> TokenStream2 {
+    quote!()
+}
+
+#[cfg(not(feature = "dot"))]
+pub fn attr(_: &parser::MachineAttr, _: &syn::Ident) -> TokenStream2 {
+    quote!()
+}
+
+#[cfg(not(feature = "dot"))]
+pub fn items(_: &parser::MachineAttr, _: &syn::Ident) -> TokenStream2 {
+    quote!()
+}
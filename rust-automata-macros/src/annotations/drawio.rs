@@ -0,0 +1,112 @@
+use crate::parser;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+
+#[cfg(any(feature = "drawio", feature = "diagram-files"))]
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\n', "&#xa;")
+}
+
+/// The state diagram as a draw.io (diagrams.net) file.
+///
+/// The states are laid out in a row, to be rearranged in draw.io. The cell IDs are the state
+/// names and the transition hashes, so they stay the same when the machine changes.
+#[cfg(any(feature = "drawio", feature = "diagram-files"))]
+pub fn source(m: &parser::MachineAttr, machine: &syn::Ident) -> String {
+    use crate::annotations::{
+        edge_label_lines, edges, is_terminal, transition_id, unhandled_inputs,
+    };
+    use crate::util;
+    use std::fmt::Write;
+
+    const STATE_STYLE: &str = "rounded=1;whiteSpace=wrap;html=0;";
+    const EDGE_STYLE: &str = "edgeStyle=orthogonalEdgeStyle;curved=1;html=0;";
+
+    let mut xml = String::new();
+    writeln!(xml, r#"<mxfile>"#).unwrap();
+    writeln!(xml, r#"  <diagram name="{machine}" id="{machine}">"#).unwrap();
+    writeln!(xml, r#"    <mxGraphModel>"#).unwrap();
+    writeln!(xml, r#"      <root>"#).unwrap();
+    writeln!(xml, r#"        <mxCell id="0"/>"#).unwrap();
+    writeln!(xml, r#"        <mxCell id="1" parent="0"/>"#).unwrap();
+    let vertex = |xml: &mut String, id: &str, style: &str, x: usize| {
+        writeln!(
+            xml,
+            r#"        <mxCell id="{id}" value="{id}" style="{style}" vertex="1" parent="1">"#
+        )
+        .unwrap();
+        writeln!(
+            xml,
+            r#"          <mxGeometry x="{x}" y="40" width="120" height="60" as="geometry"/>"#
+        )
+        .unwrap();
+        writeln!(xml, r#"        </mxCell>"#).unwrap();
+    };
+    let edge = |xml: &mut String, id: &str, label: &str, style: &str, from: &str, to: &str| {
+        writeln!(
+            xml,
+            r#"        <mxCell id="{id}" value="{}" style="{style}" edge="1" parent="1" source="{from}" target="{to}">"#,
+            escape(label)
+        )
+        .unwrap();
+        writeln!(xml, r#"          <mxGeometry relative="1" as="geometry"/>"#).unwrap();
+        writeln!(xml, r#"        </mxCell>"#).unwrap();
+    };
+
+    for (i, state) in m.states.iter().enumerate() {
+        let mut style = STATE_STYLE.to_string();
+        if i == 0 {
+            style.push_str("fontStyle=1;");
+        }
+        if is_terminal(m, state) {
+            style.push_str("strokeWidth=3;");
+        }
+        let id = util::last(state).to_string();
+        vertex(&mut xml, &id, &style, 40 + 200 * i);
+    }
+    for transitions in edges(m) {
+        let id = transition_id(transitions[0]);
+        let label = edge_label_lines(&transitions, &m.diagram).join("\n");
+        let from = util::last(&transitions[0].from_state).to_string();
+        let to = util::last(&transitions[0].to_state).to_string();
+        edge(&mut xml, &id, &label, EDGE_STYLE, &from, &to);
+    }
+    let unhandled = unhandled_inputs(m);
+    if !unhandled.is_empty() {
+        let style = format!("{STATE_STYLE}strokeColor=#FF0000;fontColor=#FF0000;");
+        vertex(&mut xml, "Failure", &style, 40 + 200 * m.states.len());
+    }
+    for (from, inputs) in unhandled {
+        let label: Vec<_> = inputs.iter().map(|i| format!("{i}?")).collect();
+        let style = format!("{EDGE_STYLE}dashed=1;strokeColor=#FF0000;");
+        let from = from.to_string();
+        let id = format!("failure_{from}");
+        edge(&mut xml, &id, &label.join("\n"), &style, &from, "Failure");
+    }
+    writeln!(xml, r#"      </root>"#).unwrap();
+    writeln!(xml, r#"    </mxGraphModel>"#).unwrap();
+    writeln!(xml, r#"  </diagram>"#).unwrap();
+    writeln!(xml, r#"</mxfile>"#).unwrap();
+    xml
+}
+
+/// `Machine::DRAWIO`, the diagram as a `.drawio` file.
+#[cfg(feature = "drawio")]
+pub fn items(m: &parser::MachineAttr, machine: &syn::Ident) -> TokenStream2 {
+    let xml = source(m, machine);
+    quote! {
+        impl #machine {
+            /// The state diagram as a draw.io (diagrams.net) file.
+            pub const DRAWIO: &'static str = #xml;
+        }
+    }
+}
+
+#[cfg(not(feature = "drawio"))]
+pub fn items(_: &parser::MachineAttr, _: &syn::Ident) -> TokenStream2 {
+    quote!()
+}
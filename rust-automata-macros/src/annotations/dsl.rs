@@ -2,6 +2,16 @@ use crate::parser;
 use proc_macro2::TokenStream as TokenStream2;
 use quote::quote;
 
+/// The `[hot] ` or `[cold] ` prefix of a transition.
+#[cfg(feature = "dsl")]
+fn hint_prefix(tr: &parser::Transition) -> &'static str {
+    match tr.hint {
+        parser::Hint::None => "",
+        parser::Hint::Hot => "[hot] ",
+        parser::Hint::Cold => "[cold] ",
+    }
+}
+
 #[cfg(feature = "dsl")]
 pub fn attr(m: &parser::MachineAttr) -> TokenStream2 {
     use crate::parser::guard_expr_to_string;
@@ -34,6 +44,33 @@ pub fn attr(m: &parser::MachineAttr) -> TokenStream2 {
     }
     writeln!(dsl, "),").unwrap();
 
+    // Write terminal states
+    if !m.terminal.is_empty() {
+        let terminal: Vec<_> = m
+            .terminal
+            .iter()
+            .map(|p| util::last(p).to_string())
+            .collect();
+        writeln!(dsl, "///terminal({}),", terminal.join(", ")).unwrap();
+    }
+
+    // Write groups
+    if !m.groups.is_empty() {
+        let groups: Vec<_> = m
+            .groups
+            .iter()
+            .map(|group| {
+                let states: Vec<_> = group
+                    .states
+                    .iter()
+                    .map(|p| util::last(p).to_string())
+                    .collect();
+                format!("{}: [{}]", group.name, states.join(", "))
+            })
+            .collect();
+        writeln!(dsl, "///groups({}),", groups.join(", ")).unwrap();
+    }
+
     // Write outputs
     write!(dsl, "///outputs(").unwrap();
     for (i, path) in m.outputs.iter().enumerate() {
@@ -51,7 +88,7 @@ pub fn attr(m: &parser::MachineAttr) -> TokenStream2 {
         .iter()
         .map(|tr| {
             let from = util::last(&tr.from_state).to_string();
-            let mut length = from.len() + 2; // +2 for the parentheses
+            let mut length = hint_prefix(tr).len() + from.len() + 2; // +2 for the parentheses
 
             if let Some(ref input) = tr.input {
                 let input_id = util::last(input).to_string();
@@ -87,11 +124,11 @@ pub fn attr(m: &parser::MachineAttr) -> TokenStream2 {
         let from = util::last(&tr.from_state);
         let to = util::last(&tr.to_state);
 
-        // Format left side (from state + input)
-        let mut left_side = format!("({})", from);
+        // Format left side (hint + from state + input)
+        let mut left_side = format!("{}({})", hint_prefix(tr), from);
         if let Some(ref input) = tr.input {
             let input_id = util::last(input);
-            left_side = format!("({}, {})", from, input_id);
+            left_side = format!("{}({}, {})", hint_prefix(tr), from, input_id);
         }
 
         // Format middle part (to state + output)
@@ -148,6 +185,39 @@ pub fn attr(m: &parser::MachineAttr) -> TokenStream2 {
         writeln!(dsl, ")").unwrap();
     }
 
+    // Write dispatch
+    if m.table_dispatch {
+        writeln!(dsl, "///dispatch(table),").unwrap();
+    }
+
+    // Write diagram options
+    let diagram = &m.diagram;
+    let mut options = Vec::new();
+    if let Some(ref theme) = diagram.theme {
+        options.push(format!("theme = {theme}"));
+    }
+    if let Some(ref rankdir) = diagram.rankdir {
+        options.push(format!("rankdir = {rankdir}"));
+    }
+    if let Some(ref layout) = diagram.layout {
+        options.push(format!("layout = {layout}"));
+    }
+    if diagram.hide_outputs {
+        options.push("hide_outputs".to_string());
+    }
+    if diagram.merge_edges {
+        options.push("merge_edges".to_string());
+    }
+    if diagram.show_failure {
+        options.push("show_failure".to_string());
+    }
+    for format in &diagram.disabled {
+        options.push(format!("{format} = false"));
+    }
+    if !options.is_empty() {
+        writeln!(dsl, "///diagram({})", options.join(", ")).unwrap();
+    }
+
     writeln!(dsl, "///```").unwrap();
     writeln!(dsl, "///").unwrap();
 
@@ -0,0 +1,116 @@
+use crate::parser;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+
+/// Where the diagram sources are written: `RUST_AUTOMATA_DIAGRAM_DIR`, or else the `OUT_DIR`
+/// of a crate with a build script, in a subdirectory named after the crate being compiled.
+#[cfg(feature = "diagram-files")]
+fn diagram_dir() -> Option<std::path::PathBuf> {
+    let dir =
+        std::env::var_os("RUST_AUTOMATA_DIAGRAM_DIR").or_else(|| std::env::var_os("OUT_DIR"))?;
+    // The crates of a workspace share the directory, so machines of the same name
+    // in different crates do not overwrite each other.
+    let krate = std::env::var_os("CARGO_CRATE_NAME").unwrap_or_default();
+    Some(std::path::PathBuf::from(dir).join(krate))
+}
+
+/// The file names and contents of the diagrams: `<Machine>.mmd`, `<Machine>.puml`, `<Machine>.dot`,
+/// `<Machine>.d2`, `<Machine>.scxml`, `<Machine>.html`, `<Machine>.csv` and `<Machine>.drawio`,
+/// leaving out the formats turned off for the machine.
+#[cfg(feature = "diagram-files")]
+pub fn sources(m: &parser::MachineAttr, machine: &syn::Ident) -> Vec<(String, String)> {
+    use crate::annotations::{csv, d2, dot, drawio, html, mermaid, plantuml, scxml};
+
+    let formats = [
+        ("mermaid", "mmd"),
+        ("plantuml", "puml"),
+        ("dot", "dot"),
+        ("d2", "d2"),
+        ("scxml", "scxml"),
+        ("html", "html"),
+        ("csv", "csv"),
+        ("drawio", "drawio"),
+    ];
+    formats
+        .into_iter()
+        .filter(|(format, _)| m.diagram.enabled(format))
+        .map(|(format, ext)| {
+            let source = match format {
+                "mermaid" => mermaid::source(m),
+                "plantuml" => plantuml::source(m),
+                "dot" => dot::source(m, machine),
+                "d2" => d2::source(m),
+                "scxml" => scxml::source(m, machine),
+                "html" => html::source(m, machine),
+                "csv" => csv::source(m),
+                _ => drawio::source(m, machine),
+            };
+            (format!("{machine}.{ext}"), source)
+        })
+        .collect()
+}
+
+/// Write the diagram [`sources`] into the diagram directory, if there is one.
+///
+/// The files are named after the machine, so machines of the same name in one crate
+/// overwrite each other's files.
+#[cfg(feature = "diagram-files")]
+pub fn write(m: &parser::MachineAttr, machine: &syn::Ident) -> TokenStream2 {
+    let Some(dir) = diagram_dir() else {
+        return quote!();
+    };
+    let files = sources(m, machine);
+    let written = std::fs::create_dir_all(&dir).and_then(|_| {
+        files
+            .iter()
+            .try_for_each(|(name, source)| write_if_changed(&dir.join(name), source))
+    });
+    match written {
+        Ok(()) => quote!(),
+        Err(err) => {
+            let msg = format!("Cannot write diagrams to {}: {err}", dir.display());
+            quote! { compile_error!(#msg); }
+        }
+    }
+}
+
+// Rewriting unchanged files would trigger file watchers on every expansion.
+#[cfg(feature = "diagram-files")]
+pub fn write_if_changed(path: &std::path::Path, source: &str) -> std::io::Result<()> {
+    if std::fs::read_to_string(path).is_ok_and(|old| old == source) {
+        return Ok(());
+    }
+    std::fs::write(path, source)
+}
+
+#[cfg(not(feature = "diagram-files"))]
+pub fn write(_: &parser::MachineAttr, _: &syn::Ident) -> TokenStream2 {
+    quote!()
+}
+
+#[cfg(all(test, feature = "diagram-files"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_files_after_the_machine() {
+        let m: parser::MachineAttr = syn::parse_str(
+            "states(A, B), transitions((A) -> (B)), diagram(html = false, csv = false)",
+        )
+        .unwrap();
+        let machine = quote::format_ident!("Lock");
+        let names: Vec<_> = sources(&m, &machine)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        let expected = [
+            "Lock.mmd",
+            "Lock.puml",
+            "Lock.dot",
+            "Lock.d2",
+            "Lock.scxml",
+            "Lock.drawio",
+        ];
+        assert_eq!(names, expected);
+    }
+}
@@ -0,0 +1,135 @@
+use crate::parser;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+
+#[cfg(any(feature = "html", feature = "diagram-files"))]
+const STYLE: &str = "body{font-family:sans-serif;margin:2em}\
+table{border-collapse:collapse}\
+th,td{border:1px solid #ccc;padding:.3em .6em;text-align:left}\
+[data-k]{cursor:default;border-radius:3px;padding:0 2px}\
+tr.hl{background:#f4f4f4}\
+[data-k].hl{background:#ffe08a}";
+
+// Hovering a symbol highlights it everywhere, with the transitions it appears in.
+#[cfg(any(feature = "html", feature = "diagram-files"))]
+const SCRIPT: &str = "document.querySelectorAll('[data-k]').forEach(e=>{\
+const all=()=>document.querySelectorAll(`[data-k=\"${e.dataset.k}\"]`);\
+e.addEventListener('mouseenter',()=>all().forEach(x=>{x.classList.add('hl');\
+x.closest('tr')?.classList.add('hl')}));\
+e.addEventListener('mouseleave',()=>document.querySelectorAll('.hl')\
+.forEach(x=>x.classList.remove('hl')));});";
+
+#[cfg(any(feature = "html", feature = "diagram-files"))]
+fn symbol(kind: &str, name: &str) -> String {
+    format!("<span data-k=\"{kind}:{name}\">{name}</span>")
+}
+
+/// The guard expression, with every guard highlightable on its own.
+#[cfg(any(feature = "html", feature = "diagram-files"))]
+fn guard_html(guard: &syn::Expr) -> String {
+    use crate::parser::guard_expr_to_string;
+    use crate::util;
+    use crate::GUARD_PREFIX;
+    use std::cell::RefCell;
+
+    // Escape the operators, but not the spans around the guards.
+    let guards = RefCell::new(Vec::new());
+    let expr = guard_expr_to_string(guard, &|path| {
+        let mut guards = guards.borrow_mut();
+        guards.push(util::key(path).replace(GUARD_PREFIX, ""));
+        format!("\u{1}{}\u{1}", guards.len() - 1)
+    });
+    let expr = expr
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+    expr.split('\u{1}')
+        .enumerate()
+        .map(|(i, part)| match i % 2 {
+            0 => part.to_string(),
+            _ => symbol("guard", &guards.borrow()[part.parse::<usize>().unwrap()]),
+        })
+        .collect()
+}
+
+/// A self-contained HTML page with the states and transitions of the machine.
+#[cfg(any(feature = "html", feature = "diagram-files"))]
+pub fn source(m: &parser::MachineAttr, machine: &syn::Ident) -> String {
+    use crate::annotations::is_terminal;
+    use crate::util;
+    use crate::HANDLE_PREFIX;
+    use std::fmt::Write;
+
+    let name = |p: &syn::Path| util::last(p).to_string();
+    let mut html = String::new();
+    writeln!(html, "<!DOCTYPE html>").unwrap();
+    writeln!(html, "<html>").unwrap();
+    writeln!(html, "<head>").unwrap();
+    writeln!(html, "<meta charset=\"utf-8\">").unwrap();
+    writeln!(html, "<title>{machine}</title>").unwrap();
+    writeln!(html, "<style>{STYLE}</style>").unwrap();
+    writeln!(html, "</head>").unwrap();
+    writeln!(html, "<body>").unwrap();
+    writeln!(html, "<h1>{machine}</h1>").unwrap();
+
+    writeln!(html, "<h2>States</h2>").unwrap();
+    writeln!(html, "<ul>").unwrap();
+    for (i, state) in m.states.iter().enumerate() {
+        let initial = if i == 0 { " (initial)" } else { "" };
+        let terminal = if is_terminal(m, state) {
+            " (terminal)"
+        } else {
+            ""
+        };
+        let state = symbol("state", &name(state));
+        writeln!(html, "<li>{state}{initial}{terminal}</li>").unwrap();
+    }
+    writeln!(html, "</ul>").unwrap();
+
+    writeln!(html, "<h2>Transitions</h2>").unwrap();
+    writeln!(html, "<table>").unwrap();
+    writeln!(
+        html,
+        "<tr><th>From</th><th>Input</th><th>Guard</th><th>To</th><th>Output</th><th>Handler</th></tr>"
+    )
+    .unwrap();
+    for tr in &m.transitions {
+        let cells = [
+            symbol("state", &name(&tr.from_state)),
+            tr.input
+                .as_ref()
+                .map_or(String::new(), |i| symbol("input", &name(i))),
+            tr.guard.as_ref().map_or(String::new(), guard_html),
+            symbol("state", &name(&tr.to_state)),
+            tr.output
+                .as_ref()
+                .map_or(String::new(), |o| symbol("output", &name(o))),
+            tr.handler.as_ref().map_or(String::new(), |h| {
+                symbol("handler", &h.to_string().replace(HANDLE_PREFIX, ""))
+            }),
+        ];
+        writeln!(html, "<tr><td>{}</td></tr>", cells.join("</td><td>")).unwrap();
+    }
+    writeln!(html, "</table>").unwrap();
+    writeln!(html, "<script>{SCRIPT}</script>").unwrap();
+    writeln!(html, "</body>").unwrap();
+    writeln!(html, "</html>").unwrap();
+    html
+}
+
+/// `Machine::HTML`, a self-contained page to explore the machine in a browser.
+#[cfg(feature = "html")]
+pub fn items(m: &parser::MachineAttr, machine: &syn::Ident) -> TokenStream2 {
+    let html = source(m, machine);
+    quote! {
+        impl #machine {
+            /// A self-contained HTML page with the states and transitions of the machine.
+            pub const HTML: &'static str = #html;
+        }
+    }
+}
+
+#[cfg(not(feature = "html"))]
+pub fn items(_: &parser::MachineAttr, _: &syn::Ident) -> TokenStream2 {
+    quote!()
+}
@@ -2,55 +2,148 @@ use crate::parser;
 use proc_macro2::TokenStream as TokenStream2;
 use quote::quote;
 
-#[cfg(feature = "mermaid")]
-fn transition_label(tr: &parser::Transition) -> String {
-    use crate::annotations::doc_link;
+/// A `title` attribute, shown as a tooltip.
+#[cfg(any(feature = "mermaid", feature = "diagram-files"))]
+fn title(text: &str) -> String {
+    let text = text
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+    format!(" title='{text}'")
+}
+
+/// The parts of an edge label, with links to the symbols' docs. The links to guards and
+/// handlers show the signatures the methods must have.
+#[cfg(any(feature = "mermaid", feature = "diagram-files"))]
+fn label_parts(tr: &parser::Transition, options: &parser::DiagramOptions) -> Vec<String> {
+    use crate::annotations::{doc_link, guard_signature, handler_signature};
     use crate::parser::guard_expr_to_string;
     use crate::util;
     use crate::GUARD_PREFIX;
     use crate::HANDLE_PREFIX;
-    let mut label = String::new();
+    let mut parts = Vec::new();
     if let Some(ref i) = tr.input {
-        let ev = util::last(i);
-        label = format!("<a href='{}'>{}?</a>", doc_link(i), ev);
-    }
-    if let Some(ref out) = tr.output {
-        let out_id = util::last(out);
-        label.push_str(&format!(
-            "{}<a href='{}'>{}!</a>",
-            if label.is_empty() { "" } else { "<br>" },
+        parts.push(format!("<a href='{}'>{}?</a>", doc_link(i), util::last(i)));
+    }
+    if let Some(out) = tr.output.as_ref().filter(|_| !options.hide_outputs) {
+        parts.push(format!(
+            "<a href='{}'>{}!</a>",
             doc_link(out),
-            out_id
+            util::last(out)
         ));
     }
     if let Some(ref g) = tr.guard {
         let guard_str = guard_expr_to_string(g, &|path| {
             let guard_id = util::key(path);
             format!(
-                "<a href='#method.{}'>{}</a>",
+                "<a href='#method.{}'{}>{}</a>",
                 guard_id,
+                title(&guard_signature(path, tr)),
                 guard_id.replace(GUARD_PREFIX, "")
             )
         });
-        label.push_str(&format!(
-            "{0}&nbsp;{1}",
-            if label.is_empty() { "" } else { "<br>" },
-            guard_str
-        ));
+        parts.push(format!("&nbsp;{guard_str}"));
     }
     if let Some(ref h) = tr.handler {
-        label.push_str(&format!(
-            "{0}↪️&nbsp;<a href='#method.{h}'>{1}</a>",
-            if label.is_empty() { "" } else { "<br>" },
+        let title = handler_signature(tr).map_or(String::new(), |sig| title(&sig));
+        parts.push(format!(
+            "↪️&nbsp;<a href='#method.{h}'{title}>{}</a>",
             h.to_string().replace(HANDLE_PREFIX, "")
         ));
     }
-    label
+    parts
 }
 
-#[cfg(feature = "mermaid")]
-pub fn attr(m: &parser::MachineAttr) -> TokenStream2 {
-    use crate::annotations::doc_link;
+/// The label of an edge. A merged edge has a line per transition.
+#[cfg(any(feature = "mermaid", feature = "diagram-files"))]
+fn edge_label(edge: &[&parser::Transition], options: &parser::DiagramOptions) -> String {
+    match edge {
+        [tr] => label_parts(tr, options).join("<br>"),
+        _ => edge
+            .iter()
+            .map(|tr| label_parts(tr, options).join(" "))
+            .collect::<Vec<_>>()
+            .join("<br>"),
+    }
+}
+
+/// The Mermaid diagram, without the code fence.
+#[cfg(any(feature = "mermaid", feature = "diagram-files"))]
+pub fn source(m: &parser::MachineAttr) -> String {
+    match m.diagram.layout {
+        Some(ref layout) if layout == "flowchart" => flowchart(m),
+        _ => state_diagram(m),
+    }
+}
+
+/// The machine as a Mermaid flowchart. Unlike in the state diagram, self-loops are plain edges.
+#[cfg(any(feature = "mermaid", feature = "diagram-files"))]
+fn flowchart(m: &parser::MachineAttr) -> String {
+    use crate::annotations::{doc_link, edges, is_terminal, ungrouped_states, unhandled_inputs};
+    use crate::util;
+    use std::fmt::Write;
+
+    let mut md = String::new();
+    if let Some(ref theme) = m.diagram.theme {
+        writeln!(md, "%%{{init: {{'theme': '{theme}'}}}}%%").unwrap();
+    }
+    let direction = m
+        .diagram
+        .rankdir
+        .as_ref()
+        .map_or("LR".to_string(), ToString::to_string);
+    writeln!(md, "flowchart {direction}").unwrap();
+    writeln!(md, "    classDef initial font-weight:bold").unwrap();
+    writeln!(md, "    __start(( )) --> {}", util::last(&m.states[0])).unwrap();
+
+    // Clickable state nodes, terminal states as double circles, groups as subgraphs
+    let node = |md: &mut String, indent: &str, path: &syn::Path| {
+        let id = util::last(path);
+        let text = format!("\"<a href='{}'>{id}</a>\"", doc_link(path));
+        if is_terminal(m, path) {
+            writeln!(md, "{indent}{id}((({text})))").unwrap();
+        } else {
+            writeln!(md, "{indent}{id}({text})").unwrap();
+        }
+    };
+    for path in ungrouped_states(m) {
+        node(&mut md, "    ", path);
+    }
+    for group in &m.groups {
+        writeln!(md, "    subgraph {}", group.name).unwrap();
+        for path in &group.states {
+            node(&mut md, "        ", path);
+        }
+        writeln!(md, "    end").unwrap();
+    }
+    writeln!(md, "    class {} initial", util::last(&m.states[0])).unwrap();
+
+    // Transitions
+    for edge in edges(m) {
+        let from = util::last(&edge[0].from_state);
+        let to = util::last(&edge[0].to_state);
+        let label = edge_label(&edge, &m.diagram);
+        if label.is_empty() {
+            writeln!(md, "    {from} --> {to}").unwrap();
+        } else {
+            writeln!(md, "    {from} -->|\"{label}\"| {to}").unwrap();
+        }
+    }
+
+    // Fall-through edges
+    for (from, inputs) in unhandled_inputs(m) {
+        let label = inputs.iter().map(|i| format!("{i}?")).collect::<Vec<_>>();
+        writeln!(md, "    {from} -.->|\"{}\"| Failure", label.join("<br>")).unwrap();
+    }
+    md
+}
+
+/// The machine as a Mermaid state diagram.
+#[cfg(any(feature = "mermaid", feature = "diagram-files"))]
+fn state_diagram(m: &parser::MachineAttr) -> String {
+    use crate::annotations::{
+        doc_link, edges, is_terminal, transition_id, ungrouped_states, unhandled_inputs,
+    };
     use crate::util;
     use std::fmt::Write;
 
@@ -59,50 +152,151 @@ pub fn attr(m: &parser::MachineAttr) -> TokenStream2 {
     let initial = state_ids.first().unwrap();
 
     let mut md = String::new();
-    writeln!(md, "///```mermaid").unwrap();
-    writeln!(md, "///stateDiagram-v2").unwrap();
+    if let Some(ref theme) = m.diagram.theme {
+        writeln!(md, "%%{{init: {{'theme': '{theme}'}}}}%%").unwrap();
+    }
+    writeln!(md, "stateDiagram-v2").unwrap();
+    if let Some(ref rankdir) = m.diagram.rankdir {
+        writeln!(md, "    direction {rankdir}").unwrap();
+    }
     writeln!(
         md,
-        "///    classDef selfLoop fill:#eee,stroke-width:0px,shape:rectangle,margin:0,padding:0"
+        "    classDef selfLoop fill:#eee,stroke-width:0px,shape:rectangle,margin:0,padding:0"
     )
     .unwrap();
-    writeln!(md, "///    [*] --> {}", initial).unwrap();
+    writeln!(md, "    classDef initial font-weight:bold").unwrap();
+    writeln!(md, "    classDef terminal stroke-width:3px").unwrap();
 
-    // Clickable state aliases
-    for (id, path) in state_ids.iter().zip(state_paths) {
+    // Clickable state aliases, groups as composite states
+    let alias = |md: &mut String, indent: &str, path: &syn::Path| {
+        let id = util::last(path);
         writeln!(
             md,
-            "///    state \"<a href='{}'>{}</a>\" as {}",
-            doc_link(path),
-            id,
-            id
+            "{indent}state \"<a href='{}'>{id}</a>\" as {id}",
+            doc_link(path)
         )
         .unwrap();
+    };
+    for path in ungrouped_states(m) {
+        alias(&mut md, "    ", path);
     }
+    for group in &m.groups {
+        writeln!(md, "    state {} {{", group.name).unwrap();
+        for path in &group.states {
+            alias(&mut md, "        ", path);
+        }
+        writeln!(md, "    }}").unwrap();
+    }
+    // After the aliases, so that a grouped initial state isn't drawn outside its group
+    writeln!(md, "    [*] --> {}", initial).unwrap();
+    writeln!(md, "    class {initial} initial").unwrap();
 
     // Transitions
-    for (i, tr) in m.transitions.iter().enumerate() {
-        let from = util::last(&tr.from_state);
-        let to = util::last(&tr.to_state);
-        let label = transition_label(tr);
+    for edge in edges(m) {
+        let from = util::last(&edge[0].from_state);
+        let to = util::last(&edge[0].to_state);
+        let label = edge_label(&edge, &m.diagram);
         if from == to {
-            writeln!(md, "///    state \"{label}\" as tran_{from}_{to}_{i}").unwrap();
-            writeln!(md, "///    class tran_{from}_{to}_{i} selfLoop").unwrap();
-            writeln!(md, "///    {from} --> tran_{from}_{to}_{i}").unwrap();
-            writeln!(md, "///    tran_{from}_{to}_{i} --> {from}").unwrap();
+            let id = transition_id(edge[0]);
+            writeln!(md, "    state \"{label}\" as {id}").unwrap();
+            writeln!(md, "    class {id} selfLoop").unwrap();
+            writeln!(md, "    {from} --> {id}").unwrap();
+            writeln!(md, "    {id} --> {from}").unwrap();
         } else {
-            writeln!(md, "///    {from} --> {to}: {label}").unwrap();
+            writeln!(md, "    {from} --> {to}: {label}").unwrap();
+        }
+    }
+
+    // Terminal states
+    for (id, path) in state_ids.iter().zip(state_paths) {
+        if is_terminal(m, path) {
+            writeln!(md, "    class {id} terminal").unwrap();
+            writeln!(md, "    {id} --> [*]").unwrap();
         }
     }
 
+    // Fall-through edges
+    for (from, inputs) in unhandled_inputs(m) {
+        let label = inputs.iter().map(|i| format!("{i}?")).collect::<Vec<_>>();
+        writeln!(md, "    {from} --> Failure: {}", label.join("<br>")).unwrap();
+    }
+    md
+}
+
+#[cfg(feature = "mermaid")]
+pub fn attr(m: &parser::MachineAttr) -> TokenStream2 {
+    use std::fmt::Write;
+
+    let mut md = String::new();
+    writeln!(md, "///```mermaid").unwrap();
+    for line in source(m).lines() {
+        writeln!(md, "///{line}").unwrap();
+    }
     writeln!(md, "///```").unwrap();
     writeln!(md, "///").unwrap();
+    md.push_str(&legend(m));
 
     let tokens: TokenStream2 = md.parse().unwrap();
     quote! { #[cfg_attr(doc, ::rust_automata::aquamarine)] #tokens }
 }
 
+/// The symbol conventions of the edge labels, with links to the inputs and outputs.
+#[cfg(feature = "mermaid")]
+fn legend(m: &parser::MachineAttr) -> String {
+    use crate::util;
+    use std::fmt::Write;
+
+    let links = |paths: &[syn::Path]| {
+        paths
+            .iter()
+            .map(|p| format!("[`{}`]({})", util::last(p), util::key(p)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let mut md = String::new();
+    writeln!(md, "///**Legend**").unwrap();
+    writeln!(md, "///").unwrap();
+    if !m.inputs.is_empty() {
+        writeln!(md, "///- `Input?` consumes an input: {}", links(&m.inputs)).unwrap();
+    }
+    if !m.outputs.is_empty() {
+        writeln!(
+            md,
+            "///- `Output!` produces an output: {}",
+            links(&m.outputs)
+        )
+        .unwrap();
+    }
+    writeln!(
+        md,
+        "///- A guard expression must hold for the transition to be taken."
+    )
+    .unwrap();
+    writeln!(md, "///- ↪️ calls the handler.").unwrap();
+    writeln!(md, "///").unwrap();
+    md
+}
+
+/// `<MACHINE>_MERMAID` in the internal module, the diagram without the code fence.
+#[cfg(feature = "mermaid")]
+pub fn constant(m: &parser::MachineAttr, machine: &syn::Ident) -> TokenStream2 {
+    let name = quote::format_ident!(
+        "{}_MERMAID",
+        crate::util::snake(machine).to_string().to_uppercase()
+    );
+    let md = source(m);
+    quote! {
+        /// The Mermaid state diagram of the machine.
+        pub const #name: &str = #md;
+    }
+}
+
 #[cfg(not(feature = "mermaid"))]
 pub fn attr(_: &parser::MachineAttr) -> TokenStream2 {
     quote!()
 }
+
+#[cfg(not(feature = "mermaid"))]
+pub fn constant(_: &parser::MachineAttr, _: &syn::Ident) -> TokenStream2 {
+    quote!()
+}
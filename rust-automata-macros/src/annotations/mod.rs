@@ -1,8 +1,31 @@
+mod csv;
+mod d2;
+mod dot;
+mod drawio;
 mod dsl;
+pub mod files;
+mod html;
 mod mermaid;
+mod plantuml;
+mod scxml;
+mod svg;
 
+pub use csv::items as csv_items;
+pub use d2::attr as d2_attr;
+pub use d2::items as d2_items;
+pub use dot::attr as dot_attr;
+pub use dot::constant as dot_const;
+pub use dot::items as dot_items;
+pub use drawio::items as drawio_items;
 pub use dsl::attr as dsl_attr;
+pub use files::write as write_diagram_files;
+pub use html::items as html_items;
 pub use mermaid::attr as mermaid_attr;
+pub use mermaid::constant as mermaid_const;
+pub use plantuml::attr as plantuml_attr;
+pub use plantuml::items as plantuml_items;
+pub use scxml::items as scxml_items;
+pub use svg::attr as svg_attr;
 
 /// Convert a path to the rust‑doc HTML file path.
 ///
@@ -23,3 +46,197 @@ fn doc_link(p: &syn::Path) -> String {
         format!("{}/struct.{last}.html", segs.join("/"))
     }
 }
+
+/// For every state, the inputs it has no transition for, when `diagram(show_failure)` is on.
+/// Consuming them leads to the `Failure` state.
+#[allow(dead_code)]
+fn unhandled_inputs(m: &crate::parser::MachineAttr) -> Vec<(&syn::Ident, Vec<&syn::Ident>)> {
+    use crate::util;
+    use std::collections::HashSet;
+    if !m.diagram.show_failure {
+        return Vec::new();
+    }
+    let handled: HashSet<_> = m
+        .transitions
+        .iter()
+        .filter_map(|tr| Some((util::key(&tr.from_state), util::key(tr.input.as_ref()?))))
+        .collect();
+    m.states
+        .iter()
+        .filter_map(|state| {
+            let state_key = util::key(state);
+            let inputs: Vec<_> = m
+                .inputs
+                .iter()
+                .filter(|input| !handled.contains(&(state_key.clone(), util::key(input))))
+                .map(util::last)
+                .collect();
+            (!inputs.is_empty()).then(|| (util::last(state), inputs))
+        })
+        .collect()
+}
+
+/// The lines of a plain-text edge label: `Input?`, `Output!`, `[guard expression]` and `/ handler`.
+#[allow(dead_code)]
+fn label_lines(
+    tr: &crate::parser::Transition,
+    options: &crate::parser::DiagramOptions,
+) -> Vec<String> {
+    use crate::parser::guard_expr_to_string;
+    use crate::util;
+    use crate::GUARD_PREFIX;
+    use crate::HANDLE_PREFIX;
+    let mut lines = Vec::new();
+    if let Some(ref i) = tr.input {
+        lines.push(format!("{}?", util::last(i)));
+    }
+    if let Some(out) = tr.output.as_ref().filter(|_| !options.hide_outputs) {
+        lines.push(format!("{}!", util::last(out)));
+    }
+    if let Some(ref g) = tr.guard {
+        let guard_str = guard_expr_to_string(g, &|path| util::key(path).replace(GUARD_PREFIX, ""));
+        lines.push(format!("[{guard_str}]"));
+    }
+    if let Some(ref h) = tr.handler {
+        lines.push(format!("/ {}", h.to_string().replace(HANDLE_PREFIX, "")));
+    }
+    lines
+}
+
+/// The guard methods called by a guard expression.
+#[allow(dead_code)]
+fn guard_paths(expr: &syn::Expr) -> Vec<&syn::Path> {
+    match expr {
+        syn::Expr::Path(expr_path) => vec![&expr_path.path],
+        syn::Expr::Binary(binary) => {
+            let mut paths = guard_paths(&binary.left);
+            paths.extend(guard_paths(&binary.right));
+            paths
+        }
+        syn::Expr::Unary(unary) => guard_paths(&unary.expr),
+        _ => Vec::new(),
+    }
+}
+
+/// The signature the guard method must have, as in the signature checks of the macro.
+#[allow(dead_code)]
+fn guard_signature(guard: &syn::Path, tr: &crate::parser::Transition) -> String {
+    use crate::util;
+    use crate::GUARD_PREFIX;
+    let guard = util::key(guard);
+    if guard.starts_with(GUARD_PREFIX) {
+        let state = util::key(&tr.from_state);
+        format!("fn {guard}(&self, state: &{state}) -> bool")
+    } else {
+        format!("fn {guard}(&self) -> bool")
+    }
+}
+
+/// The signature the handler method must have, for handlers with the `handle_` prefix, which
+/// are the ones the macro checks.
+#[allow(dead_code)]
+fn handler_signature(tr: &crate::parser::Transition) -> Option<String> {
+    use crate::util;
+    use crate::HANDLE_PREFIX;
+    let handler = tr
+        .handler
+        .as_ref()
+        .filter(|h| h.to_string().starts_with(HANDLE_PREFIX))?;
+    let mut params = format!("&mut self, state: {}", util::key(&tr.from_state));
+    if let Some(ref input) = tr.input {
+        params.push_str(&format!(", input: {}", util::key(input)));
+    }
+    let to = util::key(&tr.to_state);
+    let ret = match tr.output {
+        Some(ref output) => format!("({to}, {})", util::key(output)),
+        None => to,
+    };
+    Some(format!("fn {handler}({params}) -> {ret}"))
+}
+
+/// Whether the state is declared in the `terminal(..)` section.
+#[allow(dead_code)]
+fn is_terminal(m: &crate::parser::MachineAttr, state: &syn::Path) -> bool {
+    use crate::util;
+    m.terminal.iter().any(|t| util::key(t) == util::key(state))
+}
+
+/// The states outside of any `groups(..)` group.
+#[allow(dead_code)]
+fn ungrouped_states(m: &crate::parser::MachineAttr) -> Vec<&syn::Path> {
+    use crate::util;
+    m.states
+        .iter()
+        .filter(|state| {
+            !m.groups
+                .iter()
+                .flat_map(|group| &group.states)
+                .any(|s| util::key(s) == util::key(state))
+        })
+        .collect()
+}
+
+/// A diagram node ID for a transition, derived from its content rather than its position,
+/// so adding a transition doesn't rename the nodes of all the others.
+///
+/// Uses FNV-1a, which, unlike `std`'s hashers, is stable across compiler versions.
+#[allow(dead_code)]
+fn transition_id(tr: &crate::parser::Transition) -> String {
+    use crate::parser::guard_expr_to_string;
+    use crate::util;
+    let content = [
+        Some(util::key(&tr.from_state)),
+        tr.input.as_ref().map(util::key),
+        tr.guard
+            .as_ref()
+            .map(|g| guard_expr_to_string(g, &util::key)),
+        Some(util::key(&tr.to_state)),
+        tr.output.as_ref().map(util::key),
+        tr.handler.as_ref().map(ToString::to_string),
+    ]
+    .map(Option::unwrap_or_default)
+    .join("|");
+    let hash = content.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    });
+    format!("tran_{}_{:08x}", util::last(&tr.from_state), hash as u32)
+}
+
+/// The edges of a diagram, in order of appearance. With `diagram(merge_edges)`, all the
+/// transitions between a pair of states share an edge; otherwise, each has its own.
+#[allow(dead_code)]
+fn edges(m: &crate::parser::MachineAttr) -> Vec<Vec<&crate::parser::Transition>> {
+    use crate::util;
+    use std::collections::HashMap;
+    if !m.diagram.merge_edges {
+        return m.transitions.iter().map(|tr| vec![tr]).collect();
+    }
+    let mut edges: Vec<Vec<&crate::parser::Transition>> = Vec::new();
+    let mut positions: HashMap<_, usize> = HashMap::new();
+    for tr in &m.transitions {
+        let pair = (util::key(&tr.from_state), util::key(&tr.to_state));
+        match positions.get(&pair) {
+            Some(&position) => edges[position].push(tr),
+            None => {
+                positions.insert(pair, edges.len());
+                edges.push(vec![tr]);
+            }
+        }
+    }
+    edges
+}
+
+/// The lines of a plain-text edge label. A merged edge has a line per transition.
+#[allow(dead_code)]
+fn edge_label_lines(
+    edge: &[&crate::parser::Transition],
+    options: &crate::parser::DiagramOptions,
+) -> Vec<String> {
+    match edge {
+        [tr] => label_lines(tr, options),
+        _ => edge
+            .iter()
+            .map(|tr| label_lines(tr, options).join(" "))
+            .collect(),
+    }
+}
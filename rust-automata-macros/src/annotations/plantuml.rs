@@ -0,0 +1,69 @@
+use crate::parser;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+
+/// The PlantUML state diagram, from `@startuml` to `@enduml`.
+#[cfg(any(feature = "plantuml", feature = "diagram-files"))]
+pub fn source(m: &parser::MachineAttr) -> String {
+    use crate::annotations::{edge_label_lines, edges, is_terminal, unhandled_inputs};
+    use crate::util;
+    use std::fmt::Write;
+
+    let state_ids: Vec<_> = m.states.iter().map(util::last).collect();
+    let mut uml = String::new();
+    writeln!(uml, "@startuml").unwrap();
+    writeln!(uml, "[*] --> {}", state_ids.first().unwrap()).unwrap();
+    for id in &state_ids {
+        writeln!(uml, "state {id}").unwrap();
+    }
+    for edge in edges(m) {
+        let from = util::last(&edge[0].from_state);
+        let to = util::last(&edge[0].to_state);
+        let label = edge_label_lines(&edge, &m.diagram).join("\\n");
+        if label.is_empty() {
+            writeln!(uml, "{from} --> {to}").unwrap();
+        } else {
+            writeln!(uml, "{from} --> {to} : {label}").unwrap();
+        }
+    }
+    for (from, inputs) in unhandled_inputs(m) {
+        let label = inputs.iter().map(|i| format!("{i}?")).collect::<Vec<_>>();
+        writeln!(uml, "{from} -[dashed]-> Failure : {}", label.join("\\n")).unwrap();
+    }
+    for state in m.states.iter().filter(|s| is_terminal(m, s)) {
+        writeln!(uml, "{} --> [*]", util::last(state)).unwrap();
+    }
+    writeln!(uml, "@enduml").unwrap();
+    uml
+}
+
+#[cfg(feature = "plantuml")]
+pub fn attr(m: &parser::MachineAttr) -> TokenStream2 {
+    let mut doc = vec!["```plantuml".to_string()];
+    doc.extend(source(m).lines().map(str::to_string));
+    doc.push("```".to_string());
+    doc.push(String::new());
+    quote! { #( #[doc = #doc] )* }
+}
+
+/// `Machine::PLANTUML`, the diagram as a standalone `.puml` source.
+#[cfg(feature = "plantuml")]
+pub fn items(m: &parser::MachineAttr, machine: &syn::Ident) -> TokenStream2 {
+    let uml = source(m);
+    quote! {
+        impl #machine {
+            /// The state diagram as a PlantUML source.
+            pub const PLANTUML: &'static str = #uml;
+        }
+    }
+}
+
+#[cfg(not(feature = "plantuml"))]
+pub fn attr(_: &parser::MachineAttr) -> TokenStream2 {
+    quote!()
+}
+
+#[cfg(not(feature = "plantuml"))]
+pub fn items(_: &parser::MachineAttr, _: &syn::Ident) -> TokenStream2 {
+    quote!()
+}
@@ -0,0 +1,95 @@
+use crate::parser;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+
+#[cfg(any(feature = "scxml", feature = "diagram-files"))]
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// The machine as a W3C SCXML document.
+///
+/// Inputs are events, guards are `cond` strings with the names of the guard methods, and
+/// outputs are sent to the parent session. Transitions without an input are eventless.
+/// Terminal states without transitions are `<final>` states.
+#[cfg(any(feature = "scxml", feature = "diagram-files"))]
+pub fn source(m: &parser::MachineAttr, machine: &syn::Ident) -> String {
+    use crate::annotations::is_terminal;
+    use crate::parser::guard_expr_to_string;
+    use crate::util;
+    use std::collections::HashMap;
+    use std::fmt::Write;
+
+    let mut xml = String::new();
+    writeln!(xml, r#"<?xml version="1.0" encoding="UTF-8"?>"#).unwrap();
+    writeln!(
+        xml,
+        r#"<scxml xmlns="http://www.w3.org/2005/07/scxml" version="1.0" name="{machine}" initial="{}">"#,
+        util::last(m.states.first().unwrap())
+    )
+    .unwrap();
+    let mut from: HashMap<_, Vec<_>> = HashMap::new();
+    for tr in &m.transitions {
+        from.entry(util::key(&tr.from_state)).or_default().push(tr);
+    }
+    for state in &m.states {
+        let transitions = from.remove(&util::key(state)).unwrap_or_default();
+        if transitions.is_empty() {
+            let element = if is_terminal(m, state) {
+                "final"
+            } else {
+                "state"
+            };
+            writeln!(xml, r#"  <{element} id="{}"/>"#, util::last(state)).unwrap();
+            continue;
+        }
+        writeln!(xml, r#"  <state id="{}">"#, util::last(state)).unwrap();
+        for tr in transitions {
+            let mut attrs = String::new();
+            if let Some(ref input) = tr.input {
+                write!(attrs, r#" event="{}""#, util::last(input)).unwrap();
+            }
+            if let Some(ref guard) = tr.guard {
+                let cond = guard_expr_to_string(guard, &|path| util::key(path));
+                write!(attrs, r#" cond="{}""#, escape(&cond)).unwrap();
+            }
+            write!(attrs, r#" target="{}""#, util::last(&tr.to_state)).unwrap();
+            match tr.output {
+                Some(ref output) => {
+                    writeln!(xml, "    <transition{attrs}>").unwrap();
+                    writeln!(
+                        xml,
+                        r##"      <send event="{}" target="#_parent"/>"##,
+                        util::last(output)
+                    )
+                    .unwrap();
+                    writeln!(xml, "    </transition>").unwrap();
+                }
+                None => writeln!(xml, "    <transition{attrs}/>").unwrap(),
+            }
+        }
+        writeln!(xml, "  </state>").unwrap();
+    }
+    writeln!(xml, "</scxml>").unwrap();
+    xml
+}
+
+/// `Machine::SCXML`, the machine as an SCXML document.
+#[cfg(feature = "scxml")]
+pub fn items(m: &parser::MachineAttr, machine: &syn::Ident) -> TokenStream2 {
+    let xml = source(m, machine);
+    quote! {
+        impl #machine {
+            /// The machine as a W3C SCXML document.
+            pub const SCXML: &'static str = #xml;
+        }
+    }
+}
+
+#[cfg(not(feature = "scxml"))]
+pub fn items(_: &parser::MachineAttr, _: &syn::Ident) -> TokenStream2 {
+    quote!()
+}
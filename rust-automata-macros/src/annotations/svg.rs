@@ -0,0 +1,42 @@
+use crate::parser;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+
+/// The Graphviz diagram rendered to SVG with `layout-rs`, or `None` if it cannot lay it out.
+#[cfg(feature = "svg")]
+pub fn source(m: &parser::MachineAttr, machine: &syn::Ident) -> Option<String> {
+    use crate::annotations::dot;
+    use layout::backends::svg::SVGWriter;
+    use layout::gv::{DotParser, GraphBuilder};
+
+    // `layout-rs` only takes IDs with a leading underscore in quotes.
+    let dot = dot::source(m, machine).replace("\n    __start ", "\n    \"__start\" ");
+    let graph = DotParser::new(&dot).process().ok()?;
+    // The layout panics on some graphs it doesn't support; the docs go without the SVG then.
+    std::panic::catch_unwind(move || {
+        let mut builder = GraphBuilder::new();
+        builder.visit_graph(&graph);
+        let mut visual = builder.get();
+        let mut svg = SVGWriter::new();
+        visual.do_it(false, false, false, &mut svg);
+        svg.finalize()
+    })
+    .ok()
+}
+
+/// The SVG inlined in the docs, which needs neither JavaScript nor a Graphviz installation.
+#[cfg(feature = "svg")]
+pub fn attr(m: &parser::MachineAttr, machine: &syn::Ident) -> TokenStream2 {
+    let Some(svg) = source(m, machine) else {
+        return quote!();
+    };
+    // Markdown ends an HTML block at an empty line.
+    let svg: Vec<_> = svg.lines().filter(|line| !line.trim().is_empty()).collect();
+    let doc = format!("<div>\n{}\n</div>\n", svg.join("\n"));
+    quote! { #[doc = #doc] }
+}
+
+#[cfg(not(feature = "svg"))]
+pub fn attr(_: &parser::MachineAttr, _: &syn::Ident) -> TokenStream2 {
+    quote!()
+}
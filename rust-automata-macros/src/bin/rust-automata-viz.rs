@@ -0,0 +1,176 @@
+//! Render the diagrams of every `#[state_machine]` in a source tree, without compiling it.
+//!
+//! ```text
+//! rust-automata-viz [--out DIR] [--svg] PATH...
+//! ```
+//!
+//! Writes `<Machine>.mmd`, `<Machine>.puml`, `<Machine>.dot`, `<Machine>.d2`, `<Machine>.scxml`,
+//! `<Machine>.html`, `<Machine>.csv` and `<Machine>.drawio` for every machine found in the `.rs` files under `PATH`s into `DIR`
+//! (the current directory by default). With `--svg`, also renders `<Machine>.svg` with the
+//! Graphviz `dot` command. Machines of the same name are reported as errors instead of
+//! overwriting each other's diagrams.
+//!
+//! The diagrams are the same as the ones generated by the `diagram-files` feature.
+
+// Only the diagram sources are used, not the doc attributes.
+#[allow(dead_code, unused_imports)]
+#[path = "../annotations/mod.rs"]
+mod annotations;
+#[allow(dead_code)]
+#[path = "../parser.rs"]
+mod parser;
+#[allow(dead_code)]
+#[path = "../util.rs"]
+mod util;
+
+use annotations::files;
+use parser::MachineAttr;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitCode};
+use util::{GUARD_PREFIX, HANDLE_PREFIX};
+
+const USAGE: &str = "Usage: rust-automata-viz [--out DIR] [--svg] PATH...";
+
+struct Args {
+    out: PathBuf,
+    svg: bool,
+    paths: Vec<PathBuf>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        out: PathBuf::from("."),
+        svg: false,
+        paths: Vec::new(),
+    };
+    let mut it = std::env::args().skip(1);
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "--out" => args.out = it.next().ok_or("--out needs a directory")?.into(),
+            "--svg" => args.svg = true,
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            _ if arg.starts_with("--") => return Err(format!("Unknown option {arg}\n{USAGE}")),
+            _ => args.paths.push(arg.into()),
+        }
+    }
+    if args.paths.is_empty() {
+        return Err(USAGE.to_string());
+    }
+    Ok(args)
+}
+
+/// The `.rs` files under `path`, or `path` itself if it is a file.
+fn rust_files(path: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    if path.is_file() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+    let mut entries = std::fs::read_dir(path)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort();
+    for entry in entries {
+        if entry.is_dir() {
+            rust_files(&entry, files)?;
+        } else if entry.extension().is_some_and(|ext| ext == "rs") {
+            files.push(entry);
+        }
+    }
+    Ok(())
+}
+
+/// The machines declared in `items`, including nested inline modules.
+fn machines(items: &[syn::Item], found: &mut Vec<(syn::Ident, MachineAttr)>) -> syn::Result<()> {
+    for item in items {
+        match item {
+            syn::Item::Struct(s) => {
+                let attr = s
+                    .attrs
+                    .iter()
+                    .find(|a| a.path().segments.last().unwrap().ident == "state_machine");
+                if let Some(attr) = attr {
+                    found.push((s.ident.clone(), attr.parse_args()?));
+                }
+            }
+            syn::Item::Mod(m) => {
+                if let Some((_, items)) = &m.content {
+                    machines(items, found)?;
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Render the machines of `file`. `claims` holds the files where the machines rendered so far
+/// are defined, so that a machine does not overwrite the diagrams of another one of the same name.
+fn render(file: &Path, args: &Args, claims: &mut BTreeMap<String, String>) -> Result<(), String> {
+    let source = std::fs::read_to_string(file).map_err(|e| e.to_string())?;
+    let syntax = syn::parse_file(&source).map_err(|e| e.to_string())?;
+    let mut found = Vec::new();
+    machines(&syntax.items, &mut found).map_err(|e| e.to_string())?;
+    for (index, (machine, m)) in found.iter().enumerate() {
+        // Machines are only rendered once, so each gets its own location.
+        let location = format!("{} (machine {})", file.display(), index + 1);
+        if let Some(other) = claims.insert(machine.to_string(), location) {
+            return Err(format!(
+                "{machine} would overwrite the diagrams of the {machine} in {other}"
+            ));
+        }
+        for (name, source) in files::sources(m, machine) {
+            files::write_if_changed(&args.out.join(name), &source).map_err(|e| e.to_string())?;
+        }
+        if args.svg {
+            let dot = args.out.join(format!("{machine}.dot"));
+            let svg = args.out.join(format!("{machine}.svg"));
+            let status = Command::new("dot")
+                .arg("-Tsvg")
+                .arg(&dot)
+                .arg("-o")
+                .arg(&svg)
+                .status()
+                .map_err(|e| format!("Cannot run Graphviz dot: {e}"))?;
+            if !status.success() {
+                return Err(format!("Graphviz dot failed on {}", dot.display()));
+            }
+        }
+        println!("{}: {machine}", file.display());
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(msg) => {
+            eprintln!("{msg}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut sources = Vec::new();
+    for path in &args.paths {
+        if let Err(e) = rust_files(path, &mut sources) {
+            eprintln!("{}: {e}", path.display());
+            return ExitCode::FAILURE;
+        }
+    }
+    if let Err(e) = std::fs::create_dir_all(&args.out) {
+        eprintln!("{}: {e}", args.out.display());
+        return ExitCode::FAILURE;
+    }
+    let mut failed = false;
+    let mut claims = BTreeMap::new();
+    for file in &sources {
+        if let Err(e) = render(file, &args, &mut claims) {
+            eprintln!("{}: {e}", file.display());
+            failed = true;
+        }
+    }
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
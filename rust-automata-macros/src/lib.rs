@@ -3,8 +3,20 @@
 //! See the [`rust-automata` crate](https://docs.rs/rust-automata/) for more details.
 //!
 //! Documentation features:
-//! - `"mermaid"`: embed a clickable Mermaid state diagram.
+//! - `"mermaid"`: embed a clickable Mermaid state diagram, also available as
+//!   `internal_<Machine>::<MACHINE>_MERMAID`.
+//! - `"plantuml"`: embed a PlantUML state diagram, also available as `Machine::PLANTUML`.
+//! - `"dot"`: embed a Graphviz state diagram, also available as `Machine::DOT`.
+//! - `"svg"`: embed the Graphviz diagram rendered to SVG with `layout-rs`, which works without
+//!   JavaScript, for example on docs.rs.
+//! - `"d2"`: embed a D2 state diagram, also available as `Machine::D2`.
+//! - `"scxml"`: export the machine as a W3C SCXML document in `Machine::SCXML`.
+//! - `"html"`: a self-contained HTML page to explore the machine in `Machine::HTML`.
+//! - `"csv"`: the transitions as a state×input matrix in `Machine::CSV`.
+//! - `"drawio"`: the state diagram as a draw.io (diagrams.net) file in `Machine::DRAWIO`.
 //! - `"dsl"`: (re)generate a DSL for the machine.
+//! - `"diagram-files"`: write `<Machine>.mmd`, `<Machine>.puml`, `<Machine>.dot`, `<Machine>.d2`,
+//!   `<Machine>.scxml`, `<Machine>.html`, `<Machine>.csv` and `<Machine>.drawio` to `RUST_AUTOMATA_DIAGRAM_DIR/<crate>`, or else to `OUT_DIR/<crate>`.
 
 #![recursion_limit = "256"]
 
@@ -12,59 +24,16 @@ extern crate proc_macro;
 
 use proc_macro::TokenStream;
 use proc_macro2::TokenStream as TokenStream2;
-use quote::{format_ident, quote};
-use std::collections::BTreeSet;
+use quote::{format_ident, quote, quote_spanned};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use syn::{parse_macro_input, DeriveInput, Ident, ItemStruct, Path};
 
 mod parser;
-use parser::{MachineAttr, Transition};
+use parser::{Hint, MachineAttr, Transition};
 
 mod annotations;
 
-// Handlers that have this prefix receive states and inputs and should return a state and an output.
-const HANDLE_PREFIX: &str = "handle_";
-// Guards that have this prefix receive state reference and should return a boolean.
-const GUARD_PREFIX: &str = "guard_";
-
-mod util {
-    use super::*;
-    use heck::ToSnakeCase;
-
-    /// `CamelCase` → `snake_case` ident.
-    pub fn snake(id: &Ident) -> Ident {
-        Ident::new(&id.to_string().to_snake_case(), id.span())
-    }
-
-    /// Last segment of `syn::Path` → `snake_case` ident.
-    pub fn snake_path(p: &Path) -> Ident {
-        snake(last(p))
-    }
-
-    /// Last identifier of a path (`states::Open` → `Open`)
-    pub fn last(p: &Path) -> &Ident {
-        &p.segments.last().unwrap().ident
-    }
-
-    /// A unique string key for set membership (`states::Open`)
-    pub fn key(p: &Path) -> String {
-        p.segments
-            .iter()
-            .map(|s| s.ident.to_string())
-            .collect::<Vec<_>>()
-            .join("::")
-    }
-
-    /// Strip a trailing `Machine` from a type name if present.
-    pub fn strip_machine(id: &Ident) -> String {
-        let s = id.to_string();
-        s.strip_suffix("Machine").unwrap_or(&s).to_owned()
-    }
-
-    pub fn compile_error_if(condition: bool, message: &str) -> Option<TokenStream2> {
-        condition.then(|| quote! { compile_error!(#message); })
-    }
-}
-
+mod util;
 use util::*;
 
 mod building_blocks {
@@ -77,17 +46,17 @@ mod building_blocks {
                 let to_ty = &tr.to_state;
 
                 match (tr.input.as_ref(), tr.output.as_ref()) {
-                    (Some(inp_ty), Some(out_ty)) => quote! {
-                        super::#machine_ident::#handler as fn(&mut super::#machine_ident, super::#state_ty, super::#inp_ty) -> (super::#to_ty, super::#out_ty);
+                    (Some(inp_ty), Some(out_ty)) => quote_spanned! {handler.span()=>
+                        let _ = Self::#handler as fn(&mut super::#machine_ident, super::#state_ty, super::#inp_ty) -> (super::#to_ty, super::#out_ty);
                     },
-                    (Some(inp_ty), None) => quote! {
-                        super::#machine_ident::#handler as fn(&mut super::#machine_ident, super::#state_ty, super::#inp_ty) -> super::#to_ty;
+                    (Some(inp_ty), None) => quote_spanned! {handler.span()=>
+                        let _ = Self::#handler as fn(&mut super::#machine_ident, super::#state_ty, super::#inp_ty) -> super::#to_ty;
                     },
-                    (None, Some(out_ty)) => quote! {
-                        super::#machine_ident::#handler as fn(&mut super::#machine_ident, super::#state_ty) -> (super::#to_ty, super::#out_ty);
+                    (None, Some(out_ty)) => quote_spanned! {handler.span()=>
+                        let _ = Self::#handler as fn(&mut super::#machine_ident, super::#state_ty) -> (super::#to_ty, super::#out_ty);
                     },
-                    (None, None) => quote! {
-                        super::#machine_ident::#handler as fn(&mut super::#machine_ident, super::#state_ty) -> super::#to_ty;
+                    (None, None) => quote_spanned! {handler.span()=>
+                        let _ = Self::#handler as fn(&mut super::#machine_ident, super::#state_ty) -> super::#to_ty;
                     },
                 }
             }
@@ -95,6 +64,64 @@ mod building_blocks {
         }
     }
 
+    /// A fallback for every `handle_*` method, picked only if the machine has no such method,
+    /// to report the handler as missing with the signature to implement.
+    ///
+    /// Inherent methods take precedence over trait methods, so the fallback method of a trait
+    /// implemented for the machine is only resolved when the handler does not exist. It requires
+    /// a trait that is never implemented, whose `on_unimplemented` message is the error.
+    pub fn make_missing_handler_fallbacks(m: &MachineAttr, machine_ident: &Ident) -> TokenStream2 {
+        use heck::ToUpperCamelCase;
+
+        let mut seen = HashSet::new();
+        let fallbacks = m.transitions.iter().filter_map(|tr| {
+            let handler = tr.handler.as_ref()?;
+            if !handler.to_string().starts_with(HANDLE_PREFIX) || !seen.insert(handler.to_string()) {
+                return None;
+            }
+            let camel = handler.to_string().to_upper_camel_case();
+            let missing = format_ident!("Missing{}", camel);
+            let fallback = format_ident!("Fallback{}", camel);
+            let (state_ty, to_ty) = (&tr.from_state, &tr.to_state);
+            let params = match &tr.input {
+                Some(inp_ty) => quote! { _: super::#state_ty, _: super::#inp_ty },
+                None => quote! { _: super::#state_ty },
+            };
+            let ret = match &tr.output {
+                Some(out_ty) => quote! { (super::#to_ty, super::#out_ty) },
+                None => quote! { super::#to_ty },
+            };
+            let input = tr.input.as_ref().map(|i| format!(", input: {}", key(i)));
+            let ret_str = match &tr.output {
+                Some(o) => format!("({}, {})", key(&tr.to_state), key(o)),
+                None => key(&tr.to_state),
+            };
+            let signature = format!(
+                "fn {handler}(&mut self, state: {}{}) -> {ret_str}",
+                key(&tr.from_state),
+                input.unwrap_or_default()
+            );
+            let message = format!("no handler `{handler}` for `{machine_ident}`");
+            let note = format!("implement `{signature}` in `impl {machine_ident}`");
+            Some(quote! {
+                #[diagnostic::on_unimplemented(message = #message, label = "handler not found", note = #note)]
+                #[doc(hidden)]
+                pub trait #missing {}
+                #[doc(hidden)]
+                pub trait #fallback {
+                    fn #handler(&mut self, #params) -> #ret
+                    where
+                        Self: #missing,
+                    {
+                        unreachable!()
+                    }
+                }
+                impl #fallback for super::#machine_ident {}
+            })
+        });
+        quote! { #( #fallbacks )* }
+    }
+
     pub fn instantiate_vals(tr: &parser::Transition, state_var: &Ident, nothing_ident: &Ident) -> TokenStream2 {
         let next_val = if key(&tr.from_state) == key(&tr.to_state) {
             quote! { #state_var }
@@ -102,8 +129,7 @@ mod building_blocks {
             let to_path = &tr.to_state;
             quote! { super::#to_path::default() }
         };
-        let out_val = if tr.output.is_some() {
-            let out_path = tr.output.as_ref().unwrap();
+        let out_val = if let Some(out_path) = &tr.output {
             quote! { super::#out_path::default() }
         } else {
             quote! { #nothing_ident::default() }
@@ -125,17 +151,17 @@ mod building_blocks {
             Some(handler) if handler.to_string().starts_with(HANDLE_PREFIX) => {
                 let has_input = tr.input.is_some();
                 let has_output = tr.output.is_some();
-                let call = match (has_input, has_output) {
-                    (true, true) => {
-                        quote! { (next_val, out_val) = self.#handler(#state_var, #input_var); }
-                    }
-                    (true, false) => {
-                        quote! { next_val = self.#handler(#state_var, #input_var); out_val = #nothing_ident::default(); }
-                    }
-                    (false, true) => quote! { (next_val, out_val) = self.#handler(#state_var); },
-                    (false, false) => {
-                        quote! { next_val = self.#handler(#state_var); out_val = #nothing_ident::default(); }
-                    }
+                // Errors about the handler point at its name in the attribute.
+                let args = if has_input {
+                    quote! { #state_var, #input_var }
+                } else {
+                    quote! { #state_var }
+                };
+                let handler_call = quote_spanned! {handler.span()=> self.#handler(#args) };
+                let call = if has_output {
+                    quote! { (next_val, out_val) = #handler_call; }
+                } else {
+                    quote! { next_val = #handler_call; out_val = #nothing_ident::default(); }
                 };
                 (call, quote! {})
             }
@@ -148,6 +174,15 @@ mod building_blocks {
     }
 
     pub fn build_guard_code(tr: &Transition, state_var: &Ident) -> TokenStream2 {
+        match build_guard_condition(tr, state_var) {
+            Some(condition) => quote! { if #condition },
+            // no guard
+            None => quote! {},
+        }
+    }
+
+    /// The guard expression of the transition, evaluated on `state_var`.
+    pub fn build_guard_condition(tr: &Transition, state_var: &Ident) -> Option<TokenStream2> {
         match &tr.guard {
             Some(expr) => {
                 fn transform_expr(expr: &syn::Expr, state_var: &Ident) -> TokenStream2 {
@@ -175,11 +210,9 @@ mod building_blocks {
                     }
                 }
 
-                let transformed = transform_expr(expr, state_var);
-                quote! { if #transformed }
+                Some(transform_expr(expr, state_var))
             }
-            // no guard
-            None => quote! {},
+            None => None,
         }
     }
 
@@ -193,32 +226,72 @@ mod building_blocks {
         if states_set.is_empty() {
             return quote! { compile_error!("No states are defined"); };
         }
+        // Ids are `u16`, and id 0 is taken by `Failure` and `Nothing`.
+        let too_large = |paths: &Vec<Path>| paths.len() >= u16::MAX as usize;
+        if too_large(&m.states) || too_large(&m.inputs) || too_large(&m.outputs) {
+            let msg = "At most 65534 states, inputs or outputs are supported";
+            return quote! { compile_error!(#msg); };
+        }
+        let terminal_errors = m.terminal.iter().filter_map(|state| {
+            if states_set.contains(&key(state)) {
+                return None;
+            }
+            let msg = format!(
+                "Unknown terminal state: {}{}",
+                key(state),
+                did_you_mean(&key(state), &states_set)
+            );
+            compile_error_if(true, state, &msg)
+        });
+        let mut grouped = BTreeSet::new();
+        let group_errors = m.groups.iter().flat_map(|group| {
+            group
+                .states
+                .iter()
+                .filter_map(|state| {
+                    if !states_set.contains(&key(state)) {
+                        let msg = format!(
+                            "Unknown state: {} in group {}{}",
+                            key(state),
+                            group.name,
+                            did_you_mean(&key(state), &states_set)
+                        );
+                        return compile_error_if(true, state, &msg);
+                    }
+                    compile_error_if(
+                        !grouped.insert(key(state)),
+                        state,
+                        &format!("State {} is in more than one group", key(state)),
+                    )
+                })
+                .collect::<Vec<_>>()
+        });
         let errors = m.transitions.iter().flat_map(|tr| {
             let tr_descr = tr.to_string();
+            let unknown = |kind: &str, path: &Path, declared: &BTreeSet<String>| {
+                let key = key(path);
+                if declared.contains(&key) {
+                    return None;
+                }
+                let msg = format!(
+                    "Unknown {kind}: {key} in {tr_descr}{}",
+                    did_you_mean(&key, declared)
+                );
+                compile_error_if(true, path, &msg)
+            };
             vec![
-                compile_error_if(
-                    !states_set.contains(&key(&tr.from_state)),
-                    &format!("Unknown state: {} in {}", key(&tr.from_state), tr_descr),
-                ),
-                compile_error_if(
-                    !states_set.contains(&key(&tr.to_state)),
-                    &format!("Unknown state: {} in {}", key(&tr.to_state), tr_descr),
-                ),
-                tr.input.as_ref().and_then(|i| {
-                    compile_error_if(
-                        !inputs_set.contains(&key(i)),
-                        &format!("Unknown input: {} in {}", key(i), tr_descr),
-                    )
-                }),
-                tr.output.as_ref().and_then(|o| {
-                    compile_error_if(
-                        !outputs_set.contains(&key(o)),
-                        &format!("Unknown output: {} in {}", key(o), tr_descr),
-                    )
-                }),
+                unknown("state", &tr.from_state, &states_set),
+                unknown("state", &tr.to_state, &states_set),
+                tr.input
+                    .as_ref()
+                    .and_then(|i| unknown("input", i, &inputs_set)),
+                tr.output
+                    .as_ref()
+                    .and_then(|o| unknown("output", o, &outputs_set)),
                 tr.handler.as_ref().and_then(|h| {
                     compile_error_if(
                         h.to_string().starts_with(GUARD_PREFIX),
+                        h,
                         &format!("Handler cannot start with guard_ prefix: {}", h),
                     )
                 }),
@@ -226,7 +299,7 @@ mod building_blocks {
             .into_iter()
             .flatten()
         });
-        quote! { #(#errors)* }
+        quote! { #(#terminal_errors)* #(#group_errors)* #(#errors)* }
     }
 
     // A helper function that maps an iterable collection of identifiers to our enum match arms.
@@ -242,21 +315,40 @@ mod building_blocks {
             .collect()
     }
 
-    pub fn generate_enum_variants(ids: &Vec<&Ident>) -> Vec<proc_macro2::TokenStream> {
-        ids.iter()
+    /// Match arms building a variant from `Default`, or `None` if the struct does not implement it.
+    pub fn generate_default_variants(paths: &[Path]) -> Vec<TokenStream2> {
+        paths
+            .iter()
             .enumerate()
-            .map(|(idx, id)| {
-                let idx = format!("{}_usize", idx + 1);
-                let idx_token = syn::parse_str::<syn::Expr>(&idx).unwrap();
-                let id_token = id.to_string();
+            .map(|(idx, p)| {
+                let idx = idx + 1;
+                let id = last(p);
+                quote! {
+                    #idx => (&rust_automata::__private::Probe::<super::#p>::new()).make().map(Self::#id)
+                }
+            })
+            .collect()
+    }
+
+    /// Match arms wrapping a boxed struct in its variant, if the type matches.
+    pub fn generate_any_variants(paths: &[Path]) -> Vec<TokenStream2> {
+        paths
+            .iter()
+            .enumerate()
+            .map(|(idx, p)| {
+                let idx = idx + 1;
+                let id = last(p);
                 quote! {
-                    #idx_token => #id_token
+                    #idx => value.downcast::<super::#p>().map(|v| Self::#id(*v))
                 }
             })
             .collect()
     }
 
-    pub fn build_getters(alphabet_paths: &[Path]) -> TokenStream2 {
+    pub fn build_getters(alphabet_paths: &[Path], generate: bool) -> TokenStream2 {
+        if !generate {
+            return quote! {};
+        }
         let getters = alphabet_paths.iter().map(|p| {
             let id = last(p);
             let direct_fn = snake_path(p);
@@ -270,7 +362,7 @@ mod building_blocks {
                     if let Self::#id(o) = self { Some(o) } else { None }
                 }
                 pub fn #direct_fn(&self) -> &super::#p {
-                    self.#maybe_fn().expect(&format!("No such symbol like {}", stringify!(#direct_fn)))
+                    rust_automata::__private::expect_symbol(self.#maybe_fn(), stringify!(#direct_fn))
                 }
             }
         });
@@ -293,7 +385,7 @@ mod building_blocks {
                     fn from(o: #enum_ident) -> Self {
                         match o {
                             #enum_ident::#id(v) => v,
-                            _ => panic!("Invalid symbol requested from {}", stringify!(#p)),
+                            _ => rust_automata::__private::invalid_symbol(stringify!(#p)),
                         }
                     }
                 }
@@ -303,19 +395,34 @@ mod building_blocks {
         quote! { #( #conversions )* }
     }
 
+    /// `#[repr(u8)]`, or `#[repr(u16)]` for large enums, so that the tag is as small as the id.
+    pub fn repr_attr(variant_count: usize) -> TokenStream2 {
+        if variant_count <= u8::MAX as usize + 1 {
+            quote! { #[repr(u8)] }
+        } else {
+            quote! { #[repr(u16)] }
+        }
+    }
+
     pub fn build_alphabet(
         derive_attr: &TokenStream2,
         enum_ident: &Ident,
         nothing_ident: &Ident,
         alphabet_paths: &Vec<Path>,
+        generate_getters: bool,
     ) -> TokenStream2 {
         let alphabet_ids: Vec<_> = alphabet_paths.iter().map(last).collect();
         let enumerable_ids_alphabet = generate_enum_matches(&alphabet_ids);
-        let enumerable_variants = generate_enum_variants(&alphabet_ids);
-        let alphabet_getters = build_getters(alphabet_paths);
+        let variant_names = alphabet_ids.iter().map(|id| id.to_string());
+        let alphabet_getters = build_getters(alphabet_paths, generate_getters);
         let alphabet_conversions = build_conversions(enum_ident, alphabet_paths);
+        let variant_count = alphabet_paths.len() + 1;
+        let repr_attr = repr_attr(variant_count);
+        let default_variants = generate_default_variants(alphabet_paths);
+        let any_variants = generate_any_variants(alphabet_paths);
         quote! {
             #derive_attr
+            #repr_attr
             pub enum #enum_ident {
                 Nothing(#nothing_ident),
                 #( #alphabet_ids ( super::#alphabet_paths ) ),*
@@ -331,13 +438,40 @@ mod building_blocks {
                         #( #enumerable_ids_alphabet ),*
                     }
                 }
-                fn get_variant(id: &rust_automata::EnumId<#enum_ident>) -> &'static str {
-                    match id.id {
-                        0_usize => "Nothing",
-                        #( #enumerable_variants, )*
-                        _ => panic!("Invalid symbol requested from id: {}", id.id),
+                fn variant_count() -> usize { #variant_count }
+                fn default_of(id: &rust_automata::EnumId<#enum_ident>) -> Option<Self> {
+                    use rust_automata::__private::{ViaDefault, ViaNone};
+                    match id.index() {
+                        0 => Some(Self::Nothing(#nothing_ident)),
+                        #( #default_variants, )*
+                        _ => None,
                     }
                 }
+                fn from_any(
+                    id: &rust_automata::EnumId<#enum_ident>,
+                    value: Box<dyn std::any::Any>,
+                ) -> Result<Self, Box<dyn std::any::Any>> {
+                    match id.index() {
+                        0 => value.downcast::<#nothing_ident>().map(|v| Self::Nothing(*v)),
+                        #( #any_variants, )*
+                        _ => Err(value),
+                    }
+                }
+                fn as_any(&self) -> &dyn std::any::Any {
+                    match self {
+                        Self::Nothing(v) => v,
+                        #( Self::#alphabet_ids(v) => v ),*
+                    }
+                }
+                fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+                    match self {
+                        Self::Nothing(v) => v,
+                        #( Self::#alphabet_ids(v) => v ),*
+                    }
+                }
+                fn get_variant(id: &rust_automata::EnumId<#enum_ident>) -> &'static str {
+                    rust_automata::__private::variant_name(&["Nothing", #( #variant_names ),*], id)
+                }
             }
             impl #enum_ident {
                 #alphabet_getters
@@ -356,7 +490,7 @@ mod building_blocks {
                 fn from(o: #enum_ident) -> Self {
                     match o {
                         #enum_ident::Nothing(v) => v,
-                        _ => panic!("Invalid symbol requested from {}", stringify!(#enum_ident)),
+                        _ => rust_automata::__private::invalid_symbol(stringify!(#enum_ident)),
                     }
                 }
             }
@@ -367,15 +501,21 @@ mod building_blocks {
         derive_attr: &TokenStream2,
         enum_ident: &Ident,
         state_paths: &Vec<Path>,
+        generate_getters: bool,
     ) -> TokenStream2 {
         let state_ids: Vec<_> = state_paths.iter().map(last).collect();
         let enumerable_ids_states = generate_enum_matches(&state_ids);
-        let enumerable_variants = generate_enum_variants(&state_ids);
-        let state_getters = build_getters(state_paths);
+        let variant_names = state_ids.iter().map(|id| id.to_string());
+        let state_getters = build_getters(state_paths, generate_getters);
         let state_conversions = build_conversions(enum_ident, state_paths);
+        let variant_count = state_paths.len() + 1;
+        let repr_attr = repr_attr(variant_count);
+        let default_variants = generate_default_variants(state_paths);
+        let any_variants = generate_any_variants(state_paths);
 
         quote! {
             #derive_attr
+            #repr_attr
             pub enum #enum_ident {
                 Failure(()),
                  #( #state_ids ( super::#state_paths ) ),*
@@ -391,13 +531,38 @@ mod building_blocks {
                         #( #enumerable_ids_states ),*
                     }
                 }
-                fn get_variant(id: &rust_automata::EnumId<#enum_ident>) -> &'static str {
-                    match id.id {
-                        0_usize => "Failure",
-                        #( #enumerable_variants, )*
-                        _ => panic!("Invalid symbol requested from id: {}", id.id),
+                fn variant_count() -> usize { #variant_count }
+                fn default_of(id: &rust_automata::EnumId<#enum_ident>) -> Option<Self> {
+                    use rust_automata::__private::{ViaDefault, ViaNone};
+                    match id.index() {
+                        #( #default_variants, )*
+                        _ => None,
+                    }
+                }
+                fn from_any(
+                    id: &rust_automata::EnumId<#enum_ident>,
+                    value: Box<dyn std::any::Any>,
+                ) -> Result<Self, Box<dyn std::any::Any>> {
+                    match id.index() {
+                        #( #any_variants, )*
+                        _ => Err(value),
+                    }
+                }
+                fn as_any(&self) -> &dyn std::any::Any {
+                    match self {
+                        Self::Failure(v) => v,
+                        #( Self::#state_ids(v) => v ),*
+                    }
+                }
+                fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+                    match self {
+                        Self::Failure(v) => v,
+                        #( Self::#state_ids(v) => v ),*
                     }
                 }
+                fn get_variant(id: &rust_automata::EnumId<#enum_ident>) -> &'static str {
+                    rust_automata::__private::variant_name(&["Failure", #( #variant_names ),*], id)
+                }
             }
             impl #enum_ident {
                 #state_getters
@@ -406,20 +571,348 @@ mod building_blocks {
         }
     }
 
+    /// FNV-1a hash of the states, alphabets and transitions, stable across compilers.
+    pub fn schema_hash(m: &MachineAttr) -> u64 {
+        let names = |paths: &[Path]| paths.iter().map(key).collect::<Vec<_>>().join(",");
+        let mut schema = format!(
+            "{};{};{}",
+            names(&m.states),
+            names(&m.inputs),
+            names(&m.outputs)
+        );
+        for tr in &m.transitions {
+            let opt = |p: &Option<Path>| p.as_ref().map(key).unwrap_or_default();
+            schema += &format!(
+                ";{},{}->{},{}",
+                key(&tr.from_state),
+                opt(&tr.input),
+                key(&tr.to_state),
+                opt(&tr.output)
+            );
+        }
+        schema.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        })
+    }
+
+    /// The code dispatching a transition: the items to put in the internal module, and the body
+    /// computing the next state and output of `old_state` and `input`.
+    pub struct DispatchCode {
+        pub items: TokenStream2,
+        pub body: TokenStream2,
+    }
+
+    /// Dispatch through a static table with a function per state and input, for `dispatch(table)`.
+    ///
+    /// A function matches only the transitions of its state and input, so a large machine doesn't
+    /// branch through all of them. The functions are hidden methods of the machine, so that the
+    /// handlers and guards are called as in the `match` dispatch.
+    pub fn build_dispatch_table(
+        m: &MachineAttr,
+        machine_ident: &Ident,
+        base: &str,
+        arms: Vec<TokenStream2>,
+    ) -> DispatchCode {
+        let state_enum_ident = format_ident!("{}State", base);
+        let input_enum_ident = format_ident!("{}Input", base);
+        let output_enum_ident = format_ident!("{}Output", base);
+        let num_states = m.states.len() + 1; // with Failure
+        let num_inputs = m.inputs.len() + 1; // with Nothing
+        let failure = format_ident!("__rust_automata_dispatch_failure");
+        let cell_fn =
+            |state: usize, input: usize| format_ident!("__rust_automata_dispatch_{state}_{input}");
+
+        let (state_indices, input_indices) = (symbol_indices(&m.states), symbol_indices(&m.inputs));
+        let mut cells: BTreeMap<(usize, usize), Vec<TokenStream2>> = BTreeMap::new();
+        let mut hints: HashMap<(usize, usize), Vec<Hint>> = HashMap::new();
+        for (tr, arm) in m.transitions.iter().zip(arms) {
+            let state = compute_symbol_index(Some(&tr.from_state), &state_indices, tr);
+            let input = compute_symbol_index(tr.input.as_ref(), &input_indices, tr);
+            cells.entry((state, input)).or_default().push(arm);
+            hints.entry((state, input)).or_default().push(tr.hint);
+        }
+        let cell_fns = cells.iter().map(|(&(state, input), arms)| {
+            let name = cell_fn(state, input);
+            let hints = &hints[&(state, input)];
+            let inline = if hints.contains(&Hint::Hot) {
+                quote! { #[inline(always)] }
+            } else if hints.iter().all(|hint| *hint == Hint::Cold) {
+                quote! { #[cold] #[inline(never)] }
+            } else {
+                quote! {}
+            };
+            quote! {
+                #[doc(hidden)]
+                #inline
+                #[allow(unreachable_patterns)]
+                fn #name(&mut self, old_state: #state_enum_ident, input: #input_enum_ident) -> (#state_enum_ident, #output_enum_ident) {
+                    match (old_state, input) {
+                        #( #arms , )*
+                        (_, _) => (#state_enum_ident::failure(), #output_enum_ident::nothing()),
+                    }
+                }
+            }
+        });
+        let rows = (0..num_states).map(|state| {
+            let row = (0..num_inputs).map(|input| {
+                let name = if cells.contains_key(&(state, input)) {
+                    cell_fn(state, input)
+                } else {
+                    failure.clone()
+                };
+                quote! { super::#machine_ident::#name }
+            });
+            quote! { [ #( #row ),* ] }
+        });
+
+        DispatchCode {
+            items: quote! {
+                type Dispatch = fn(&mut super::#machine_ident, #state_enum_ident, #input_enum_ident) -> (#state_enum_ident, #output_enum_ident);
+
+                static DISPATCH: [[Dispatch; #num_inputs]; #num_states] = [ #( #rows ),* ];
+
+                impl super::#machine_ident {
+                    #[doc(hidden)]
+                    fn #failure(&mut self, _: #state_enum_ident, _: #input_enum_ident) -> (#state_enum_ident, #output_enum_ident) {
+                        (#state_enum_ident::failure(), #output_enum_ident::nothing())
+                    }
+                    #( #cell_fns )*
+                }
+            },
+            body: quote! {
+                let dispatch = DISPATCH[old_state.enum_id().index()][input.enum_id().index()];
+                dispatch(self, old_state, input)
+            },
+        }
+    }
+
+    /// A static bitset of the (state, input) pairs with a transition, checked by `can_transition`
+    /// before the `match`, so that the guards are called only when a transition may be enabled.
+    pub fn build_transition_bitset(m: &MachineAttr) -> TokenStream2 {
+        let num_states = m.states.len() + 1; // with Failure
+        let num_inputs = m.inputs.len() + 1; // with Nothing
+        let words = num_inputs.div_ceil(64);
+        let (state_indices, input_indices) = (symbol_indices(&m.states), symbol_indices(&m.inputs));
+        let mut bits = vec![0u64; num_states * words];
+        for tr in &m.transitions {
+            let state = compute_symbol_index(Some(&tr.from_state), &state_indices, tr);
+            let input = compute_symbol_index(tr.input.as_ref(), &input_indices, tr);
+            bits[state * words + input / 64] |= 1 << (input % 64);
+        }
+        let len = bits.len();
+        quote! {
+            static CAN_TRANSITION: [u64; #len] = [ #( #bits ),* ];
+            let (state_idx, input_idx) = (state.enum_id().index(), input.index());
+            if input_idx >= #num_inputs
+                || CAN_TRANSITION[state_idx * #words + input_idx / 64] & (1 << (input_idx % 64)) == 0
+            {
+                return None;
+            }
+        }
+    }
+
+    /// `const fn`s answering which transitions exist, from the ids alone, for compile-time checks.
+    ///
+    /// A (state, input) pair maps to the target of its first transition, unless that one has a
+    /// guard, as then the target is only known at runtime.
+    pub fn build_const_transitions(
+        m: &MachineAttr,
+        machine_ident: &Ident,
+        base: &str,
+    ) -> TokenStream2 {
+        let state_enum_ident = format_ident!("{}State", base);
+        let input_enum_ident = format_ident!("{}Input", base);
+        let output_enum_ident = format_ident!("{}Output", base);
+        let state_indices = symbol_indices(&m.states);
+        let input_indices = symbol_indices(&m.inputs);
+        let output_indices = symbol_indices(&m.outputs);
+
+        let mut cells: BTreeMap<(usize, usize), &Transition> = BTreeMap::new();
+        for tr in &m.transitions {
+            let state = compute_symbol_index(Some(&tr.from_state), &state_indices, tr);
+            let input = compute_symbol_index(tr.input.as_ref(), &input_indices, tr);
+            cells.entry((state, input)).or_insert(tr);
+        }
+        let pairs = cells
+            .keys()
+            .map(|(state, input)| quote! { (#state, #input) => true });
+        let targets = cells.iter().map(|((state, input), tr)| {
+            if tr.guard.is_some() {
+                return quote! { (#state, #input) => None };
+            }
+            let to = compute_symbol_index(Some(&tr.to_state), &state_indices, tr);
+            let output = compute_symbol_index(tr.output.as_ref(), &output_indices, tr);
+            quote! { (#state, #input) => Some((EnumId::new(#to), EnumId::new(#output))) }
+        });
+        quote! {
+            impl super::#machine_ident {
+                /// Whether the state has a transition for the input, regardless of the guards.
+                /// Usable in constants, like `const _: () = assert!(...)`.
+                pub const fn has_transition(state: EnumId<#state_enum_ident>, input: EnumId<#input_enum_ident>) -> bool {
+                    match (state.index(), input.index()) {
+                        #( #pairs , )*
+                        _ => false,
+                    }
+                }
+
+                /// The next state and output for the input, if it does not depend on a guard.
+                /// Usable in constants, like `const _: () = assert!(...)`.
+                pub const fn transition_target(
+                    state: EnumId<#state_enum_ident>,
+                    input: EnumId<#input_enum_ident>,
+                ) -> Option<(EnumId<#state_enum_ident>, EnumId<#output_enum_ident>)> {
+                    match (state.index(), input.index()) {
+                        #( #targets , )*
+                        _ => None,
+                    }
+                }
+            }
+        }
+    }
+
+    /// `consume_*` methods per input, for `consume_methods(true)`: the trait declaring them,
+    /// and its implementation for the internal module.
+    ///
+    /// The methods are in a trait, as the crate of the machine cannot add inherent methods to
+    /// `StateMachine`. Each method matches only the transitions of its input, so the input
+    /// goes to the handler as is, without the input enum.
+    pub fn build_consume_methods(
+        m: &MachineAttr,
+        machine_ident: &Ident,
+        base: &str,
+        vis: &syn::Visibility,
+    ) -> (TokenStream2, TokenStream2) {
+        if !m.consume_methods {
+            return (quote! {}, quote! {});
+        }
+        let trait_ident = format_ident!("{}Consume", machine_ident);
+        let state_enum_ident = format_ident!("{}State", base);
+        let output_enum_ident = format_ident!("{}Output", base);
+        let nothing_ident = format_ident!("{}Nothing", base);
+        let output_indices = symbol_indices(&m.outputs);
+        let mut by_input: HashMap<String, Vec<TokenStream2>> = HashMap::new();
+        for (idx, tr) in m.transitions.iter().enumerate() {
+            let Some(input) = &tr.input else { continue };
+            let from_id = last(&tr.from_state);
+            let to_id = last(&tr.to_state);
+            let to_path = &tr.to_state;
+            let state_var = format_ident!("state{idx}");
+            let input_var = format_ident!("input{idx}");
+            let out_type = match &tr.output {
+                Some(out_path) => quote! { super::#out_path },
+                None => quote! { #nothing_ident },
+            };
+            let out_idx = compute_symbol_index(tr.output.as_ref(), &output_indices, tr);
+            let (transition_call, value_instantiation) =
+                build_handler_code(tr, &state_var, &input_var, &nothing_ident);
+            let guard_call = build_guard_code(tr, &state_var);
+            let cold_path =
+                (tr.hint == Hint::Cold).then(|| quote! { rust_automata::__private::cold_path(); });
+            by_input.entry(key(input)).or_default().push(quote! {
+                #state_enum_ident::#from_id(#state_var) #guard_call => {
+                    #cold_path
+                    let #input_var = input;
+                    let next_val: super::#to_path;
+                    let out_val: #out_type;
+                    #transition_call
+                    #value_instantiation
+                    let _ = out_val;
+                    (#state_enum_ident::#to_id(next_val), rust_automata::EnumId::new(#out_idx))
+                }
+            });
+        }
+
+        let mut declarations = Vec::new();
+        let mut methods = Vec::new();
+        let mut functions = Vec::new();
+        for input in &m.inputs {
+            let method = format_ident!("consume_{}", snake_path(input));
+            let function = format_ident!("__rust_automata_consume_{}", snake_path(input));
+            let arms = by_input.remove(&key(input)).unwrap_or_default();
+            let doc = format!("Consume a `{}`, do not care about the output.", last(input));
+            declarations.push(quote! {
+                #[doc = #doc]
+                fn #method(&mut self, input: #input);
+            });
+            methods.push(quote! {
+                #[inline]
+                fn #method(&mut self, input: super::#input) {
+                    self.consume_direct(input, super::#machine_ident::#function);
+                }
+            });
+            functions.push(quote! {
+                #[doc(hidden)]
+                #[inline]
+                #[allow(unreachable_patterns)]
+                fn #function(&mut self, state: &mut #state_enum_ident, input: super::#input) -> rust_automata::EnumId<#output_enum_ident> {
+                    let old_state = std::mem::replace(state, #state_enum_ident::failure());
+                    let (new_state, output) = match old_state {
+                        #( #arms , )*
+                        _ => (#state_enum_ident::failure(), rust_automata::EnumId::new(0)),
+                    };
+                    *state = new_state;
+                    output
+                }
+            });
+        }
+        let doc = format!(
+            "The `consume_*` methods of a `StateMachine<{machine_ident}>`, one per input.\n\n\
+             Like `StateMachine::consume`, but the input goes to the transition as is, without \
+             the conversions into the input enum."
+        );
+        let declaration = quote! {
+            #[doc = #doc]
+            #vis trait #trait_ident {
+                #( #declarations )*
+            }
+        };
+        let implementation = quote! {
+            impl super::#trait_ident for rust_automata::StateMachine<super::#machine_ident> {
+                #( #methods )*
+            }
+            impl super::#machine_ident {
+                #( #functions )*
+            }
+        };
+        (declaration, implementation)
+    }
+
+    /// Groups the code generated per transition by the state the transition starts from,
+    /// keeping the order of declaration within a state.
+    ///
+    /// Matching the state first and the transitions of that state in a small inner `match` keeps
+    /// the compile time linear in the number of transitions for large machines.
+    pub fn by_from_state<T>(
+        m: &MachineAttr,
+        state_indices: &HashMap<String, usize>,
+        code: impl Iterator<Item = T>,
+    ) -> BTreeMap<usize, Vec<T>> {
+        let mut chunks: BTreeMap<usize, Vec<T>> = BTreeMap::new();
+        for (tr, code) in m.transitions.iter().zip(code) {
+            let state = compute_symbol_index(Some(&tr.from_state), state_indices, tr);
+            chunks.entry(state).or_default().push(code);
+        }
+        chunks
+    }
+
+    /// Indices of the states or symbols by name, so that looking them up does not scan the list.
+    pub fn symbol_indices(symbols: &[syn::Path]) -> HashMap<String, usize> {
+        symbols
+            .iter()
+            .enumerate()
+            .map(|(idx, p)| (key(p), idx + 1))
+            .collect()
+    }
+
     pub fn compute_symbol_index(
         needle: Option<&syn::Path>,
-        symbols: &[syn::Path],
+        indices: &HashMap<String, usize>,
         tr: &parser::Transition,
     ) -> usize {
         match needle {
-            Some(symbol) => {
-                1 + symbols
-                    .iter()
-                    .position(|p| key(p) == key(symbol))
-                    .unwrap_or_else(|| {
-                        panic!("Symbol {} not found in transition: {}", key(symbol), tr);
-                    })
-            }
+            Some(symbol) => *indices.get(&key(symbol)).unwrap_or_else(|| {
+                panic!("Symbol {} not found in transition: {}", key(symbol), tr);
+            }),
             None => 0,
         }
     }
@@ -483,6 +976,9 @@ pub fn state_machine(attr: TokenStream, item: TokenStream) -> TokenStream {
             }
         });
 
+    let state_indices = symbol_indices(state_paths);
+    let input_indices = symbol_indices(input_paths);
+    let output_indices = symbol_indices(output_paths);
     let transition_match_arms = m.transitions.iter().enumerate().map(|(idx, tr)| {
         let from_id = last(&tr.from_state);
         let to_id = last(&tr.to_state);
@@ -503,50 +999,191 @@ pub fn state_machine(attr: TokenStream, item: TokenStream) -> TokenStream {
         };
         let (transition_call, value_instantiation) = build_handler_code(tr, &state_var, &input_var, &nothing_ident);
         let guard_call = build_guard_code(tr, &state_var);
+        let cold_path = (tr.hint == Hint::Cold).then(|| quote! { rust_automata::__private::cold_path(); });
 
+        // The enums are named explicitly, as the table dispatch functions are not in the trait impl.
         quote! {
-            (Self::State::#from_id(#state_var), Self::Input::#inp_id(#input_var)) #guard_call => {
+            (#state_enum_ident::#from_id(#state_var), #input_enum_ident::#inp_id(#input_var)) #guard_call => {
+                #cold_path
                 #type_declaration
                 #transition_call
                 #value_instantiation
                 (
-                    Self::State::#to_id(next_val),
-                    Self::Output::#out_id(out_val)
+                    #state_enum_ident::#to_id(next_val),
+                    #output_enum_ident::#out_id(out_val)
                 )
             }
         }
     });
+    let dispatch = if m.table_dispatch {
+        build_dispatch_table(&m, &machine_ident, &base, transition_match_arms.collect())
+    } else {
+        let pair = |tr: &Transition| {
+            let state = compute_symbol_index(Some(&tr.from_state), &state_indices, tr);
+            let input = compute_symbol_index(tr.input.as_ref(), &input_indices, tr);
+            (state, input)
+        };
+        let hot_transitions = m.transitions.iter().filter(|tr| tr.hint == Hint::Hot);
+        let hot: HashSet<_> = hot_transitions.map(pair).collect();
+        let arms = m.transitions.iter().zip(transition_match_arms);
+        let arms = arms.map(|(tr, arm)| (hot.contains(&pair(tr)), arm));
+        let chunks = by_from_state(&m, &state_indices, arms).into_iter();
+        let chunks = chunks.map(|(state_idx, mut arms)| {
+            // Match the inputs with a hot transition first. The arms of different inputs don't
+            // overlap, and the sort is stable, so the transitions of an input keep their priority.
+            arms.sort_by_key(|(hot, _)| !hot);
+            let arms = arms.into_iter().map(|(_, arm)| arm);
+            quote! {
+                #state_idx => match (old_state, input) {
+                    #( #arms , )*
+                    (_, _) => (Self::State::failure(), Self::Output::nothing()),
+                }
+            }
+        });
+        DispatchCode {
+            items: quote! {},
+            body: quote! {
+                match old_state.enum_id().index() {
+                    #( #chunks , )*
+                    _ => (Self::State::failure(), Self::Output::nothing()),
+                }
+            },
+        }
+    };
+    let (dispatch_items, dispatch_body) = (dispatch.items, dispatch.body);
+    let const_transitions = build_const_transitions(&m, &machine_ident, &base);
+    let (consume_trait, consume_methods) = build_consume_methods(&m, &machine_ident, &base, &vis);
     let can_transition_match_arms = m.transitions.iter().enumerate().map(|(idx, tr) | {
         let from_id = last(&tr.from_state);
         let state_var = format_ident!("state{idx}");
-        let input_idx: usize = compute_symbol_index(tr.input.as_ref(), input_paths, tr);
-        let output_idx: usize = compute_symbol_index(tr.output.as_ref(), output_paths, tr);
+        let input_idx: usize = compute_symbol_index(tr.input.as_ref(), &input_indices, tr);
+        let output_idx: usize = compute_symbol_index(tr.output.as_ref(), &output_indices, tr);
+        let to_idx: usize = compute_symbol_index(Some(&tr.to_state), &state_indices, tr);
         let guard_call = build_guard_code(tr, &state_var);
         quote! {
-            (Self::State::#from_id(#state_var), #input_idx) #guard_call => Some(rust_automata::EnumId::new(#output_idx))
+            (Self::State::#from_id(#state_var), #input_idx) #guard_call => Some((rust_automata::EnumId::new(#to_idx), rust_automata::EnumId::new(#output_idx)))
         }
     });
 
-    let input_alphabet = build_alphabet(&derive_attr, &input_enum_ident, &nothing_ident, input_paths);
-    let output_alphabet = build_alphabet(&derive_attr, &output_enum_ident, &nothing_ident, output_paths);
-    let state_set = build_set(&derive_attr, &state_enum_ident, state_paths);
+    let can_transition_chunks = by_from_state(&m, &state_indices, can_transition_match_arms);
+    let can_transition_chunks = can_transition_chunks.into_iter().map(|(state_idx, arms)| {
+        quote! {
+            #state_idx => match (state, input.index()) {
+                #( #arms , )*
+                (_, _) => None,
+            }
+        }
+    });
+
+    let transition_bitset = build_transition_bitset(&m);
+
+    let graph_edges = m.transitions.iter().map(|tr| {
+        let from = compute_symbol_index(Some(&tr.from_state), &state_indices, tr);
+        let input = compute_symbol_index(tr.input.as_ref(), &input_indices, tr);
+        let to = compute_symbol_index(Some(&tr.to_state), &state_indices, tr);
+        let output = compute_symbol_index(tr.output.as_ref(), &output_indices, tr);
+        let guard = match &tr.guard {
+            Some(g) => {
+                let g = parser::guard_expr_to_string(g, &parser::key);
+                quote! { Some(#g) }
+            }
+            None => quote! { None },
+        };
+        let handler = match &tr.handler {
+            Some(h) => {
+                let h = h.to_string();
+                quote! { Some(#h) }
+            }
+            None => quote! { None },
+        };
+        quote! {
+            rust_automata::graph::Edge { from: #from, input: #input, to: #to, output: #output, guard: #guard, handler: #handler }
+        }
+    });
+    let graph_names = |paths: &Vec<Path>| {
+        paths
+            .iter()
+            .map(|p| last(p).to_string())
+            .collect::<Vec<_>>()
+    };
+    let graph_states = graph_names(state_paths);
+    let graph_inputs = graph_names(input_paths);
+    let graph_outputs = graph_names(output_paths);
+    let graph_schema = schema_hash(&m);
+    let all_symbols = |names: &[String]| {
+        names
+            .iter()
+            .enumerate()
+            .map(|(idx, name)| {
+                let id = idx + 1;
+                quote! { (rust_automata::EnumId::new(#id), #name) }
+            })
+            .collect::<Vec<_>>()
+    };
+    let all_states = all_symbols(&graph_states);
+    let all_inputs = all_symbols(&graph_inputs);
+    let all_outputs = all_symbols(&graph_outputs);
+
+    let input_alphabet = build_alphabet(&derive_attr, &input_enum_ident, &nothing_ident, input_paths, m.generate_getters);
+    let output_alphabet = build_alphabet(&derive_attr, &output_enum_ident, &nothing_ident, output_paths, m.generate_getters);
+    let state_set = build_set(&derive_attr, &state_enum_ident, state_paths, m.generate_getters);
 
     let sig_checks = m
         .transitions
         .iter()
         .map(|tr| make_handler_sig_check(tr, &machine_ident));
+    let missing_handlers = make_missing_handler_fallbacks(&m, &machine_ident);
 
     // ────────────────── annotations ──────────────────
-    let mermaid_attr = annotations::mermaid_attr(&m);
-    let dsl_attr = annotations::dsl_attr(&m);
+    // Formats can be turned off per machine, on top of the crate features.
+    let enabled = |format, generate: &dyn Fn() -> TokenStream2| {
+        if m.diagram.enabled(format) {
+            generate()
+        } else {
+            quote!()
+        }
+    };
+    let mermaid_attr = enabled("mermaid", &|| annotations::mermaid_attr(&m));
+    let plantuml_attr = enabled("plantuml", &|| annotations::plantuml_attr(&m));
+    let plantuml_items = enabled("plantuml", &|| {
+        annotations::plantuml_items(&m, &machine_ident)
+    });
+    let dot_attr = enabled("dot", &|| annotations::dot_attr(&m, &machine_ident));
+    let dot_items = enabled("dot", &|| annotations::dot_items(&m, &machine_ident));
+    let svg_attr = enabled("svg", &|| annotations::svg_attr(&m, &machine_ident));
+    let mermaid_const = enabled("mermaid", &|| {
+        annotations::mermaid_const(&m, &machine_ident)
+    });
+    let dot_const = enabled("dot", &|| annotations::dot_const(&m, &machine_ident));
+    let d2_attr = enabled("d2", &|| annotations::d2_attr(&m));
+    let d2_items = enabled("d2", &|| annotations::d2_items(&m, &machine_ident));
+    let scxml_items = enabled("scxml", &|| annotations::scxml_items(&m, &machine_ident));
+    let html_items = enabled("html", &|| annotations::html_items(&m, &machine_ident));
+    let csv_items = enabled("csv", &|| annotations::csv_items(&m, &machine_ident));
+    let drawio_items = enabled("drawio", &|| annotations::drawio_items(&m, &machine_ident));
+    let dsl_attr = enabled("dsl", &|| annotations::dsl_attr(&m));
+    let diagram_files = annotations::write_diagram_files(&m, &machine_ident);
 
     // ────────────────── put everything together ──────────────────
     let output = quote! {
         #mermaid_attr
+        #plantuml_attr
+        #dot_attr
+        #svg_attr
+        #d2_attr
         #dsl_attr
         #machine_ts
+        #plantuml_items
+        #dot_items
+        #d2_items
+        #scxml_items
+        #html_items
+        #csv_items
+        #drawio_items
+        #diagram_files
 
         #( #maybe_generate_structs )*
+        #consume_trait
 
         #[allow(non_snake_case)]
         #[doc(hidden)]
@@ -560,40 +1197,58 @@ pub fn state_machine(attr: TokenStream, item: TokenStream) -> TokenStream {
             #input_alphabet
             #output_alphabet
 
+            #mermaid_const
+            #dot_const
+
+            #missing_handlers
+            #dispatch_items
+            #const_transitions
+            #consume_methods
+
             impl rust_automata::StateMachineImpl for super::#machine_ident {
                 type Input  = #input_enum_ident;
                 type State  = #state_enum_ident;
                 type Output = #output_enum_ident;
                 type InitialState = super::#initial_state_ident;
                 type Nothing = #nothing_ident;
-                fn transition(
-                    &mut self,
-                    mut state: rust_automata::Takeable<Self::State>,
-                    input: Self::Input,
-                ) -> (rust_automata::Takeable<Self::State>, Self::Output) {
+                const ALL_STATES: &'static [(EnumId<Self::State>, &'static str)] = &[ #( #all_states ),* ];
+                const ALL_INPUTS: &'static [(EnumId<Self::Input>, &'static str)] = &[ #( #all_inputs ),* ];
+                const ALL_OUTPUTS: &'static [(EnumId<Self::Output>, &'static str)] = &[ #( #all_outputs ),* ];
+                fn transition(&mut self, state: &mut Self::State, input: Self::Input) -> Self::Output {
 
                     // Make nice error messages
                     #( #sig_checks )*
 
-                    let out = state.borrow_result(|old_state| {
-                        match (old_state, input) {
-                            #( #transition_match_arms , )*
-                            (_, _) => { (Self::State::failure(), Self::Output::nothing()) }
-                        }
-                    });
-                    (state, out)
+                    // Failure stays in place if a handler panics.
+                    let old_state = std::mem::replace(state, <Self::State as rust_automata::StateTrait>::failure());
+                    let (new_state, out) = { #dispatch_body };
+                    *state = new_state;
+                    out
                 }
 
-                fn can_transition(&self, state: &Self::State, input: EnumId<Self::Input>) -> Option<EnumId<Self::Output>> {
-                    match (state, input.id) {
-                        #( #can_transition_match_arms , )*
-                        (_, _) => None,
+                fn can_transition(&self, state: &Self::State, input: EnumId<Self::Input>) -> Option<(EnumId<Self::State>, EnumId<Self::Output>)> {
+                    #transition_bitset
+                    match state_idx {
+                        #( #can_transition_chunks , )*
+                        _ => None,
                     }
                 }
 
                 fn name() -> &'static str {
                     stringify!(#machine_ident)
                 }
+
+                fn graph() -> &'static rust_automata::graph::MachineGraph {
+                    static GRAPH: rust_automata::graph::MachineGraph = rust_automata::graph::MachineGraph {
+                        name: stringify!(#machine_ident),
+                        schema: #graph_schema,
+                        states: &["Failure", #( #graph_states ),*],
+                        inputs: &["Nothing", #( #graph_inputs ),*],
+                        outputs: &["Nothing", #( #graph_outputs ),*],
+                        transitions: &[ #( #graph_edges ),* ],
+                    };
+                    &GRAPH
+                }
             }
         }
     };
@@ -623,12 +1278,13 @@ pub fn display_derive(input: TokenStream) -> TokenStream {
         }
     };
 
-    // For each variant, create a match arm that writes the variant's name.
+    // For each variant, create a match arm that gives the variant's name,
+    // so that a single `write_str` is expanded rather than a `write!` per variant.
     let arms = data_enum.variants.into_iter().map(|variant| {
         let variant_ident = variant.ident;
         let variant_str = variant_ident.to_string();
         quote! {
-            Self::#variant_ident(_) => write!(f, "{}", #variant_str)
+            Self::#variant_ident(_) => #variant_str
         }
     });
 
@@ -636,12 +1292,54 @@ pub fn display_derive(input: TokenStream) -> TokenStream {
     let expanded = quote! {
         impl std::fmt::Display for #name {
             fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-                match self {
+                f.write_str(match self {
                     #(#arms),*
-                }
+                })
             }
         }
     };
 
     expanded.into()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_large_machine() {
+        // Suggestions for unknown symbols must not be computed for the declared ones.
+        let states = (0..2000).map(|i| format!("S{i}")).collect::<Vec<_>>();
+        let transitions = (0..4000)
+            .map(|i| {
+                format!(
+                    "(S{}, I{}) -> (S{}, O{})",
+                    i % 2000,
+                    i % 50,
+                    (i + 1) % 2000,
+                    i % 50
+                )
+            })
+            .collect::<Vec<_>>();
+        let symbols = |prefix: &str| (0..50).map(|i| format!("{prefix}{i}")).collect::<Vec<_>>();
+        let src = format!(
+            "states({}), inputs({}), outputs({}), terminal(S0), transitions({})",
+            states.join(", "),
+            symbols("I").join(", "),
+            symbols("O").join(", "),
+            transitions.join(", ")
+        );
+        let m: MachineAttr = syn::parse_str(&src).unwrap();
+        let calls = || crate::util::SUGGESTIONS.with(|calls| calls.get());
+        let before = calls();
+        assert!(building_blocks::validate_machine_attr(&m).is_empty());
+        assert_eq!(calls(), before);
+
+        // An unknown symbol is still reported with a suggestion.
+        let src = "states(Open, Closed), transitions((Closd) -> (Open))";
+        let m: MachineAttr = syn::parse_str(src).unwrap();
+        let errors = building_blocks::validate_machine_attr(&m).to_string();
+        assert!(errors.contains("did you mean `Closed`?"), "{errors}");
+        assert_eq!(calls(), before + 1);
+    }
+}
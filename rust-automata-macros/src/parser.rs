@@ -1,7 +1,8 @@
 use quote::ToTokens;
+use syn::ext::IdentExt;
 use syn::punctuated::Punctuated;
 use syn::{
-    parenthesized,
+    bracketed, parenthesized,
     parse::{Parse, ParseStream, Result},
     Ident, Path, Token,
 };
@@ -10,9 +11,10 @@ use syn::{
 ///
 /// Grammar accepted now:
 /// ```text
-/// (from_state[, input]) -> (to_state[, output]) [ : guard_expr ] [ = handler ]
+/// [ [hot] | [cold] ] (from_state[, input]) -> (to_state[, output]) [ : guard_expr ] [ = handler ]
 /// ```
 /// * `guard_expr` and `handler` are optional.
+/// * `[hot]` and `[cold]` are optional hints for the code generation, see [`Hint`].
 /// * `from_state`, `input`, `to_state`, `output` are all parsed as `Path`,
 ///   so module‐qualified identifiers work out of the box.
 /// * `guard_expr` is parsed as a boolean expression (can use &&, ||, !, etc.)
@@ -26,10 +28,38 @@ pub struct Transition {
     // See also `try_match_guard`
     pub guard: Option<syn::Expr>,
     pub handler: Option<Ident>,
+    pub hint: Hint,
+}
+
+/// How often a transition is expected to be taken, from a `[hot]` or `[cold]` prefix.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Hint {
+    #[default]
+    None,
+    /// Inlined into the table dispatch, and matched first by the `match` dispatch.
+    Hot,
+    /// Kept out of the way of the other transitions, like an error path.
+    Cold,
 }
 
 impl Parse for Transition {
     fn parse(input: ParseStream) -> Result<Self> {
+        // -------------------------
+        // Optional hint in brackets
+        // -------------------------
+        let hint = if input.peek(syn::token::Bracket) {
+            let content;
+            bracketed!(content in input);
+            let hint: Ident = content.parse()?;
+            match hint.to_string().as_str() {
+                "hot" => Hint::Hot,
+                "cold" => Hint::Cold,
+                _ => return Err(syn::Error::new_spanned(hint, "expected `hot` or `cold`")),
+            }
+        } else {
+            Hint::None
+        };
+
         // -------------------------
         // Left‑hand side
         // -------------------------
@@ -94,6 +124,7 @@ impl Parse for Transition {
             output: output_event,
             guard,
             handler,
+            hint,
         })
     }
 }
@@ -177,79 +208,120 @@ impl Display for Transition {
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Options of the generated diagrams, from the `diagram(..)` section.
+///
+/// ```text
+/// diagram(theme = dark, rankdir = TB, hide_outputs)
+/// ```
+/// * `theme` is a Mermaid theme: `default`, `base`, `dark`, `forest` or `neutral`.
+///   DOT only distinguishes `dark`.
+/// * `rankdir` is the layout direction of Mermaid and DOT: `LR`, `RL`, `TB` or `BT`.
+/// * `layout` is the kind of Mermaid diagram: a `state` diagram, or a `flowchart`,
+///   which lays out machines with many self-loops better.
+/// * `hide_outputs` leaves the outputs out of the edge labels.
+/// * `merge_edges` draws the transitions between the same pair of states as one edge,
+///   with a label line per transition.
+/// * `show_failure` adds the implicit `Failure` state, with an edge from every state
+///   labelled with the inputs it has no transition for.
+/// * `mermaid`, `plantuml`, `dot`, `svg`, `d2`, `scxml`, `html`, `csv`, `drawio` and `dsl`
+///   turn the formats enabled by the crate features off for this machine with `= false`.
+#[derive(Default)]
+pub struct DiagramOptions {
+    pub theme: Option<Ident>,
+    pub rankdir: Option<Ident>,
+    pub layout: Option<Ident>,
+    pub hide_outputs: bool,
+    pub merge_edges: bool,
+    pub show_failure: bool,
+    pub disabled: Vec<String>,
+}
 
-    #[test]
-    fn parses_full_form() {
-        let src = r#"(S1) -> (S2, E1) : guard_xyz = handler_xyz"#;
-        let t: Transition = syn::parse_str(src).unwrap();
-        assert!(t.guard.is_some());
-        assert_eq!(t.handler.unwrap().to_string(), "handler_xyz");
-        assert_eq!(
-            guard_expr_to_string(&t.guard.unwrap(), &|p| key(p)),
-            "guard_xyz"
-        );
+impl DiagramOptions {
+    /// Whether the format isn't turned off for this machine. The crate feature decides the rest.
+    pub fn enabled(&self, format: &str) -> bool {
+        !self.disabled.iter().any(|f| f == format)
     }
+}
 
-    #[test]
-    fn parses_minimal_form() {
-        let src = "(A) -> (B)";
-        let t: Transition = syn::parse_str(src).unwrap();
-        assert!(t.input.is_none());
-        assert!(t.output.is_none());
-        assert!(t.guard.is_none());
-        assert!(t.handler.is_none());
+impl Parse for DiagramOptions {
+    fn parse(input: ParseStream) -> Result<Self> {
+        const THEMES: &[&str] = &["default", "base", "dark", "forest", "neutral"];
+        const RANKDIRS: &[&str] = &["LR", "RL", "TB", "BT"];
+        const LAYOUTS: &[&str] = &["state", "flowchart"];
+        const FORMATS: &[&str] = &[
+            "mermaid", "plantuml", "dot", "svg", "d2", "scxml", "html", "csv", "drawio", "dsl",
+        ];
+        let mut options = Self::default();
+        while !input.is_empty() {
+            let key: Ident = input.parse()?;
+            match &*key.to_string() {
+                "theme" => options.theme = Some(parse_choice(input, THEMES)?),
+                "rankdir" => options.rankdir = Some(parse_choice(input, RANKDIRS)?),
+                "layout" => options.layout = Some(parse_choice(input, LAYOUTS)?),
+                "hide_outputs" => options.hide_outputs = parse_flag(input)?,
+                "merge_edges" => options.merge_edges = parse_flag(input)?,
+                "show_failure" => options.show_failure = parse_flag(input)?,
+                format if FORMATS.contains(&format) => {
+                    options.disabled.retain(|f| f != format);
+                    if !parse_flag(input)? {
+                        options.disabled.push(format.to_string());
+                    }
+                }
+                _ => return Err(syn::Error::new_spanned(key, "unknown diagram option")),
+            }
+            if input.peek(Token![,]) {
+                input.parse::<Token![,]>()?;
+            }
+        }
+        Ok(options)
     }
+}
 
-    #[test]
-    fn parses_handler_only() {
-        let src = "(A) -> (B) = handler_xyz";
-        let t: Transition = syn::parse_str(src).unwrap();
-        assert!(t.input.is_none());
-        assert!(t.output.is_none());
-        assert!(t.guard.is_none());
-        assert_eq!(t.handler.unwrap().to_string(), "handler_xyz");
+// A bare flag is `true`.
+fn parse_flag(input: ParseStream) -> Result<bool> {
+    if input.peek(Token![=]) {
+        input.parse::<Token![=]>()?;
+        parse_bool(input)
+    } else {
+        Ok(true)
     }
+}
 
-    #[test]
-    fn parses_complex_guard() {
-        let src = r#"(S1) -> (S2) : a && b || !c"#;
-        let t: Transition = syn::parse_str(src).unwrap();
-        assert!(t.guard.is_some());
-        assert_eq!(
-            guard_expr_to_string(&t.guard.unwrap(), &|p| key(p)),
-            "a && b || !c"
-        );
-    }
+fn parse_choice(input: ParseStream, choices: &[&str]) -> Result<Ident> {
+    input.parse::<Token![=]>()?;
+    parse_choice_value(input, choices)
+}
 
-    #[test]
-    fn parses_complex_guard_with_handler() {
-        let src = r#"(S1) -> (S2) : a && b || !c = handler_xyz"#;
-        let t: Transition = syn::parse_str(src).unwrap();
-        assert!(t.guard.is_some());
-        assert_eq!(t.handler.unwrap().to_string(), "handler_xyz");
-        assert_eq!(
-            guard_expr_to_string(&t.guard.unwrap(), &|p| key(p)),
-            "a && b || !c"
-        );
+fn parse_choice_value(input: ParseStream, choices: &[&str]) -> Result<Ident> {
+    // Any identifier, including keywords like `match`.
+    let value = Ident::parse_any(input)?;
+    if !choices.contains(&&*value.to_string()) {
+        let msg = format!("expected one of {}", choices.join(", "));
+        return Err(syn::Error::new_spanned(value, msg));
     }
+    Ok(value)
+}
 
-    #[test]
-    fn parses_invalid() {
-        let src = r#"blabla"#;
-        assert!(syn::parse_str::<Transition>(src).is_err());
-        let src = r#"(S1,S2)"#;
-        assert!(syn::parse_str::<Transition>(src).is_err());
-        let src = r#"(S1) -> (S2) : a(some_invalid_expr)"#;
-        assert!(syn::parse_str::<Transition>(src).is_err());
-        let src = r#"(S1) -> (S2) = some_invalid_expr(handler_xyz)"#;
-        assert!(syn::parse_str::<Transition>(src).is_err());
-        let src = r#"(S1) -> (S2) : a(some_invalid_expr)"#;
-        assert!(syn::parse_str::<Transition>(src).is_err());
-        let src = r#"(S1) -> (S2) = some_invalid_expr(handler_xyz)"#;
-        assert!(syn::parse_str::<Transition>(src).is_err());
+/// A named group of states, drawn together in the diagrams. Groups don't change the semantics.
+///
+/// ```text
+/// Connected: [Idle, Busy]
+/// ```
+pub struct Group {
+    pub name: Ident,
+    pub states: Vec<Path>,
+}
+
+impl Parse for Group {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let name = input.parse()?;
+        input.parse::<Token![:]>()?;
+        let content;
+        bracketed!(content in input);
+        Ok(Self {
+            name,
+            states: parse_path_list(&content)?,
+        })
     }
 }
 
@@ -258,26 +330,47 @@ mod tests {
 /// Grammar (sections may appear in any order)
 ///
 /// ```text
-/// section := inputs(..) | states(..) | outputs(..) | transitions(..)
+/// section := inputs(..) | states(..) | outputs(..) | transitions(..) | derive(..)
+///          | generate_structs(..) | diagram(..) | terminal(..) | groups(..)
+///          | dispatch(match | table) | consume_methods(..) | generate_getters(..)
 /// attr    := section (, section)*
 /// ```
 pub struct MachineAttr {
     pub inputs: Vec<Path>,
     pub states: Vec<Path>,
+    /// States where a run is complete, highlighted in the diagrams.
+    pub terminal: Vec<Path>,
+    /// Groups of states, drawn as clusters in the diagrams.
+    pub groups: Vec<Group>,
     pub outputs: Vec<Path>,
     pub transitions: Vec<Transition>,
     pub derives: Vec<Path>,
     pub generate_structs: bool,
+    /// Generate the `is_*`, `maybe_*` and direct getters of the state, input and output enums.
+    pub generate_getters: bool,
+    /// Dispatch transitions through a table of functions indexed by state and input, instead of
+    /// a single `match`.
+    pub table_dispatch: bool,
+    /// Generate a `consume_*` method per input, which runs the transitions of that input type
+    /// without wrapping it in the input enum.
+    pub consume_methods: bool,
+    pub diagram: DiagramOptions,
 }
 
 impl Parse for MachineAttr {
     fn parse(input: ParseStream) -> Result<Self> {
         let mut inputs: Option<Vec<Path>> = None;
         let mut states: Option<Vec<Path>> = None;
+        let mut terminal: Option<Vec<Path>> = None;
+        let mut groups: Option<Vec<Group>> = None;
         let mut outputs: Option<Vec<Path>> = None;
         let mut transitions: Option<Vec<Transition>> = None;
         let mut derives: Option<Vec<Path>> = None;
         let mut generate_structs: Option<bool> = None;
+        let mut generate_getters: Option<bool> = None;
+        let mut table_dispatch: Option<bool> = None;
+        let mut consume_methods: Option<bool> = None;
+        let mut diagram: Option<DiagramOptions> = None;
         while !input.is_empty() {
             let section: Ident = input.parse()?;
             let content;
@@ -290,6 +383,13 @@ impl Parse for MachineAttr {
                 "states" => {
                     states = Some(parse_path_list(&content)?);
                 }
+                "terminal" => {
+                    terminal = Some(parse_path_list(&content)?);
+                }
+                "groups" => {
+                    let list = Punctuated::<Group, Token![,]>::parse_terminated(&content)?;
+                    groups = Some(list.into_iter().collect());
+                }
                 "outputs" => {
                     outputs = Some(parse_path_list(&content)?);
                 }
@@ -302,6 +402,19 @@ impl Parse for MachineAttr {
                 "generate_structs" => {
                     generate_structs = Some(parse_bool(&content)?);
                 }
+                "generate_getters" => {
+                    generate_getters = Some(parse_bool(&content)?);
+                }
+                "dispatch" => {
+                    let dispatch = parse_choice_value(&content, &["match", "table"])?;
+                    table_dispatch = Some(dispatch == "table");
+                }
+                "consume_methods" => {
+                    consume_methods = Some(parse_bool(&content)?);
+                }
+                "diagram" => {
+                    diagram = Some(content.parse()?);
+                }
                 section => return Err(syn::Error::new_spanned(section, "unknown section")),
             }
 
@@ -314,10 +427,16 @@ impl Parse for MachineAttr {
         Ok(Self {
             inputs: inputs.unwrap_or_default(),
             states: states.unwrap_or_default(),
+            terminal: terminal.unwrap_or_default(),
+            groups: groups.unwrap_or_default(),
             outputs: outputs.unwrap_or_default(),
             transitions: transitions.unwrap_or_default(),
             derives: derives.unwrap_or_default(),
             generate_structs: generate_structs.unwrap_or(false),
+            generate_getters: generate_getters.unwrap_or(true),
+            table_dispatch: table_dispatch.unwrap_or(false),
+            consume_methods: consume_methods.unwrap_or(false),
+            diagram: diagram.unwrap_or_default(),
         })
     }
 }
@@ -337,3 +456,156 @@ fn parse_bool(input: ParseStream) -> Result<bool> {
     let b: syn::LitBool = input.parse()?;
     Ok(b.value)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_full_form() {
+        let src = r#"(S1) -> (S2, E1) : guard_xyz = handler_xyz"#;
+        let t: Transition = syn::parse_str(src).unwrap();
+        assert!(t.guard.is_some());
+        assert_eq!(t.handler.unwrap().to_string(), "handler_xyz");
+        assert_eq!(
+            guard_expr_to_string(&t.guard.unwrap(), &|p| key(p)),
+            "guard_xyz"
+        );
+    }
+
+    #[test]
+    fn parses_minimal_form() {
+        let src = "(A) -> (B)";
+        let t: Transition = syn::parse_str(src).unwrap();
+        assert!(t.input.is_none());
+        assert!(t.output.is_none());
+        assert!(t.guard.is_none());
+        assert!(t.handler.is_none());
+    }
+
+    #[test]
+    fn parses_handler_only() {
+        let src = "(A) -> (B) = handler_xyz";
+        let t: Transition = syn::parse_str(src).unwrap();
+        assert!(t.input.is_none());
+        assert!(t.output.is_none());
+        assert!(t.guard.is_none());
+        assert_eq!(t.handler.unwrap().to_string(), "handler_xyz");
+    }
+
+    #[test]
+    fn parses_complex_guard() {
+        let src = r#"(S1) -> (S2) : a && b || !c"#;
+        let t: Transition = syn::parse_str(src).unwrap();
+        assert!(t.guard.is_some());
+        assert_eq!(
+            guard_expr_to_string(&t.guard.unwrap(), &|p| key(p)),
+            "a && b || !c"
+        );
+    }
+
+    #[test]
+    fn parses_complex_guard_with_handler() {
+        let src = r#"(S1) -> (S2) : a && b || !c = handler_xyz"#;
+        let t: Transition = syn::parse_str(src).unwrap();
+        assert!(t.guard.is_some());
+        assert_eq!(t.handler.unwrap().to_string(), "handler_xyz");
+        assert_eq!(
+            guard_expr_to_string(&t.guard.unwrap(), &|p| key(p)),
+            "a && b || !c"
+        );
+    }
+
+    #[test]
+    fn parses_diagram_options() {
+        let o: DiagramOptions = syn::parse_str("theme = dark, rankdir = TB, hide_outputs").unwrap();
+        assert_eq!(o.theme.unwrap().to_string(), "dark");
+        assert_eq!(o.rankdir.unwrap().to_string(), "TB");
+        assert!(o.hide_outputs);
+        assert!(!o.show_failure);
+        assert!(o.layout.is_none());
+        assert!(!o.merge_edges);
+        let o: DiagramOptions = syn::parse_str("layout = flowchart, merge_edges").unwrap();
+        assert_eq!(o.layout.unwrap().to_string(), "flowchart");
+        assert!(o.merge_edges);
+        assert!(o.disabled.is_empty());
+        let o: DiagramOptions = syn::parse_str("mermaid = false, dot = true").unwrap();
+        assert!(!o.enabled("mermaid"));
+        assert!(o.enabled("dot"));
+        let o: DiagramOptions =
+            syn::parse_str("hide_outputs = false, show_failure = true").unwrap();
+        assert!(!o.hide_outputs);
+        assert!(o.show_failure);
+        assert!(syn::parse_str::<DiagramOptions>("theme = sepia").is_err());
+        assert!(syn::parse_str::<DiagramOptions>("rankdir = up").is_err());
+        assert!(syn::parse_str::<DiagramOptions>("layout = tree").is_err());
+        assert!(syn::parse_str::<DiagramOptions>("colors = true").is_err());
+    }
+
+    #[test]
+    fn parses_dispatch() {
+        let m: MachineAttr = syn::parse_str("states(A)").unwrap();
+        assert!(!m.table_dispatch);
+        let m: MachineAttr = syn::parse_str("states(A), dispatch(table)").unwrap();
+        assert!(m.table_dispatch);
+        let m: MachineAttr = syn::parse_str("states(A), dispatch(match)").unwrap();
+        assert!(!m.table_dispatch);
+        assert!(syn::parse_str::<MachineAttr>("states(A), dispatch(jump)").is_err());
+    }
+
+    #[test]
+    fn parses_generate_getters() {
+        let m: MachineAttr = syn::parse_str("states(A)").unwrap();
+        assert!(m.generate_getters);
+        let m: MachineAttr = syn::parse_str("states(A), generate_getters(false)").unwrap();
+        assert!(!m.generate_getters);
+    }
+
+    #[test]
+    fn parses_consume_methods() {
+        let m: MachineAttr = syn::parse_str("states(A)").unwrap();
+        assert!(!m.consume_methods);
+        let m: MachineAttr = syn::parse_str("states(A), consume_methods(true)").unwrap();
+        assert!(m.consume_methods);
+        assert!(syn::parse_str::<MachineAttr>("states(A), consume_methods(yes)").is_err());
+    }
+
+    #[test]
+    fn parses_hints() {
+        let tr: Transition = syn::parse_str("(A, I) -> (B)").unwrap();
+        assert_eq!(tr.hint, Hint::None);
+        let tr: Transition = syn::parse_str("[hot] (A, I) -> (B) : guard_x = handle_y").unwrap();
+        assert_eq!(tr.hint, Hint::Hot);
+        assert!(tr.guard.is_some() && tr.handler.is_some());
+        let tr: Transition = syn::parse_str("[cold] (A) -> (B)").unwrap();
+        assert_eq!(tr.hint, Hint::Cold);
+        assert!(syn::parse_str::<Transition>("[warm] (A) -> (B)").is_err());
+    }
+
+    #[test]
+    fn parses_groups() {
+        let m: MachineAttr =
+            syn::parse_str("states(A, B, C), groups(First: [A, B], Second: [states::C],)").unwrap();
+        assert_eq!(m.groups.len(), 2);
+        assert_eq!(m.groups[0].name.to_string(), "First");
+        assert_eq!(m.groups[0].states.len(), 2);
+        assert_eq!(key(&m.groups[1].states[0]), "states::C");
+        assert!(syn::parse_str::<MachineAttr>("groups(First(A))").is_err());
+    }
+
+    #[test]
+    fn parses_invalid() {
+        let src = r#"blabla"#;
+        assert!(syn::parse_str::<Transition>(src).is_err());
+        let src = r#"(S1,S2)"#;
+        assert!(syn::parse_str::<Transition>(src).is_err());
+        let src = r#"(S1) -> (S2) : a(some_invalid_expr)"#;
+        assert!(syn::parse_str::<Transition>(src).is_err());
+        let src = r#"(S1) -> (S2) = some_invalid_expr(handler_xyz)"#;
+        assert!(syn::parse_str::<Transition>(src).is_err());
+        let src = r#"(S1) -> (S2) : a(some_invalid_expr)"#;
+        assert!(syn::parse_str::<Transition>(src).is_err());
+        let src = r#"(S1) -> (S2) = some_invalid_expr(handler_xyz)"#;
+        assert!(syn::parse_str::<Transition>(src).is_err());
+    }
+}
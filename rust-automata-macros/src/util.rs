@@ -0,0 +1,96 @@
+use heck::ToSnakeCase;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::ToTokens;
+use syn::{Ident, Path};
+
+// Handlers that have this prefix receive states and inputs and should return a state and an output.
+pub const HANDLE_PREFIX: &str = "handle_";
+// Guards that have this prefix receive state reference and should return a boolean.
+pub const GUARD_PREFIX: &str = "guard_";
+
+/// `CamelCase` → `snake_case` ident.
+pub fn snake(id: &Ident) -> Ident {
+    Ident::new(&id.to_string().to_snake_case(), id.span())
+}
+
+/// Last segment of `syn::Path` → `snake_case` ident.
+pub fn snake_path(p: &Path) -> Ident {
+    snake(last(p))
+}
+
+/// Last identifier of a path (`states::Open` → `Open`)
+pub fn last(p: &Path) -> &Ident {
+    &p.segments.last().unwrap().ident
+}
+
+/// A unique string key for set membership (`states::Open`)
+pub fn key(p: &Path) -> String {
+    p.segments
+        .iter()
+        .map(|s| s.ident.to_string())
+        .collect::<Vec<_>>()
+        .join("::")
+}
+
+/// Strip a trailing `Machine` from a type name if present.
+pub fn strip_machine(id: &Ident) -> String {
+    let s = id.to_string();
+    s.strip_suffix("Machine").unwrap_or(&s).to_owned()
+}
+
+/// A compile error pointing at `spanned`, such as the path of an unknown state in the attribute.
+pub fn compile_error_if(
+    condition: bool,
+    spanned: &dyn ToTokens,
+    message: &str,
+) -> Option<TokenStream2> {
+    condition.then(|| syn::Error::new_spanned(spanned, message).to_compile_error())
+}
+
+/// Levenshtein distance between two strings, counted in characters.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+thread_local! {
+    // Calls of `did_you_mean`, as suggestions are too slow to compute for every declared symbol.
+    pub static SUGGESTIONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// `, did you mean `states::Opened`?` for the declared symbol closest to `unknown`, or nothing
+/// if none is close. Symbols are compared both by their key and by their last segment, so that
+/// a missing module prefix is found too.
+pub fn did_you_mean<'a>(unknown: &str, declared: impl IntoIterator<Item = &'a String>) -> String {
+    fn last_segment(key: &str) -> &str {
+        key.rsplit("::").next().unwrap_or(key)
+    }
+    #[cfg(test)]
+    SUGGESTIONS.with(|calls| calls.set(calls.get() + 1));
+    // About one typo per three characters.
+    let close = |a: &str, b: &str| {
+        let distance = edit_distance(a, b);
+        (distance <= a.chars().count().max(3) / 3).then_some(distance)
+    };
+    declared
+        .into_iter()
+        .filter_map(|key| {
+            let by_key = close(unknown, key);
+            let by_last = close(last_segment(unknown), last_segment(key));
+            Some((by_key.into_iter().chain(by_last).min()?, key))
+        })
+        .min()
+        .map(|(_, key)| format!(", did you mean `{key}`?"))
+        .unwrap_or_default()
+}
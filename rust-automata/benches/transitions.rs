@@ -0,0 +1,173 @@
+//! Benchmarks of the transition hot paths, for machines of a few sizes.
+//!
+//! Compare a change against a baseline with
+//! `cargo bench -p rust-automata --bench transitions -- --save-baseline main` on the old code,
+//! then `cargo bench -p rust-automata --bench transitions -- --baseline main` on the new one.
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use rust_automata::bus::Bus;
+use rust_automata::*;
+
+mod toggle {
+    use rust_automata::*;
+
+    #[state_machine(
+        inputs(Tick),
+        states(Off, On),
+        outputs(Tock),
+        transitions(
+            (Off, Tick) -> (On, Tock),
+            (On, Tick) -> (Off, Tock),
+        ),
+        generate_structs(true),
+        derive(Debug, Clone),
+    )]
+    pub struct Toggle;
+}
+
+macro_rules! ring {
+    ($module:ident, $machine:ident $(, $dispatch:ident)?) => {
+        mod $module {
+            use rust_automata::*;
+
+            #[state_machine(
+                inputs(Tick, Skip),
+                states(R0, R1, R2, R3, R4, R5, R6, R7, R8, R9, R10, R11, R12, R13, R14, R15),
+                outputs(Tock),
+                transitions(
+                    (R0, Tick) -> (R1, Tock),
+                    (R1, Tick) -> (R2, Tock),
+                    (R2, Tick) -> (R3, Tock),
+                    (R3, Tick) -> (R4, Tock),
+                    (R4, Tick) -> (R5, Tock),
+                    (R5, Tick) -> (R6, Tock),
+                    (R6, Tick) -> (R7, Tock),
+                    (R7, Tick) -> (R8, Tock),
+                    (R8, Tick) -> (R9, Tock),
+                    (R9, Tick) -> (R10, Tock),
+                    (R10, Tick) -> (R11, Tock),
+                    (R11, Tick) -> (R12, Tock),
+                    (R12, Tick) -> (R13, Tock),
+                    (R13, Tick) -> (R14, Tock),
+                    (R14, Tick) -> (R15, Tock),
+                    (R15, Tick) -> (R0, Tock),
+                    (R15, Skip) -> (R0),
+                ),
+                generate_structs(true),
+                derive(Debug, Clone),
+                $( dispatch($dispatch), )?
+            )]
+            pub struct $machine;
+        }
+    };
+}
+
+ring!(ring, Ring);
+ring!(ring_table, RingTable, table);
+
+mod pipe {
+    use rust_automata::*;
+
+    #[derive(Debug, Clone, Default)]
+    pub struct Ping;
+    #[derive(Debug, Clone, Default)]
+    pub struct Pong;
+    #[derive(Debug, Clone, Default)]
+    pub struct Idle;
+
+    #[state_machine(
+        inputs(Ping),
+        states(Idle),
+        outputs(Pong),
+        transitions((Idle, Ping) -> (Idle, Pong)),
+        derive(Debug, Clone),
+    )]
+    pub struct Relay;
+
+    #[state_machine(
+        inputs(Pong),
+        states(Idle),
+        transitions((Idle, Pong) -> (Idle)),
+        derive(Debug, Clone),
+    )]
+    pub struct Sink;
+}
+
+fn relay(c: &mut Criterion) {
+    let mut group = c.benchmark_group("relay");
+    group.bench_function(BenchmarkId::new("states", 2), |b| {
+        let mut m = StateMachine::new(toggle::Toggle, toggle::Off);
+        b.iter(|| -> toggle::Tock { m.relay(black_box(toggle::Tick)) });
+    });
+    group.bench_function(BenchmarkId::new("states", 16), |b| {
+        let mut m = StateMachine::new(ring::Ring, ring::R0);
+        b.iter(|| -> ring::Tock { m.relay(black_box(ring::Tick)) });
+    });
+    group.bench_function(BenchmarkId::new("states_table", 16), |b| {
+        let mut m = StateMachine::new(ring_table::RingTable, ring_table::R0);
+        b.iter(|| -> ring_table::Tock { m.relay(black_box(ring_table::Tick)) });
+    });
+    group.finish();
+}
+
+fn consume(c: &mut Criterion) {
+    let mut group = c.benchmark_group("consume");
+    group.bench_function(BenchmarkId::new("states", 2), |b| {
+        let mut m = StateMachine::new(toggle::Toggle, toggle::Off);
+        b.iter(|| m.consume(black_box(toggle::Tick)));
+    });
+    group.bench_function(BenchmarkId::new("states", 16), |b| {
+        let mut m = StateMachine::new(ring::Ring, ring::R0);
+        b.iter(|| m.consume(black_box(ring::Tick)));
+    });
+    group.bench_function(BenchmarkId::new("states_table", 16), |b| {
+        let mut m = StateMachine::new(ring_table::RingTable, ring_table::R0);
+        b.iter(|| m.consume(black_box(ring_table::Tick)));
+    });
+    group.finish();
+}
+
+fn consume_slice(c: &mut Criterion) {
+    let mut group = c.benchmark_group("consume_slice");
+    let inputs: Vec<<ring::Ring as StateMachineImpl>::Input> =
+        (0..1024).map(|_| ring::Tick.into()).collect();
+    group.bench_function(BenchmarkId::new("states", 16), |b| {
+        let mut m = StateMachine::new(ring::Ring, ring::R0);
+        b.iter(|| m.consume_slice(black_box(&inputs)));
+    });
+    let inputs: Vec<<ring_table::RingTable as StateMachineImpl>::Input> =
+        (0..1024).map(|_| ring_table::Tick.into()).collect();
+    group.bench_function(BenchmarkId::new("states_table", 16), |b| {
+        let mut m = StateMachine::new(ring_table::RingTable, ring_table::R0);
+        b.iter(|| m.consume_slice(black_box(&inputs)));
+    });
+    group.finish();
+}
+
+fn can_consume(c: &mut Criterion) {
+    let mut group = c.benchmark_group("can_consume");
+    group.bench_function(BenchmarkId::new("states", 2), |b| {
+        let mut m = StateMachine::new(toggle::Toggle, toggle::Off);
+        b.iter(|| black_box(m.can_consume::<toggle::Tick>()));
+    });
+    // `Skip` is only accepted in the last state, so this is the miss path.
+    group.bench_function(BenchmarkId::new("states", 16), |b| {
+        let mut m = StateMachine::new(ring::Ring, ring::R0);
+        b.iter(|| black_box(m.can_consume::<ring::Skip>()));
+    });
+    group.finish();
+}
+
+fn bus(c: &mut Criterion) {
+    c.bench_function("bus/publish", |b| {
+        let mut bus = Bus::new();
+        let relay = bus.register(StateMachine::new(pipe::Relay, pipe::Idle));
+        bus.subscribe::<_, pipe::Ping>(relay);
+        bus.publishes::<_, pipe::Pong>(relay);
+        let sink = bus.register(StateMachine::new(pipe::Sink, pipe::Idle));
+        bus.subscribe::<_, pipe::Pong>(sink);
+        b.iter(|| bus.publish(black_box(pipe::Ping)).unwrap());
+    });
+}
+
+criterion_group!(benches, relay, consume, consume_slice, can_consume, bus);
+criterion_main!(benches);
@@ -0,0 +1,123 @@
+//! Run a state machine on its own tokio task.
+//!
+//! Inputs are sent over a channel and the produced outputs are received on another one.
+
+use crate::{Alphabet, StateMachine, StateMachineImpl};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+/// A state machine owned by a tokio task.
+///
+/// Inputs without an enabled transition are logged and dropped, instead of panicking the task.
+/// `Nothing` outputs are not forwarded.
+pub struct AsyncStateMachine<T: StateMachineImpl> {
+    inputs: mpsc::Sender<T::Input>,
+    outputs: mpsc::Receiver<T::Output>,
+    shutdown: oneshot::Sender<()>,
+    task: JoinHandle<StateMachine<T>>,
+}
+
+impl<T> AsyncStateMachine<T>
+where
+    T: StateMachineImpl + Send + 'static,
+    T::State: Send,
+    T::Input: Send,
+    T::Output: Send,
+{
+    /// Spawn the machine on the current tokio runtime.
+    ///
+    /// The channels hold at most `capacity` inputs/outputs.
+    pub fn spawn(mut machine: StateMachine<T>, capacity: usize) -> Self {
+        let (input_tx, mut input_rx) = mpsc::channel::<T::Input>(capacity);
+        let (output_tx, output_rx) = mpsc::channel(capacity);
+        let (shutdown_tx, mut shutdown_rx) = oneshot::channel();
+
+        let task = tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    biased;
+                    _ = &mut shutdown_rx => {
+                        // Stop accepting new inputs, but handle those already sent.
+                        input_rx.close();
+                        while let Some(input) = input_rx.recv().await {
+                            Self::handle(&mut machine, &output_tx, input).await;
+                        }
+                        break;
+                    }
+                    input = input_rx.recv() => match input {
+                        Some(input) => Self::handle(&mut machine, &output_tx, input).await,
+                        None => break,
+                    },
+                }
+            }
+            machine
+        });
+
+        Self {
+            inputs: input_tx,
+            outputs: output_rx,
+            shutdown: shutdown_tx,
+            task,
+        }
+    }
+
+    async fn handle(
+        machine: &mut StateMachine<T>,
+        outputs: &mpsc::Sender<T::Output>,
+        input: T::Input,
+    ) {
+        match machine.try_fire(input) {
+            Ok(output) if output.any() => {
+                // The receiver may be gone, the machine keeps running anyway.
+                let _ = outputs.send(output).await;
+            }
+            Ok(_) => {}
+            Err(rejected) => log::warn!("{}: {}", T::name(), rejected),
+        }
+    }
+
+    /// A sender to feed inputs to the machine, which can be cloned and moved to other tasks.
+    pub fn sender(&self) -> mpsc::Sender<T::Input> {
+        self.inputs.clone()
+    }
+
+    /// Send an input to the machine. Fails if the machine is shut down.
+    pub async fn send<I: Into<T::Input>>(
+        &self,
+        input: I,
+    ) -> Result<(), mpsc::error::SendError<T::Input>> {
+        self.inputs.send(input.into()).await
+    }
+
+    /// Receive the next output.
+    ///
+    /// Returns `None` if the task has stopped and all outputs were received.
+    pub async fn recv(&mut self) -> Option<T::Output> {
+        self.outputs.recv().await
+    }
+
+    /// Gracefully stop the task after processing the inputs already sent, and return the machine.
+    ///
+    /// Outputs that were not received yet are dropped.
+    pub async fn shutdown(self) -> StateMachine<T> {
+        let Self {
+            inputs,
+            outputs,
+            shutdown,
+            task,
+        } = self;
+        // Dropping the receiver first makes sure the task never waits on a full output channel.
+        drop(outputs);
+        drop(inputs);
+        let _ = shutdown.send(());
+        task.await.expect("the state machine task panicked")
+    }
+}
+
+impl<T: StateMachineImpl> std::fmt::Debug for AsyncStateMachine<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AsyncStateMachine")
+            .field("name", &T::name())
+            .finish()
+    }
+}
@@ -0,0 +1,180 @@
+//! Static analysis of a [`MachineGraph`].
+//!
+//! Guards are ignored: every declared transition is assumed to be enabled.
+//! The `Failure` state (id 0) is never part of the results.
+
+use crate::graph::MachineGraph;
+
+/// Ids of the states reachable from the initial state, in increasing order.
+pub fn reachable_states(graph: &MachineGraph) -> Vec<usize> {
+    reachable(&successors(graph))
+}
+
+/// Ids of the reachable states without any outgoing transition.
+pub fn dead_states(graph: &MachineGraph) -> Vec<usize> {
+    let successors = successors(graph);
+    reachable(&successors)
+        .into_iter()
+        .filter(|&state| successors[state].is_empty())
+        .collect()
+}
+
+/// Strongly connected components, each a list of state ids in increasing order.
+///
+/// Components are listed in reverse topological order: no transition leads from
+/// a component to one listed after it.
+pub fn strongly_connected_components(graph: &MachineGraph) -> Vec<Vec<usize>> {
+    Tarjan::new(successors(graph)).run()
+}
+
+/// The targets of the transitions leaving each state, indexed by the state id.
+fn successors(graph: &MachineGraph) -> Vec<Vec<usize>> {
+    let mut successors = vec![Vec::new(); graph.states.len()];
+    for edge in graph.transitions {
+        successors[edge.from].push(edge.to);
+    }
+    successors
+}
+
+fn reachable(successors: &[Vec<usize>]) -> Vec<usize> {
+    let mut seen = vec![false; successors.len()];
+    let mut stack = vec![MachineGraph::INITIAL_STATE];
+    seen[MachineGraph::INITIAL_STATE] = true;
+    while let Some(state) = stack.pop() {
+        for &to in &successors[state] {
+            if !seen[to] {
+                seen[to] = true;
+                stack.push(to);
+            }
+        }
+    }
+    ids_where(&seen)
+}
+
+fn ids_where(flags: &[bool]) -> Vec<usize> {
+    (1..flags.len()).filter(|&id| flags[id]).collect()
+}
+
+// Tarjan's algorithm, with an explicit stack of the visited states, so that
+// long chains of states do not overflow the call stack.
+struct Tarjan {
+    successors: Vec<Vec<usize>>,
+    index: Vec<Option<usize>>,
+    low_link: Vec<usize>,
+    on_stack: Vec<bool>,
+    stack: Vec<usize>,
+    next_index: usize,
+    components: Vec<Vec<usize>>,
+}
+
+impl Tarjan {
+    fn new(successors: Vec<Vec<usize>>) -> Self {
+        let n = successors.len();
+        Self {
+            successors,
+            index: vec![None; n],
+            low_link: vec![0; n],
+            on_stack: vec![false; n],
+            stack: Vec::new(),
+            next_index: 0,
+            components: Vec::new(),
+        }
+    }
+
+    fn run(mut self) -> Vec<Vec<usize>> {
+        for state in 1..self.successors.len() {
+            if self.index[state].is_none() {
+                self.visit(state);
+            }
+        }
+        self.components
+    }
+
+    fn visit(&mut self, root: usize) {
+        // The states being visited, with the position of the next successor to look at.
+        let mut visiting = vec![(root, 0)];
+        self.enter(root);
+        while let Some((state, next)) = visiting.last_mut() {
+            let state = *state;
+            let Some(&to) = self.successors[state].get(*next) else {
+                visiting.pop();
+                self.leave(state);
+                if let Some(&(parent, _)) = visiting.last() {
+                    self.low_link[parent] = self.low_link[parent].min(self.low_link[state]);
+                }
+                continue;
+            };
+            *next += 1;
+            match self.index[to] {
+                None => {
+                    self.enter(to);
+                    visiting.push((to, 0));
+                }
+                Some(index) if self.on_stack[to] => {
+                    self.low_link[state] = self.low_link[state].min(index);
+                }
+                Some(_) => {}
+            }
+        }
+    }
+
+    fn enter(&mut self, state: usize) {
+        self.index[state] = Some(self.next_index);
+        self.low_link[state] = self.next_index;
+        self.next_index += 1;
+        self.stack.push(state);
+        self.on_stack[state] = true;
+    }
+
+    // Collect the component of `state` once all its successors are visited, if it is the root.
+    fn leave(&mut self, state: usize) {
+        if Some(self.low_link[state]) != self.index[state] {
+            return;
+        }
+        let mut component = Vec::new();
+        while let Some(member) = self.stack.pop() {
+            self.on_stack[member] = false;
+            component.push(member);
+            if member == state {
+                break;
+            }
+        }
+        component.sort_unstable();
+        self.components.push(component);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::Edge;
+
+    #[test]
+    fn long_chain_of_states() {
+        // A cycle through all the states, far deeper than a recursive search could go.
+        let n = 200_000;
+        let transitions = (1..n)
+            .map(|from| Edge {
+                from,
+                input: 0,
+                to: from % (n - 1) + 1,
+                output: 0,
+                guard: None,
+                handler: None,
+            })
+            .collect::<Vec<_>>();
+        let graph = MachineGraph {
+            name: "Chain",
+            schema: 0,
+            states: Vec::leak(vec![""; n]),
+            inputs: &[""],
+            outputs: &[""],
+            transitions: Vec::leak(transitions),
+        };
+        assert_eq!(reachable_states(&graph).len(), n - 1);
+        assert!(dead_states(&graph).is_empty());
+        let components = strongly_connected_components(&graph);
+        assert_eq!(components.len(), 1);
+        assert_eq!(components[0].len(), n - 1);
+    }
+}
@@ -0,0 +1,87 @@
+//! Configure a machine step by step instead of calling setters after [`StateMachine::new`].
+//!
+//! ```ignore
+//! let machine = StateMachine::builder()
+//!     .data(Lock)
+//!     .initial(states::Open)
+//!     .observer(|t| println!("{}", t.to_state))
+//!     .history(16)
+//!     .build();
+//! ```
+//!
+//! There is no clock step: a machine has no clock of its own. Clocks belong to the machine
+//! data, which creates its [timers][crate::clock::Timer] from them, so set the clock
+//! in the data passed to [`data`][Builder::data].
+
+use crate::{StateMachine, StateMachineImpl, TransitionIds};
+
+type Setup<T> = Box<dyn FnOnce(&mut StateMachine<T>)>;
+
+/// Builder returned by [`StateMachine::builder`].
+///
+/// `D` and `S` track whether the data and the initial state were set,
+/// [`build`][Self::build] is available once both are.
+pub struct Builder<T: StateMachineImpl, D = (), S = ()> {
+    data: D,
+    initial: S,
+    setup: Vec<Setup<T>>,
+}
+
+impl<T: StateMachineImpl> Builder<T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            data: (),
+            initial: (),
+            setup: Vec::new(),
+        }
+    }
+}
+
+impl<T: StateMachineImpl, S> Builder<T, (), S> {
+    /// The data of the machine.
+    pub fn data(self, data: T) -> Builder<T, T, S> {
+        Builder {
+            data,
+            initial: self.initial,
+            setup: self.setup,
+        }
+    }
+}
+
+impl<T: StateMachineImpl, D> Builder<T, D, ()> {
+    /// The state the machine starts in.
+    pub fn initial(self, initial: T::InitialState) -> Builder<T, D, T::InitialState> {
+        Builder {
+            data: self.data,
+            initial,
+            setup: self.setup,
+        }
+    }
+}
+
+impl<T: StateMachineImpl, D, S> Builder<T, D, S> {
+    /// See [`StateMachine::observe`]. Can be called multiple times.
+    pub fn observer(mut self, observer: impl FnMut(&TransitionIds<T>) + Send + 'static) -> Self {
+        self.setup.push(Box::new(move |sm| sm.observe(observer)));
+        self
+    }
+
+    /// Keep the states before the last `depth` transitions, see [`StateMachine::enable_undo`].
+    pub fn history(mut self, depth: usize) -> Self
+    where
+        T::State: Clone,
+    {
+        self.setup.push(Box::new(move |sm| sm.enable_undo(depth)));
+        self
+    }
+}
+
+impl<T: StateMachineImpl> Builder<T, T, T::InitialState> {
+    pub fn build(self) -> StateMachine<T> {
+        let mut machine = StateMachine::new(self.data, self.initial);
+        for setup in self.setup {
+            setup(&mut machine);
+        }
+        machine
+    }
+}
@@ -0,0 +1,188 @@
+//! Deliver symbols within a network of machines.
+//!
+//! Machines are registered on a [`Bus`], and declare which symbol structs they
+//! [subscribe][Bus::subscribe] to (as inputs) and which they [publish][Bus::publishes] (as outputs).
+//! A published symbol is delivered to all its subscribers in the order of subscription,
+//! and the symbols they publish in turn are delivered breadth-first.
+
+use crate::{Enumerable, Enumerated, RejectedInput, StateMachine, StateMachineImpl};
+use std::any::{Any, TypeId};
+use std::collections::VecDeque;
+use std::fmt;
+use std::marker::PhantomData;
+
+type Symbol = (TypeId, Box<dyn Any>);
+// Feed a symbol to a machine, returning its (boxed) output.
+type Deliver = Box<dyn Fn(&mut dyn Any, &dyn Any) -> Result<Box<dyn Any>, RejectedInput>>;
+// Turn a (boxed) output into a symbol, or give the output back if it is of a different type.
+type Publish = Box<dyn Fn(Box<dyn Any>) -> Result<Symbol, Box<dyn Any>>>;
+
+const MISMATCH: &str = "bus handle used with a different machine type";
+
+/// A typed handle to a machine registered on a [`Bus`].
+pub struct Handle<T> {
+    index: usize,
+    _marker: PhantomData<T>,
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Handle").field(&self.index).finish()
+    }
+}
+
+struct Subscription {
+    symbol: TypeId,
+    member: usize,
+    deliver: Deliver,
+}
+
+/// Owns a set of machines and routes symbols between them deterministically.
+///
+/// Cycles of machines that keep publishing to each other are not detected.
+#[derive(Default)]
+pub struct Bus {
+    members: Vec<Box<dyn Any>>,
+    publishers: Vec<Vec<Publish>>,
+    subscriptions: Vec<Subscription>,
+}
+
+impl Bus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Move a machine onto the bus.
+    pub fn register<T>(&mut self, machine: StateMachine<T>) -> Handle<T>
+    where
+        T: StateMachineImpl + 'static,
+    {
+        self.members.push(Box::new(machine));
+        self.publishers.push(Vec::new());
+        Handle {
+            index: self.members.len() - 1,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Deliver every published `S` to the machine as an input.
+    pub fn subscribe<T, S>(&mut self, handle: Handle<T>)
+    where
+        T: StateMachineImpl + 'static,
+        T::Output: 'static,
+        S: Into<T::Input> + Clone + 'static,
+    {
+        self.subscriptions.push(Subscription {
+            symbol: TypeId::of::<S>(),
+            member: handle.index,
+            deliver: Box::new(|member, symbol| {
+                let machine = member.downcast_mut::<StateMachine<T>>().expect(MISMATCH);
+                let symbol = symbol.downcast_ref::<S>().expect(MISMATCH).clone();
+                machine
+                    .try_fire(symbol.into())
+                    .map(|output| Box::new(output) as Box<dyn Any>)
+            }),
+        });
+    }
+
+    /// Publish every `S` output of the machine on the bus.
+    pub fn publishes<T, S>(&mut self, handle: Handle<T>)
+    where
+        T: StateMachineImpl + 'static,
+        T::Output: 'static,
+        S: From<T::Output> + Enumerated<T::Output> + 'static,
+    {
+        self.publishers[handle.index].push(Box::new(|output| {
+            let output = output.downcast::<T::Output>().expect(MISMATCH);
+            if output.enum_id() == S::enum_id() {
+                Ok((TypeId::of::<S>(), Box::new(S::from(*output))))
+            } else {
+                Err(output)
+            }
+        }));
+    }
+
+    pub fn machine<T>(&self, handle: Handle<T>) -> &StateMachine<T>
+    where
+        T: StateMachineImpl + 'static,
+    {
+        self.members[handle.index]
+            .downcast_ref::<StateMachine<T>>()
+            .expect(MISMATCH)
+    }
+
+    pub(crate) fn machine_mut<T>(&mut self, handle: Handle<T>) -> &mut StateMachine<T>
+    where
+        T: StateMachineImpl + 'static,
+    {
+        self.members[handle.index]
+            .downcast_mut::<StateMachine<T>>()
+            .expect(MISMATCH)
+    }
+
+    /// Publish a symbol. Returns how many times a symbol was delivered, including the
+    /// symbols published by the subscribers in response.
+    ///
+    /// Stops at the first rejected delivery, dropping the undelivered symbols.
+    pub fn publish<S: 'static>(&mut self, symbol: S) -> Result<usize, RejectedInput> {
+        let symbol: Symbol = (TypeId::of::<S>(), Box::new(symbol));
+        self.dispatch(VecDeque::from([symbol]))
+    }
+
+    /// Take a spontaneous transition of the machine and publish its output.
+    /// Returns the number of deliveries, like [`publish`][Self::publish].
+    pub fn step<T>(&mut self, handle: Handle<T>) -> Result<usize, RejectedInput>
+    where
+        T: StateMachineImpl + 'static,
+        T::Output: 'static,
+    {
+        let output = self
+            .machine_mut(handle)
+            .try_fire(T::Nothing::default().into())?;
+        let mut queue = VecDeque::new();
+        route(&self.publishers[handle.index], Box::new(output), &mut queue);
+        self.dispatch(queue)
+    }
+
+    fn dispatch(&mut self, mut queue: VecDeque<Symbol>) -> Result<usize, RejectedInput> {
+        let mut delivered = 0;
+        while let Some((type_id, symbol)) = queue.pop_front() {
+            for sub in self.subscriptions.iter().filter(|s| s.symbol == type_id) {
+                let output = (sub.deliver)(self.members[sub.member].as_mut(), symbol.as_ref())?;
+                delivered += 1;
+                route(&self.publishers[sub.member], output, &mut queue);
+            }
+        }
+        Ok(delivered)
+    }
+}
+
+/// Queue the output as a symbol, if the machine publishes it.
+fn route(publishers: &[Publish], mut output: Box<dyn Any>, queue: &mut VecDeque<Symbol>) {
+    for publish in publishers {
+        match publish(output) {
+            Ok(symbol) => {
+                queue.push_back(symbol);
+                return;
+            }
+            Err(unmatched) => output = unmatched,
+        }
+    }
+}
+
+impl fmt::Debug for Bus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Bus")
+            .field("members", &self.members.len())
+            .field("subscriptions", &self.subscriptions.len())
+            .finish()
+    }
+}
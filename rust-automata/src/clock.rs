@@ -2,12 +2,34 @@
 
 use crate::timestamp::Timestamp;
 use crate::timestamp::TimestampDelta;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
 /// A trait for providing the current time.
 pub trait Clock: Send + Sync {
     fn now(&self) -> Timestamp;
     fn clone_box(&self) -> Box<dyn Clock>;
+
+    /// Put the clock behind an `Arc`, which is cheaper to hand out to many timers
+    /// than [`clone_box`][Self::clone_box].
+    fn shared(self) -> Arc<dyn Clock>
+    where
+        Self: Sized + 'static,
+    {
+        Arc::new(self)
+    }
+}
+
+impl<C: Clock + ?Sized + 'static> Clock for Arc<C> {
+    fn now(&self) -> Timestamp {
+        (**self).now()
+    }
+    fn clone_box(&self) -> Box<dyn Clock> {
+        Box::new(Arc::clone(self))
+    }
 }
 
 /// A time provider that uses the system's clock.
@@ -16,39 +38,167 @@ pub struct SystemClock;
 
 impl Clock for SystemClock {
     fn now(&self) -> Timestamp {
-        // We use chrono here as it is platform agnostic.
-        Timestamp::from(chrono::Utc::now())
+        match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(since_epoch) => Timestamp::zero() + since_epoch,
+            Err(err) => Timestamp::zero().saturating_sub(err.duration().into()),
+        }
     }
     fn clone_box(&self) -> Box<dyn Clock> {
         Box::new(self.clone())
     }
 }
 
-use std::sync::{Arc, Mutex};
+/// A time provider that never goes backwards, unlike [`SystemClock`].
+///
+/// Starts at the system time when created and then advances with [`Instant`],
+/// unaffected by adjustments of the system clock. Copies share the starting point.
+#[derive(Clone, Copy, Debug)]
+pub struct MonotonicClock {
+    origin: Instant,
+    start: Timestamp,
+}
+
+impl MonotonicClock {
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+            start: SystemClock.now(),
+        }
+    }
+}
+
+impl Default for MonotonicClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MonotonicClock {
+    fn now(&self) -> Timestamp {
+        self.start + self.origin.elapsed()
+    }
+    fn clone_box(&self) -> Box<dyn Clock> {
+        Box::new(*self)
+    }
+}
+
+/// A time provider that follows `tokio::time`, so it stands still in tests with
+/// `tokio::time::pause()` and moves with `tokio::time::advance()`.
+///
+/// Like [`MonotonicClock`], starts at the system time when created.
+#[cfg(feature = "tokio")]
+#[derive(Clone, Copy, Debug)]
+pub struct TokioClock {
+    origin: tokio::time::Instant,
+    start: Timestamp,
+}
+
+#[cfg(feature = "tokio")]
+impl TokioClock {
+    pub fn new() -> Self {
+        Self {
+            origin: tokio::time::Instant::now(),
+            start: SystemClock.now(),
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Default for TokioClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Clock for TokioClock {
+    fn now(&self) -> Timestamp {
+        self.start + self.origin.elapsed()
+    }
+    fn clone_box(&self) -> Box<dyn Clock> {
+        Box::new(*self)
+    }
+}
+
+type AdvanceHook = Box<dyn FnMut(Timestamp) + Send>;
 
 /// A time provider that can be mocked to advance time.
-#[derive(Clone, Debug, Default)]
+///
+/// Clones share the time and the observers.
+#[derive(Clone, Default)]
 pub struct ManualClock {
     current_time: Arc<Mutex<Timestamp>>,
+    observers: Arc<Mutex<Vec<AdvanceHook>>>,
+}
+
+impl fmt::Debug for ManualClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ManualClock")
+            .field("current_time", &self.now())
+            .finish()
+    }
 }
 
 impl ManualClock {
     pub fn new() -> Self {
-        let zero_time = Timestamp::zero();
-        Self {
-            current_time: Arc::new(Mutex::new(zero_time)),
-        }
+        Self::default()
     }
 
+    /// Move the time forward. A zero duration does nothing, a negative one panics.
     pub fn advance_by(&self, duration: TimestampDelta) {
-        assert!(duration > TimestampDelta::zero());
-        let mut time = self.current_time.lock().unwrap();
-        *time = *time + duration;
+        assert!(
+            !duration.is_negative(),
+            "ManualClock cannot go backwards by {duration}, use set_backwards"
+        );
+        if duration == TimestampDelta::zero() {
+            return;
+        }
+        let time = {
+            let mut time = self.current_time.lock().unwrap();
+            *time = *time + duration;
+            *time
+        };
+        self.notify(time);
     }
 
+    /// Move the time forward to `time`. Panics if `time` is earlier than the current time.
     pub fn advance_to(&self, time: Timestamp) {
-        let mut current_time = self.current_time.lock().unwrap();
-        *current_time = time;
+        // Panic without holding the lock, so the clock is not poisoned.
+        let current = {
+            let mut current = self.current_time.lock().unwrap();
+            let previous = *current;
+            if time >= previous {
+                *current = time;
+            }
+            previous
+        };
+        assert!(
+            time >= current,
+            "ManualClock cannot go backwards from {current} to {time}, use set_backwards"
+        );
+        self.notify(time);
+    }
+
+    /// Set the time, even to an earlier one.
+    ///
+    /// Running timers and stopwatches then measure negative elapsed times.
+    pub fn set_backwards(&self, time: Timestamp) {
+        *self.current_time.lock().unwrap() = time;
+        self.notify(time);
+    }
+
+    /// Call `observer` with the new time whenever the time is advanced, e.g. to step
+    /// the machines whose timers just expired.
+    ///
+    /// The observer must not advance the clock itself.
+    pub fn on_advance(&self, observer: impl FnMut(Timestamp) + Send + 'static) {
+        self.observers.lock().unwrap().push(Box::new(observer));
+    }
+
+    fn notify(&self, time: Timestamp) {
+        for observer in self.observers.lock().unwrap().iter_mut() {
+            observer(time);
+        }
     }
 }
 
@@ -62,6 +212,362 @@ impl Clock for ManualClock {
     }
 }
 
+/// A clock that runs off another clock, e.g. to test components whose clocks disagree.
+///
+/// The time of the inner clock is shifted by a constant offset, runs faster or slower
+/// by the drift, and is perturbed by a random jitter on every read. Clones share the
+/// jitter sequence.
+#[derive(Clone)]
+pub struct SimulationClock {
+    inner: Arc<dyn Clock>,
+    origin: Timestamp,
+    offset: TimestampDelta,
+    drift: f64,
+    jitter: TimestampDelta,
+    seed: Arc<AtomicU64>,
+}
+
+impl fmt::Debug for SimulationClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SimulationClock")
+            .field("now", &self.now())
+            .field("offset", &self.offset)
+            .field("drift", &self.drift)
+            .field("jitter", &self.jitter)
+            .finish()
+    }
+}
+
+impl SimulationClock {
+    /// Follows `inner` exactly until configured otherwise. Takes a `Box<dyn Clock>`
+    /// or a shared `Arc<dyn Clock>`, see [`Clock::shared`].
+    pub fn new(inner: impl Into<Arc<dyn Clock>>) -> Self {
+        let inner = inner.into();
+        Self {
+            origin: inner.now(),
+            inner,
+            offset: TimestampDelta::zero(),
+            drift: 0.0,
+            jitter: TimestampDelta::zero(),
+            seed: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Shift the time by `offset`.
+    pub fn with_offset(mut self, offset: TimestampDelta) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Run faster by the relative `drift`, or slower if negative, counted from the creation
+    /// of the clock. E.g. `0.001` gains a millisecond every second of the inner clock.
+    pub fn with_drift(mut self, drift: f64) -> Self {
+        self.drift = drift;
+        self
+    }
+
+    /// Add a uniformly random time of at most `max` in either direction to every read,
+    /// so the clock is no longer monotonic. The same `seed` gives the same sequence.
+    /// Panics if `max` is negative.
+    pub fn with_jitter(mut self, max: TimestampDelta, seed: u64) -> Self {
+        assert!(!max.is_negative(), "jitter must not be negative");
+        self.jitter = max;
+        self.seed = Arc::new(AtomicU64::new(seed));
+        self
+    }
+
+    fn next_jitter(&self) -> TimestampDelta {
+        if self.jitter == TimestampDelta::zero() {
+            return TimestampDelta::zero();
+        }
+        // SplitMix64.
+        let mut z = self
+            .seed
+            .fetch_add(0x9e37_79b9_7f4a_7c15, Ordering::Relaxed)
+            .wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        let max = self.jitter.as_nanos() as u64;
+        let nanos = (z % (2 * max + 1)) as i64 - max as i64;
+        TimestampDelta::from_nanos(nanos)
+    }
+}
+
+impl Clock for SimulationClock {
+    fn now(&self) -> Timestamp {
+        let now = self.inner.now();
+        let skew = (now - self.origin) * self.drift;
+        now.saturating_add(self.offset + skew + self.next_jitter())
+    }
+    fn clone_box(&self) -> Box<dyn Clock> {
+        Box::new(self.clone())
+    }
+}
+
+/// Measure elapsed time.
+pub struct Stopwatch {
+    clock: Arc<dyn Clock>,
+    start_time: Timestamp,
+}
+
+impl fmt::Debug for Stopwatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Stopwatch")
+            .field("clock", &self.clock.now()) // Just show a placeholder
+            .field("start_time", &self.start_time)
+            .finish()
+    }
+}
+
+impl Stopwatch {
+    /// Takes a `Box<dyn Clock>` or a shared `Arc<dyn Clock>`, see [`Clock::shared`].
+    pub fn new(clock: impl Into<Arc<dyn Clock>>) -> Self {
+        let clock = clock.into();
+        Self {
+            start_time: clock.now(),
+            clock,
+        }
+    }
+
+    pub fn elapsed(&self) -> TimestampDelta {
+        self.clock.now() - self.start_time
+    }
+
+    pub fn start_time(&self) -> Timestamp {
+        self.start_time
+    }
+
+    pub fn reset(&mut self) {
+        self.start_time = self.clock.now();
+    }
+}
+
+/// A timer that can be used to measure the elapsed time and check if timeout has occurred.
+///
+/// The timer can be [paused][Self::pause], the time while paused does not count as elapsed.
+#[derive(Debug)]
+pub struct Timer {
+    stopwatch: Stopwatch,
+    delay: TimestampDelta,
+    // Total duration of the finished pauses.
+    paused: TimestampDelta,
+    paused_at: Option<Timestamp>,
+}
+
+impl Timer {
+    /// Takes a `Box<dyn Clock>` or a shared `Arc<dyn Clock>`, see [`Clock::shared`].
+    pub fn new(clock: impl Into<Arc<dyn Clock>>, delay: TimestampDelta) -> Self {
+        Self {
+            delay,
+            stopwatch: Stopwatch::new(clock),
+            paused: TimestampDelta::zero(),
+            paused_at: None,
+        }
+    }
+
+    pub fn is_timeout(&self) -> bool {
+        self.elapsed() >= self.delay
+    }
+
+    /// The time at which the timer times out, if it is not paused until then.
+    pub fn deadline(&self) -> Timestamp {
+        self.stopwatch.start_time() + self.delay + self.paused_duration()
+    }
+
+    /// Time since the start or the last reset, without the pauses.
+    pub fn elapsed(&self) -> TimestampDelta {
+        self.stopwatch.elapsed() - self.paused_duration()
+    }
+
+    /// Time left until the timeout, zero once timed out.
+    pub fn remaining(&self) -> TimestampDelta {
+        (self.delay - self.elapsed()).max(TimestampDelta::zero())
+    }
+
+    /// Progress towards the timeout, from `0.0` to `1.0`. A zero delay is always `1.0`.
+    pub fn fraction_elapsed(&self) -> f64 {
+        if self.delay <= TimestampDelta::zero() {
+            return 1.0;
+        }
+        (self.elapsed().as_nanos() as f64 / self.delay.as_nanos() as f64).clamp(0.0, 1.0)
+    }
+
+    /// Restart the timer. A paused timer stays paused.
+    pub fn reset(&mut self) {
+        self.stopwatch.reset();
+        self.paused = TimestampDelta::zero();
+        if self.paused_at.is_some() {
+            self.paused_at = Some(self.stopwatch.start_time());
+        }
+    }
+
+    /// Stop the elapsed time until [`resume`][Self::resume]. Does nothing if already paused.
+    pub fn pause(&mut self) {
+        if self.paused_at.is_none() {
+            self.paused_at = Some(self.stopwatch.clock.now());
+        }
+    }
+
+    /// Let the elapsed time run again. Does nothing if not paused.
+    pub fn resume(&mut self) {
+        if let Some(paused_at) = self.paused_at.take() {
+            self.paused = self.paused + (self.stopwatch.clock.now() - paused_at);
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_at.is_some()
+    }
+
+    fn paused_duration(&self) -> TimestampDelta {
+        match self.paused_at {
+            Some(paused_at) => self.paused + (self.stopwatch.clock.now() - paused_at),
+            None => self.paused,
+        }
+    }
+}
+
+/// A timer that fires every `period`, e.g. for heartbeats.
+///
+/// The ticks are counted from the start, so they do not drift however late they are polled.
+#[derive(Debug)]
+pub struct PeriodicTimer {
+    stopwatch: Stopwatch,
+    period: TimestampDelta,
+    next_tick: Timestamp,
+}
+
+impl PeriodicTimer {
+    /// Takes a `Box<dyn Clock>` or a shared `Arc<dyn Clock>`, see [`Clock::shared`].
+    /// Panics if `period` is not positive.
+    pub fn new(clock: impl Into<Arc<dyn Clock>>, period: TimestampDelta) -> Self {
+        assert!(period > TimestampDelta::zero(), "period must be positive");
+        let stopwatch = Stopwatch::new(clock);
+        Self {
+            next_tick: stopwatch.start_time() + period,
+            stopwatch,
+            period,
+        }
+    }
+
+    /// Number of periods that ended since the last call, or since the start.
+    pub fn ticks(&mut self) -> u64 {
+        let now = self.stopwatch.clock.now();
+        if now < self.next_tick {
+            return 0;
+        }
+        let ticks = (now - self.next_tick).as_nanos() / self.period.as_nanos() + 1;
+        self.next_tick =
+            self.next_tick + TimestampDelta::from_nanos(ticks * self.period.as_nanos());
+        ticks as u64
+    }
+
+    /// Check if a period ended since the last call to [`ticks`][Self::ticks], without rearming.
+    pub fn is_due(&self) -> bool {
+        self.stopwatch.clock.now() >= self.next_tick
+    }
+
+    /// The time at which the next period ends.
+    pub fn next_tick(&self) -> Timestamp {
+        self.next_tick
+    }
+
+    pub fn period(&self) -> TimestampDelta {
+        self.period
+    }
+
+    /// Start counting the periods from now.
+    pub fn reset(&mut self) {
+        self.stopwatch.reset();
+        self.next_tick = self.stopwatch.start_time() + self.period;
+    }
+}
+
+/// Many named timers with a single clock, e.g. for a network of timed machines.
+///
+/// Answers when the next timer expires, and which timers expired when polled.
+pub struct TimerWheel<K> {
+    clock: Arc<dyn Clock>,
+    deadlines: BTreeMap<K, Timestamp>,
+    // The same timers, ordered by their deadlines.
+    queue: BTreeSet<(Timestamp, K)>,
+}
+
+impl<K: Ord + Clone> TimerWheel<K> {
+    /// Takes a `Box<dyn Clock>` or a shared `Arc<dyn Clock>`, see [`Clock::shared`].
+    pub fn new(clock: impl Into<Arc<dyn Clock>>) -> Self {
+        Self {
+            clock: clock.into(),
+            deadlines: BTreeMap::new(),
+            queue: BTreeSet::new(),
+        }
+    }
+
+    /// Start the timer `key`, expiring after `delay`. Restarts the timer if it is already running.
+    pub fn start(&mut self, key: K, delay: TimestampDelta) {
+        let deadline = self.clock.now() + delay;
+        self.start_until(key, deadline);
+    }
+
+    /// Start the timer `key`, expiring at `deadline`. Restarts the timer if it is already running.
+    pub fn start_until(&mut self, key: K, deadline: Timestamp) {
+        self.cancel(&key);
+        self.queue.insert((deadline, key.clone()));
+        self.deadlines.insert(key, deadline);
+    }
+
+    /// Stop the timer. Returns `false` if it was not running.
+    pub fn cancel(&mut self, key: &K) -> bool {
+        match self.deadlines.remove(key) {
+            Some(deadline) => self.queue.remove(&(deadline, key.clone())),
+            None => false,
+        }
+    }
+
+    /// The deadline of the timer, if it is running.
+    pub fn deadline(&self, key: &K) -> Option<Timestamp> {
+        self.deadlines.get(key).copied()
+    }
+
+    /// The earliest deadline of the running timers.
+    pub fn next_deadline(&self) -> Option<Timestamp> {
+        self.queue.first().map(|(deadline, _)| *deadline)
+    }
+
+    /// Stop and return the expired timers, the earliest deadline first.
+    pub fn poll(&mut self) -> Vec<K> {
+        let now = self.clock.now();
+        let mut expired = Vec::new();
+        while let Some((deadline, _)) = self.queue.first() {
+            if *deadline > now {
+                break;
+            }
+            let (_, key) = self.queue.pop_first().unwrap();
+            self.deadlines.remove(&key);
+            expired.push(key);
+        }
+        expired
+    }
+
+    /// Number of running timers.
+    pub fn len(&self) -> usize {
+        self.deadlines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.deadlines.is_empty()
+    }
+}
+
+impl<K: fmt::Debug> fmt::Debug for TimerWheel<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TimerWheel")
+            .field("deadlines", &self.deadlines)
+            .finish()
+    }
+}
+
 #[cfg(test)]
 pub mod tests {
     use super::*;
@@ -109,6 +615,157 @@ pub mod tests {
         );
     }
 
+    #[test]
+    fn monotonic_clock_follows_system_time() {
+        let clock = MonotonicClock::new();
+        let copy = clock.clone_box();
+        let mut last = clock.now();
+        for _ in 0..100 {
+            let now = copy.now();
+            assert!(now >= last);
+            last = now;
+        }
+        let drift = SystemClock.now() - clock.now();
+        assert!(drift.as_secs().abs() < 1);
+    }
+
+    #[test]
+    fn manual_clock_is_monotonic() {
+        let clock = ManualClock::new();
+        clock.advance_to(Timestamp::from_secs(5));
+        clock.advance_by(TimestampDelta::zero());
+        clock.advance_to(Timestamp::from_secs(5));
+        assert_eq!(clock.now(), Timestamp::from_secs(5));
+
+        let backwards = clock.clone();
+        let result =
+            std::panic::catch_unwind(move || backwards.advance_to(Timestamp::from_secs(4)));
+        assert!(result.is_err());
+        let backwards = clock.clone();
+        let result =
+            std::panic::catch_unwind(move || backwards.advance_by(TimestampDelta::from_secs(-1)));
+        assert!(result.is_err());
+
+        clock.set_backwards(Timestamp::from_secs(1));
+        assert_eq!(clock.now(), Timestamp::from_secs(1));
+    }
+
+    #[test]
+    fn simulation_clock() {
+        let clock = ManualClock::new();
+        clock.advance_to(Timestamp::from_secs(100));
+        let skewed = SimulationClock::new(clock.clone_box())
+            .with_offset(TimestampDelta::from_secs(-2))
+            .with_drift(0.5);
+        assert_eq!(skewed.now(), Timestamp::from_secs(98));
+        clock.advance_by(TimestampDelta::from_secs(10));
+        assert_eq!(skewed.now(), Timestamp::from_secs(113));
+
+        let max = TimestampDelta::from_millis(5);
+        let jittery = SimulationClock::new(clock.clone_box()).with_jitter(max, 7);
+        let same = SimulationClock::new(clock.clone_box()).with_jitter(max, 7);
+        let reads: Vec<_> = (0..100).map(|_| jittery.now()).collect();
+        assert!(reads.iter().all(|t| (*t - clock.now()).abs() <= max));
+        assert!(reads.windows(2).any(|w| w[0] != w[1]));
+        assert!(reads.iter().all(|t| *t == same.now()));
+    }
+
+    #[test]
+    fn paused_timer_does_not_elapse() {
+        let clock = ManualClock::new();
+        let mut timer = Timer::new(clock.clone_box(), TimestampDelta::from_secs(10));
+        clock.advance_by(TimestampDelta::from_secs(4));
+        timer.pause();
+        assert!(timer.is_paused());
+        clock.advance_by(TimestampDelta::from_secs(20));
+        assert_eq!(timer.elapsed(), TimestampDelta::from_secs(4));
+        assert_eq!(timer.remaining(), TimestampDelta::from_secs(6));
+        assert_eq!(timer.fraction_elapsed(), 0.4);
+        assert!(!timer.is_timeout());
+        assert_eq!(timer.deadline(), Timestamp::from_secs(30));
+
+        timer.resume();
+        timer.resume();
+        clock.advance_by(TimestampDelta::from_secs(5));
+        assert_eq!(timer.elapsed(), TimestampDelta::from_secs(9));
+        assert_eq!(timer.deadline(), Timestamp::from_secs(30));
+        clock.advance_by(TimestampDelta::from_secs(1));
+        assert!(timer.is_timeout());
+        clock.advance_by(TimestampDelta::from_secs(1));
+        assert_eq!(timer.remaining(), TimestampDelta::zero());
+        assert_eq!(timer.fraction_elapsed(), 1.0);
+
+        timer.pause();
+        timer.reset();
+        clock.advance_by(TimestampDelta::from_secs(3));
+        assert_eq!(timer.elapsed(), TimestampDelta::zero());
+        timer.resume();
+        clock.advance_by(TimestampDelta::from_secs(3));
+        assert_eq!(timer.elapsed(), TimestampDelta::from_secs(3));
+    }
+
+    #[test]
+    fn periodic_timer_does_not_drift() {
+        let clock = ManualClock::new();
+        let mut timer = PeriodicTimer::new(clock.clone_box(), TimestampDelta::from_secs(2));
+        assert_eq!(timer.ticks(), 0);
+        clock.advance_by(TimestampDelta::from_secs(3));
+        assert!(timer.is_due());
+        assert_eq!(timer.ticks(), 1);
+        assert!(!timer.is_due());
+        assert_eq!(timer.next_tick(), Timestamp::from_secs(4));
+
+        // Polled late, the missed periods are counted and the schedule is kept.
+        clock.advance_by(TimestampDelta::from_secs(6));
+        assert_eq!(timer.ticks(), 3);
+        assert_eq!(timer.next_tick(), Timestamp::from_secs(10));
+        clock.advance_by(TimestampDelta::from_secs(1));
+        assert_eq!(timer.ticks(), 1);
+
+        timer.reset();
+        assert_eq!(timer.next_tick(), Timestamp::from_secs(12));
+    }
+
+    #[test]
+    fn shared_clock() {
+        let clock = ManualClock::new();
+        let shared = clock.clone().shared();
+        let timers: Vec<_> = (1..=3)
+            .map(|secs| Timer::new(Arc::clone(&shared), TimestampDelta::from_secs(secs)))
+            .collect();
+        clock.advance_by(TimestampDelta::from_secs(2));
+        assert_eq!(shared.clone_box().now(), Timestamp::from_secs(2));
+        let expired = timers.iter().filter(|t| t.is_timeout()).count();
+        assert_eq!(expired, 2);
+    }
+
+    #[test]
+    fn timer_wheel_polls_expired_timers() {
+        let clock = ManualClock::new();
+        let mut wheel = TimerWheel::new(clock.clone_box());
+        assert_eq!(wheel.next_deadline(), None);
+        wheel.start("heartbeat", TimestampDelta::from_secs(5));
+        wheel.start("retry", TimestampDelta::from_secs(2));
+        wheel.start("lease", TimestampDelta::from_secs(30));
+        assert_eq!(wheel.next_deadline(), Some(Timestamp::from_secs(2)));
+
+        // Restarting replaces the deadline.
+        wheel.start("retry", TimestampDelta::from_secs(7));
+        assert_eq!(wheel.len(), 3);
+        assert_eq!(wheel.next_deadline(), Some(Timestamp::from_secs(5)));
+        assert!(wheel.poll().is_empty());
+
+        clock.advance_by(TimestampDelta::from_secs(7));
+        assert_eq!(wheel.poll(), ["heartbeat", "retry"]);
+        assert!(wheel.poll().is_empty());
+        assert_eq!(wheel.next_deadline(), Some(Timestamp::from_secs(30)));
+
+        assert!(wheel.cancel(&"lease"));
+        assert!(!wheel.cancel(&"lease"));
+        assert!(wheel.is_empty());
+        assert_eq!(wheel.next_deadline(), None);
+    }
+
     #[test]
     fn advance_time_across_threads_simplified() {
         use std::sync::{mpsc::sync_channel, Arc, Barrier};
@@ -159,63 +816,3 @@ pub mod tests {
         }
     }
 }
-
-/// Measure elapsed time.
-pub struct Stopwatch {
-    clock: Box<dyn Clock>,
-    start_time: Timestamp,
-}
-
-impl fmt::Debug for Stopwatch {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("Stopwatch")
-            .field("clock", &self.clock.now()) // Just show a placeholder
-            .field("start_time", &self.start_time)
-            .finish()
-    }
-}
-
-impl Stopwatch {
-    pub fn new(clock: Box<dyn Clock>) -> Self {
-        Self {
-            start_time: clock.now(),
-            clock,
-        }
-    }
-
-    pub fn elapsed(&self) -> TimestampDelta {
-        self.clock.now() - self.start_time
-    }
-
-    pub fn reset(&mut self) {
-        self.start_time = self.clock.now();
-    }
-}
-
-/// A timer that can be used to measure the elapsed time and check if timeout has occurred.
-#[derive(Debug)]
-pub struct Timer {
-    stopwatch: Stopwatch,
-    delay: TimestampDelta,
-}
-
-impl Timer {
-    pub fn new(clock: Box<dyn Clock>, delay: TimestampDelta) -> Self {
-        Self {
-            delay,
-            stopwatch: Stopwatch::new(clock),
-        }
-    }
-
-    pub fn is_timeout(&self) -> bool {
-        self.stopwatch.elapsed() >= self.delay
-    }
-
-    pub fn elapsed(&self) -> TimestampDelta {
-        self.stopwatch.elapsed()
-    }
-
-    pub fn reset(&mut self) {
-        self.stopwatch.reset();
-    }
-}
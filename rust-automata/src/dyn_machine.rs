@@ -0,0 +1,92 @@
+//! Drive machines of different types through one object-safe interface.
+//!
+//! Every [`StateMachine`] implements [`DynStateMachine`], so machines of many kinds
+//! can be kept in one registry, e.g. `Vec<Box<dyn DynStateMachine>>`, and driven by symbol names.
+
+use crate::{EnumId, Enumerable, RejectedInput, StateMachine, StateMachineImpl};
+use std::any::Any;
+use std::fmt;
+
+/// Why [`DynStateMachine::consume_by_name`] did not consume the input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConsumeError {
+    /// The machine has no input of this name.
+    UnknownInput(String),
+    /// The payload is not of the input type.
+    PayloadMismatch(&'static str),
+    /// No payload was given and the input does not implement `Default`.
+    MissingPayload(&'static str),
+    /// The input is not enabled in the current state.
+    Rejected(RejectedInput),
+}
+
+impl fmt::Display for ConsumeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownInput(input) => write!(f, "Unknown input {input}"),
+            Self::PayloadMismatch(input) => write!(f, "Payload is not of type {input}"),
+            Self::MissingPayload(input) => write!(f, "Input {input} needs a payload"),
+            Self::Rejected(rejected) => rejected.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ConsumeError {}
+
+/// A type-erased view of a [`StateMachine`].
+pub trait DynStateMachine {
+    /// The name of the machine.
+    fn name(&self) -> &'static str;
+    /// The name of the current state.
+    fn state_name(&self) -> &'static str;
+    /// Names of the inputs that can be consumed in the current state, excluding `Nothing`.
+    fn available_input_names(&self) -> Vec<&'static str>;
+    /// Consume the input with the given name.
+    ///
+    /// The payload is the boxed input struct. Without a payload, the input is built with `Default`.
+    /// Returns the name of the produced output.
+    fn consume_by_name(
+        &mut self,
+        input: &str,
+        payload: Option<Box<dyn Any>>,
+    ) -> Result<&'static str, ConsumeError>;
+}
+
+impl<T: StateMachineImpl> DynStateMachine for StateMachine<T> {
+    fn name(&self) -> &'static str {
+        T::name()
+    }
+
+    fn state_name(&self) -> &'static str {
+        StateMachine::state_name(self)
+    }
+
+    fn available_input_names(&self) -> Vec<&'static str> {
+        self.available_inputs()
+            .iter()
+            .filter(|id| id.id != 0)
+            .map(T::Input::get_variant)
+            .collect()
+    }
+
+    fn consume_by_name(
+        &mut self,
+        input: &str,
+        payload: Option<Box<dyn Any>>,
+    ) -> Result<&'static str, ConsumeError> {
+        let inputs = T::graph().inputs;
+        let id = match inputs.iter().position(|name| *name == input) {
+            Some(id) if id != 0 => EnumId::new(id),
+            _ => return Err(ConsumeError::UnknownInput(input.to_string())),
+        };
+        let name = inputs[id.index()];
+        let input = match payload {
+            Some(payload) => {
+                T::Input::from_any(&id, payload).map_err(|_| ConsumeError::PayloadMismatch(name))?
+            }
+            None => T::Input::default_of(&id).ok_or(ConsumeError::MissingPayload(name))?,
+        };
+        let output = self.try_fire(input).map_err(ConsumeError::Rejected)?;
+        Ok(T::Output::get_variant(&output.enum_id()))
+    }
+}
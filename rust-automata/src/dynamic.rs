@@ -0,0 +1,487 @@
+//! Machines interpreted at runtime from the text of the `#[state_machine(...)]` DSL.
+//!
+//! ```ignore
+//! let mut lock = DynamicMachine::parse(
+//!     "inputs(Key, Drill), states(Open, Closed), outputs(Click),
+//!      transitions(
+//!          (Open, Key) -> (Closed, Click) : guard_has_key = handle_lock,
+//!          (Closed, Key) -> (Open, Click),
+//!      )",
+//! )?;
+//! lock.register_guard("guard_has_key", || true);
+//! lock.register_handler("handle_lock", || println!("locked"));
+//! assert_eq!(lock.consume("Key")?, "Click");
+//! ```
+//!
+//! States and symbols carry no data, so guards and handlers are closures without arguments,
+//! registered by the names used in the text. Paths like `states::Open` are reduced to their
+//! last segment, other sections such as `derive(...)` are ignored. The definition can be
+//! replaced while running, see [`DynamicMachine::reload`].
+
+use std::collections::HashMap;
+use std::fmt;
+
+/// The DSL text is not a valid machine definition.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid machine definition: {}", self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Why [`DynamicMachine`] did not consume the input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DynamicError {
+    /// The machine has no input of this name.
+    UnknownInput(String),
+    /// No transition is enabled for the input in the current state.
+    Rejected { state: String, input: String },
+    /// A guard used by the definition was not registered.
+    MissingGuard(String),
+    /// A handler used by the definition was not registered.
+    MissingHandler(String),
+}
+
+impl fmt::Display for DynamicError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownInput(input) => write!(f, "Unknown input {input}"),
+            Self::Rejected { state, input } => {
+                write!(f, "Invalid transition from {state} using input {input}")
+            }
+            Self::MissingGuard(guard) => write!(f, "Guard {guard} is not registered"),
+            Self::MissingHandler(handler) => write!(f, "Handler {handler} is not registered"),
+        }
+    }
+}
+
+impl std::error::Error for DynamicError {}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Guard {
+    Call(String),
+    Not(Box<Guard>),
+    And(Box<Guard>, Box<Guard>),
+    Or(Box<Guard>, Box<Guard>),
+}
+
+#[derive(Clone, Debug)]
+struct Transition {
+    from: String,
+    // `None` for spontaneous transitions.
+    input: Option<String>,
+    to: String,
+    output: Option<String>,
+    guard: Option<Guard>,
+    handler: Option<String>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Definition {
+    inputs: Vec<String>,
+    states: Vec<String>,
+    outputs: Vec<String>,
+    transitions: Vec<Transition>,
+}
+
+/// A machine interpreted from the DSL text, starting in the first declared state.
+pub struct DynamicMachine {
+    definition: Definition,
+    state: usize,
+    guards: HashMap<String, Box<dyn Fn() -> bool + Send>>,
+    handlers: HashMap<String, Box<dyn FnMut() + Send>>,
+}
+
+impl DynamicMachine {
+    /// Parse the contents of the `#[state_machine(...)]` attribute.
+    pub fn parse(text: &str) -> Result<Self, ParseError> {
+        Ok(Self {
+            definition: Parser::new(text)?.definition()?,
+            state: 0,
+            guards: HashMap::new(),
+            handlers: HashMap::new(),
+        })
+    }
+
+    /// Replace the definition, keeping the current state and the registered guards and handlers.
+    ///
+    /// Fails if the current state is not in the new definition, the machine is then unchanged.
+    pub fn reload(&mut self, text: &str) -> Result<(), ParseError> {
+        let definition = Parser::new(text)?.definition()?;
+        let state = self.state_name();
+        let Some(index) = definition.states.iter().position(|s| s == state) else {
+            return Err(ParseError {
+                message: format!("Current state {state} is not defined"),
+            });
+        };
+        self.definition = definition;
+        self.state = index;
+        Ok(())
+    }
+
+    /// Evaluate `guard` whenever a transition is guarded by `name`. Replaces a previous guard.
+    pub fn register_guard(&mut self, name: &str, guard: impl Fn() -> bool + Send + 'static) {
+        self.guards.insert(name.to_string(), Box::new(guard));
+    }
+
+    /// Call `handler` whenever a transition with the handler `name` fires.
+    /// Replaces a previous handler.
+    pub fn register_handler(&mut self, name: &str, handler: impl FnMut() + Send + 'static) {
+        self.handlers.insert(name.to_string(), Box::new(handler));
+    }
+
+    /// Name of the current state.
+    pub fn state_name(&self) -> &str {
+        &self.definition.states[self.state]
+    }
+
+    /// Names of the inputs that can be consumed in the current state.
+    pub fn available_input_names(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        for input in &self.definition.inputs {
+            if self.can_consume(input) && !names.contains(&input.as_str()) {
+                names.push(input.as_str());
+            }
+        }
+        names
+    }
+
+    /// Check if the input would be consumed. Unregistered guards count as false.
+    pub fn can_consume(&self, input: &str) -> bool {
+        matches!(self.find(Some(input)), Ok(Some(_)))
+    }
+
+    /// Consume the input. Returns the name of the output, `"Nothing"` if there is none.
+    pub fn consume(&mut self, input: &str) -> Result<&str, DynamicError> {
+        if !self.definition.inputs.iter().any(|i| i == input) {
+            return Err(DynamicError::UnknownInput(input.to_string()));
+        }
+        self.fire(Some(input))
+    }
+
+    /// Take a spontaneous transition. Returns the name of the output, `"Nothing"` if there is none.
+    pub fn step(&mut self) -> Result<&str, DynamicError> {
+        self.fire(None)
+    }
+
+    fn fire(&mut self, input: Option<&str>) -> Result<&str, DynamicError> {
+        let Some(index) = self.find(input)? else {
+            return Err(DynamicError::Rejected {
+                state: self.state_name().to_string(),
+                input: input.unwrap_or("Nothing").to_string(),
+            });
+        };
+        let transition = &self.definition.transitions[index];
+        if let Some(name) = &transition.handler {
+            let handler = self
+                .handlers
+                .get_mut(name)
+                .ok_or_else(|| DynamicError::MissingHandler(name.clone()))?;
+            handler();
+        }
+        let to = &transition.to;
+        self.state = self.definition.states.iter().position(|s| s == to).unwrap();
+        #[cfg(feature = "log-transitions")]
+        log::debug!(
+            "({}, {}) -> ({})",
+            transition.from,
+            input.unwrap_or("Nothing"),
+            to
+        );
+        Ok(transition.output.as_deref().unwrap_or("Nothing"))
+    }
+
+    /// The first enabled transition, in the order of declaration.
+    fn find(&self, input: Option<&str>) -> Result<Option<usize>, DynamicError> {
+        let state = self.state_name();
+        for (index, t) in self.definition.transitions.iter().enumerate() {
+            if t.from != state || t.input.as_deref() != input {
+                continue;
+            }
+            let enabled = match &t.guard {
+                Some(guard) => self.eval(guard)?,
+                None => true,
+            };
+            if enabled {
+                return Ok(Some(index));
+            }
+        }
+        Ok(None)
+    }
+
+    fn eval(&self, guard: &Guard) -> Result<bool, DynamicError> {
+        Ok(match guard {
+            Guard::Call(name) => {
+                self.guards
+                    .get(name)
+                    .ok_or_else(|| DynamicError::MissingGuard(name.clone()))?()
+            }
+            Guard::Not(g) => !self.eval(g)?,
+            Guard::And(a, b) => self.eval(a)? && self.eval(b)?,
+            Guard::Or(a, b) => self.eval(a)? || self.eval(b)?,
+        })
+    }
+}
+
+impl fmt::Debug for DynamicMachine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DynamicMachine")
+            .field("state", &self.state_name())
+            .finish()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    // The last segment of a path.
+    Ident(String),
+    Punct(&'static str),
+}
+
+const PUNCTS: [&str; 10] = ["->", "&&", "||", "(", ")", "[", "]", ",", ":", "="];
+
+fn tokenize(text: &str) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut rest = text;
+    'outer: loop {
+        rest = rest.trim_start();
+        if rest.starts_with("//") {
+            rest = rest.split_once('\n').map_or("", |(_, r)| r);
+            continue;
+        }
+        let Some(c) = rest.chars().next() else {
+            return Ok(tokens);
+        };
+        if c.is_alphanumeric() || c == '_' {
+            let end = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == ':'))
+                .unwrap_or(rest.len());
+            let path = rest[..end].trim_end_matches(':');
+            let segment = path.rsplit("::").next().unwrap_or(path);
+            tokens.push(Token::Ident(segment.to_string()));
+            rest = &rest[path.len()..];
+            continue;
+        }
+        if c == '!' {
+            tokens.push(Token::Punct("!"));
+            rest = &rest[1..];
+            continue;
+        }
+        for punct in PUNCTS {
+            if let Some(r) = rest.strip_prefix(punct) {
+                tokens.push(Token::Punct(punct));
+                rest = r;
+                continue 'outer;
+            }
+        }
+        return Err(ParseError {
+            message: format!("Unexpected character {c:?}"),
+        });
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn new(text: &str) -> Result<Self, ParseError> {
+        Ok(Self {
+            tokens: tokenize(text)?,
+            pos: 0,
+        })
+    }
+
+    fn error<R>(&self, expected: &str) -> Result<R, ParseError> {
+        let found = match self.tokens.get(self.pos) {
+            Some(Token::Ident(ident)) => ident.clone(),
+            Some(Token::Punct(punct)) => punct.to_string(),
+            None => "end of input".to_string(),
+        };
+        Err(ParseError {
+            message: format!("Expected {expected}, found {found}"),
+        })
+    }
+
+    fn eat(&mut self, punct: &'static str) -> bool {
+        if self.tokens.get(self.pos) == Some(&Token::Punct(punct)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, punct: &'static str) -> Result<(), ParseError> {
+        if self.eat(punct) {
+            Ok(())
+        } else {
+            self.error(&format!("`{punct}`"))
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, ParseError> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Ident(ident)) => {
+                self.pos += 1;
+                Ok(ident.clone())
+            }
+            _ => self.error("a name"),
+        }
+    }
+
+    /// Items separated by commas, with an optional trailing comma, until `)`.
+    fn list<R>(
+        &mut self,
+        mut item: impl FnMut(&mut Self) -> Result<R, ParseError>,
+    ) -> Result<Vec<R>, ParseError> {
+        let mut items = Vec::new();
+        while !self.eat(")") {
+            items.push(item(self)?);
+            if !self.eat(",") {
+                self.expect(")")?;
+                break;
+            }
+        }
+        Ok(items)
+    }
+
+    fn definition(mut self) -> Result<Definition, ParseError> {
+        let mut definition = Definition::default();
+        while self.pos < self.tokens.len() {
+            let section = self.ident()?;
+            self.expect("(")?;
+            match section.as_str() {
+                "inputs" => definition.inputs = self.list(Self::ident)?,
+                "states" => definition.states = self.list(Self::ident)?,
+                "outputs" => definition.outputs = self.list(Self::ident)?,
+                "transitions" => definition.transitions = self.list(Self::transition)?,
+                _ => self.skip_group()?,
+            }
+            if !self.eat(",") && self.pos < self.tokens.len() {
+                return self.error("`,`");
+            }
+        }
+        validate(&definition)?;
+        Ok(definition)
+    }
+
+    /// Skip to the `)` closing an already opened group.
+    fn skip_group(&mut self) -> Result<(), ParseError> {
+        let mut depth = 1;
+        while depth > 0 {
+            match self.tokens.get(self.pos) {
+                Some(Token::Punct("(")) => depth += 1,
+                Some(Token::Punct(")")) => depth -= 1,
+                Some(_) => {}
+                None => return self.error("`)`"),
+            }
+            self.pos += 1;
+        }
+        Ok(())
+    }
+
+    /// `[hint] (State, Input) -> (State, Output) : guard = handler`, hint, input,
+    /// output, guard and handler being optional. The hint only matters to the compiler.
+    fn transition(&mut self) -> Result<Transition, ParseError> {
+        if self.eat("[") {
+            self.ident()?;
+            self.expect("]")?;
+        }
+        self.expect("(")?;
+        let from = self.ident()?;
+        let input = if self.eat(",") {
+            Some(self.ident()?)
+        } else {
+            None
+        };
+        self.expect(")")?;
+        self.expect("->")?;
+        self.expect("(")?;
+        let to = self.ident()?;
+        let output = if self.eat(",") {
+            Some(self.ident()?)
+        } else {
+            None
+        };
+        self.expect(")")?;
+        let guard = if self.eat(":") {
+            Some(self.guard_or()?)
+        } else {
+            None
+        };
+        let handler = if self.eat("=") {
+            Some(self.ident()?)
+        } else {
+            None
+        };
+        Ok(Transition {
+            from,
+            input,
+            to,
+            output,
+            guard,
+            handler,
+        })
+    }
+
+    fn guard_or(&mut self) -> Result<Guard, ParseError> {
+        let mut guard = self.guard_and()?;
+        while self.eat("||") {
+            guard = Guard::Or(Box::new(guard), Box::new(self.guard_and()?));
+        }
+        Ok(guard)
+    }
+
+    fn guard_and(&mut self) -> Result<Guard, ParseError> {
+        let mut guard = self.guard_unary()?;
+        while self.eat("&&") {
+            guard = Guard::And(Box::new(guard), Box::new(self.guard_unary()?));
+        }
+        Ok(guard)
+    }
+
+    fn guard_unary(&mut self) -> Result<Guard, ParseError> {
+        if self.eat("!") {
+            return Ok(Guard::Not(Box::new(self.guard_unary()?)));
+        }
+        if self.eat("(") {
+            let guard = self.guard_or()?;
+            self.expect(")")?;
+            return Ok(guard);
+        }
+        Ok(Guard::Call(self.ident()?))
+    }
+}
+
+fn validate(definition: &Definition) -> Result<(), ParseError> {
+    let fail = |message: String| Err(ParseError { message });
+    if definition.states.is_empty() {
+        return fail("No states are defined".to_string());
+    }
+    for t in &definition.transitions {
+        for state in [&t.from, &t.to] {
+            if !definition.states.contains(state) {
+                return fail(format!("Unknown state: {state}"));
+            }
+        }
+        if let Some(input) = t.input.as_ref().filter(|i| !definition.inputs.contains(i)) {
+            return fail(format!("Unknown input: {input}"));
+        }
+        if let Some(output) = t
+            .output
+            .as_ref()
+            .filter(|o| !definition.outputs.contains(o))
+        {
+            return fail(format!("Unknown output: {output}"));
+        }
+    }
+    Ok(())
+}
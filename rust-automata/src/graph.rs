@@ -0,0 +1,48 @@
+//! The structure of a machine, available at runtime through [`StateMachineImpl::graph`][crate::StateMachineImpl::graph].
+//!
+//! Symbols are referred to by their ids, the same as in [`EnumId`][crate::EnumId]:
+//! state 0 is `Failure`, input and output 0 are `Nothing`.
+
+/// A transition, as declared in the `transitions(...)` section.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Edge {
+    pub from: usize,
+    pub input: usize,
+    pub to: usize,
+    pub output: usize,
+    /// The guard expression, e.g. `"!guard_timeout"`.
+    pub guard: Option<&'static str>,
+    pub handler: Option<&'static str>,
+}
+
+/// States, input and output alphabets and transitions of a machine.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MachineGraph {
+    pub name: &'static str,
+    /// Hash of the states, alphabets and transitions (without guards and handlers).
+    /// Changes whenever the structure of the machine changes.
+    pub schema: u64,
+    /// State names, indexed by id.
+    pub states: &'static [&'static str],
+    /// Input names, indexed by id.
+    pub inputs: &'static [&'static str],
+    /// Output names, indexed by id.
+    pub outputs: &'static [&'static str],
+    /// Transitions in the order of declaration.
+    pub transitions: &'static [Edge],
+}
+
+impl MachineGraph {
+    /// Id of the initial state.
+    pub const INITIAL_STATE: usize = 1;
+
+    /// Id of the state with the given name.
+    pub fn state_id(&self, name: &str) -> Option<usize> {
+        self.states.iter().position(|s| *s == name)
+    }
+
+    /// Transitions leaving the state.
+    pub fn edges_from(&self, state: usize) -> impl Iterator<Item = &Edge> + '_ {
+        self.transitions.iter().filter(move |e| e.from == state)
+    }
+}
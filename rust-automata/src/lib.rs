@@ -9,18 +9,39 @@ pub use rust_automata_macros::Display;
 #[cfg(feature = "mermaid")]
 pub use aquamarine::aquamarine;
 
+#[cfg(feature = "tokio")]
+pub mod actor;
+pub mod analysis;
+pub mod builder;
+pub mod bus;
 pub mod clock;
-#[doc(hidden)]
-mod takeable;
+pub mod dyn_machine;
+pub mod dynamic;
+pub mod graph;
+#[cfg(feature = "proptest")]
+pub mod model;
+#[cfg(feature = "persistence")]
+pub mod persistence;
+pub mod pipeline;
+pub mod recorder;
+pub mod scheduler;
+pub mod shared;
+#[cfg(feature = "rand")]
+pub mod simulation;
+pub mod source;
+#[cfg(feature = "proptest")]
+pub mod strategy;
+#[cfg(feature = "futures")]
+pub mod stream;
 pub mod timestamp;
+mod undo;
+#[cfg(feature = "zero-cost")]
+pub mod zero_cost;
 
 use core::fmt::Display;
+use std::collections::{HashMap, VecDeque};
 use std::hash::Hash;
 use std::marker::PhantomData;
-use log;
-
-#[doc(hidden)]
-pub use takeable::Takeable;
 
 /// Trait for input/output alphabet. Used for internal enum generation.
 ///
@@ -44,6 +65,23 @@ pub trait StateTrait: Display {
 #[doc(hidden)]
 pub trait Enumerable<ForEnum> {
     fn enum_id(&self) -> EnumId<ForEnum>;
+    /// Number of variants, including the `Nothing`/`Failure` variant with id 0.
+    fn variant_count() -> usize;
+    /// Build the variant with the given id, if its struct implements `Default`.
+    fn default_of(id: &EnumId<ForEnum>) -> Option<Self>
+    where
+        Self: Sized;
+    /// Wrap the boxed struct in the variant with the given id, or give it back if it has another type.
+    fn from_any(
+        id: &EnumId<ForEnum>,
+        value: Box<dyn std::any::Any>,
+    ) -> Result<Self, Box<dyn std::any::Any>>
+    where
+        Self: Sized;
+    /// The wrapped struct.
+    fn as_any(&self) -> &dyn std::any::Any;
+    /// The wrapped struct, mutably.
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any;
     fn get_variant(id: &EnumId<ForEnum>) -> &'static str;
 }
 
@@ -53,35 +91,237 @@ pub trait Enumerated<InEnum> {
     fn enum_id() -> EnumId<InEnum>;
 }
 
-/// For internal use only.
-#[doc(hidden)]
-#[derive(Clone, Copy, Debug, Eq, PartialOrd, Ord, Default)]
+/// A cheap handle to a state or a symbol, without its payload.
+///
+/// Ids are `Copy`, `Eq` and `Hash`, so they can be stored in maps and compared across snapshots
+/// of the same machine definition. Id 0 is the `Failure` state or the `Nothing` symbol,
+/// the others follow the order of declaration. See [`StateId`], [`InputId`] and [`OutputId`].
+///
+/// The id is a `u16`, so that tables of ids stay small; a machine has at most 65535 states
+/// and symbols of each kind.
 pub struct EnumId<ForEnum> {
-    pub id: usize,
+    pub id: u16,
     _marker: PhantomData<ForEnum>,
 }
 
+// Manual impls, so that the wrapped enum does not need to implement the traits.
+impl<ForEnum> Clone for EnumId<ForEnum> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<ForEnum> Copy for EnumId<ForEnum> {}
+
+impl<ForEnum> std::fmt::Debug for EnumId<ForEnum> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("EnumId").field(&self.id).finish()
+    }
+}
+
 impl<ForEnum> PartialEq for EnumId<ForEnum> {
     fn eq(&self, other: &Self) -> bool {
         self.id == other.id
     }
 }
 
+impl<ForEnum> Eq for EnumId<ForEnum> {}
+
+impl<ForEnum> PartialOrd for EnumId<ForEnum> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<ForEnum> Ord for EnumId<ForEnum> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.id.cmp(&other.id)
+    }
+}
+
+impl<ForEnum> Default for EnumId<ForEnum> {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
 impl<ForEnum> Hash for EnumId<ForEnum> {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         self.id.hash(state);
     }
 }
 
+/// Id of a state of the machine `T`.
+pub type StateId<T> = EnumId<<T as StateMachineImpl>::State>;
+/// Id of an input of the machine `T`.
+pub type InputId<T> = EnumId<<T as StateMachineImpl>::Input>;
+/// Id of an output of the machine `T`.
+pub type OutputId<T> = EnumId<<T as StateMachineImpl>::Output>;
+
+impl<ForEnum: Enumerable<ForEnum>> EnumId<ForEnum> {
+    /// Name of the state or symbol.
+    pub fn name(&self) -> &'static str {
+        ForEnum::get_variant(self)
+    }
+}
+
+impl<ForEnum: Enumerable<ForEnum>> Display for EnumId<ForEnum> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
 impl<ForEnum> EnumId<ForEnum> {
-    pub fn new(id: usize) -> Self {
+    /// The id with the given index. Used by the generated code, ids are not checked
+    /// against the enum.
+    ///
+    /// # Panics
+    ///
+    /// If the index does not fit into a `u16`, at compile time in a `const`.
+    pub const fn new(id: usize) -> Self {
+        assert!(id <= u16::MAX as usize, "EnumId out of range of u16");
         EnumId {
-            id,
+            id: id as u16,
             _marker: PhantomData,
         }
     }
+
+    /// The id as an index into tables of states or symbols.
+    pub const fn index(&self) -> usize {
+        self.id as usize
+    }
+}
+
+/// Helpers for the generated code. For internal use only.
+#[doc(hidden)]
+pub mod __private {
+    use std::marker::PhantomData;
+
+    /// Autoref-based specialization: `(&Probe::<S>::new()).make()` resolves to
+    /// [`ViaDefault`] if `S: Default` and falls back to [`ViaNone`] otherwise.
+    pub struct Probe<S>(PhantomData<S>);
+
+    impl<S> Probe<S> {
+        #[allow(clippy::new_without_default)]
+        pub fn new() -> Self {
+            Probe(PhantomData)
+        }
+    }
+
+    pub trait ViaDefault<S> {
+        fn make(&self) -> Option<S>;
+    }
+
+    impl<S: Default> ViaDefault<S> for Probe<S> {
+        fn make(&self) -> Option<S> {
+            Some(S::default())
+        }
+    }
+
+    pub trait ViaNone<S> {
+        fn make(&self) -> Option<S>;
+    }
+
+    impl<S> ViaNone<S> for &Probe<S> {
+        fn make(&self) -> Option<S> {
+            None
+        }
+    }
+
+    /// Called first thing on the `[cold]` transitions, so that the optimizer treats
+    /// the code reaching them as unlikely.
+    #[cold]
+    #[inline]
+    pub fn cold_path() {}
+
+    // The generated getters, conversions and `get_variant`s call the helpers below instead of
+    // each expanding their own panic formatting, which adds up for large alphabets.
+
+    /// The name of the variant `id`, given the names of all the variants of the enum.
+    #[inline]
+    pub fn variant_name<E>(names: &'static [&'static str], id: &super::EnumId<E>) -> &'static str {
+        match names.get(id.index()) {
+            Some(name) => name,
+            None => invalid_id(id.index()),
+        }
+    }
+
+    /// The symbol behind a `maybe_*` getter, for the direct getter named `name`.
+    #[inline]
+    #[track_caller]
+    pub fn expect_symbol<'a, S>(symbol: Option<&'a S>, name: &'static str) -> &'a S {
+        match symbol {
+            Some(symbol) => symbol,
+            None => no_such_symbol(name),
+        }
+    }
+
+    #[cold]
+    #[inline(never)]
+    #[track_caller]
+    fn invalid_id(id: usize) -> ! {
+        panic!("Invalid symbol requested from id: {id}")
+    }
+
+    #[cold]
+    #[inline(never)]
+    #[track_caller]
+    fn no_such_symbol(name: &str) -> ! {
+        panic!("No such symbol like {name}")
+    }
+
+    /// Converting an enum to a symbol it doesn't hold.
+    #[cold]
+    #[inline(never)]
+    #[track_caller]
+    pub fn invalid_symbol(requested: &str) -> ! {
+        panic!("Invalid symbol requested from {requested}")
+    }
+}
+
+/// Next state and output ids of a transition. For internal use only.
+#[doc(hidden)]
+pub type TransitionTarget<State, Output> = (EnumId<State>, EnumId<Output>);
+
+/// Ids of the symbols of a transition.
+pub struct TransitionIds<T: StateMachineImpl> {
+    pub from_state: StateId<T>,
+    pub input: InputId<T>,
+    pub to_state: StateId<T>,
+    pub output: OutputId<T>,
+}
+
+impl<T: StateMachineImpl> Clone for TransitionIds<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
 }
 
+impl<T: StateMachineImpl> Copy for TransitionIds<T> {}
+
+/// An input from a batch that has no enabled transition in the current state.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RejectedInput {
+    /// Position of the rejected input in the batch.
+    pub index: usize,
+    /// Name of the state in which the input was rejected.
+    pub state: &'static str,
+    /// Name of the rejected input.
+    pub input: &'static str,
+}
+
+impl Display for RejectedInput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Input {} at position {} rejected in state {}",
+            self.input, self.index, self.state
+        )
+    }
+}
+
+impl std::error::Error for RejectedInput {}
+
 /// Describe any possible deterministic finite state  machine/transducer.
 ///
 /// This is just a formal definition that may be inconvenient to be used in practical programming,
@@ -91,36 +331,166 @@ impl<ForEnum> EnumId<ForEnum> {
 #[doc(hidden)]
 pub trait StateMachineImpl {
     /// The input alphabet enum.
-    type Input: Alphabet + Enumerable<Self::Input>;
+    type Input: Alphabet + Enumerable<Self::Input> + 'static;
     /// The possible states enum.
-    type State: StateTrait + Enumerable<Self::State>;
+    type State: StateTrait + Enumerable<Self::State> + 'static;
     /// The output alphabet enum.
-    type Output: Alphabet + Enumerable<Self::Output>;
+    type Output: Alphabet + Enumerable<Self::Output> + 'static;
     /// The initial state (an actual enum value). May be needed to be supplied manually by the user.
     type InitialState: Enumerated<Self::State> + Into<Self::State>;
     /// The nothing input/output symbol.
     type Nothing: Enumerated<Self::Input> + Enumerated<Self::Output> + Into<Self::Input> + From<Self::Output> + Default;
-    /// The transition function that takes ownership of the current state and returns
-    /// a new state along with any output based on the provided input.
-    fn transition(
-        &mut self,
-        state: Takeable<Self::State>,
-        input: Self::Input,
-    ) -> (Takeable<Self::State>, Self::Output);
-    /// Check if a transition is possible. If yes, return the output enum id.
+    /// The transition function that replaces the current state by the next state and returns
+    /// the output based on the provided input.
+    ///
+    /// The state is `Failure` if there is no enabled transition, or if a handler panics.
+    fn transition(&mut self, state: &mut Self::State, input: Self::Input) -> Self::Output;
+    /// Check if a transition is possible. If yes, return the next state and output enum ids.
     fn can_transition(
         &self,
         state: &Self::State,
         input: EnumId<Self::Input>,
-    ) -> Option<EnumId<Self::Output>>;
+    ) -> Option<TransitionTarget<Self::State, Self::Output>>;
+    /// All the states except `Failure`, with their names, in the order of declaration.
+    const ALL_STATES: &'static [(EnumId<Self::State>, &'static str)];
+    /// All the inputs except `Nothing`, with their names, in the order of declaration.
+    const ALL_INPUTS: &'static [(EnumId<Self::Input>, &'static str)];
+    /// All the outputs except `Nothing`, with their names, in the order of declaration.
+    const ALL_OUTPUTS: &'static [(EnumId<Self::Output>, &'static str)];
     /// The name of the state machine.
     fn name() -> &'static str;
+    /// The structure of the state machine.
+    fn graph() -> &'static graph::MachineGraph;
+}
+
+/// Expose the timers that guard spontaneous transitions, see [`StateMachine::advance_time`].
+pub trait Timers: StateMachineImpl {
+    /// Call `f` on every timer that is relevant in the given state.
+    fn visit_timers(&self, state: &Self::State, f: &mut dyn FnMut(&clock::Timer));
 }
 
+/// Let the transition handlers put inputs into the queue, see
+/// [`StateMachine::process_queue_with_generated`].
+pub trait GeneratesInputs: StateMachineImpl {
+    /// Call `f` on every input generated since the last call, with its priority, and forget them.
+    fn take_generated(&mut self, f: &mut dyn FnMut(Self::Input, i32));
+}
+
+type StateHook<State> = Box<dyn FnMut(&State) + Send>;
+type Observer<T> = Box<dyn FnMut(&TransitionIds<T>) + Send>;
+type OutputSink<Output> = Box<dyn FnMut(Output) + Send>;
+// The outputs, or the position of the rejected input and the outputs before it.
+type QueueResult<Output> = Result<Vec<Output>, (usize, Vec<Output>)>;
+
 /// Encapsulates the state and other SM data and expose transition functions.
 pub struct StateMachine<T: StateMachineImpl> {
-    state: Takeable<T::State>,
+    state: T::State,
     data: T,
+    extras: LazyExtras<T>,
+}
+
+/// The [`Extras`] of a machine, allocated on first use, so that a machine without them
+/// is only its state and data.
+struct LazyExtras<T: StateMachineImpl>(Option<Box<Extras<T>>>);
+
+impl<T: StateMachineImpl> LazyExtras<T> {
+    fn get(&self) -> Option<&Extras<T>> {
+        self.0.as_deref()
+    }
+
+    fn get_mut(&mut self) -> Option<&mut Extras<T>> {
+        self.0.as_deref_mut()
+    }
+
+    fn get_or_default(&mut self) -> &mut Extras<T> {
+        self.0.get_or_insert_with(Box::default)
+    }
+}
+
+impl<T: StateMachineImpl> Drop for LazyExtras<T> {
+    // Only the check is inlined, so that a machine without extras has nothing to drop.
+    #[inline]
+    fn drop(&mut self) {
+        if let Some(extras) = self.0.take() {
+            drop_extras(extras);
+        }
+    }
+}
+
+#[inline(never)]
+fn drop_extras<T: StateMachineImpl>(extras: Box<Extras<T>>) {
+    drop(extras);
+}
+
+/// The optional parts of a machine: the input queue, the transition hooks, the undo history
+/// and the checkpoints.
+struct Extras<T: StateMachineImpl> {
+    // Inputs with their priorities, the highest priority at the front.
+    queue: VecDeque<(i32, T::Input)>,
+    // Called with the new state after every transition.
+    persist: Option<StateHook<T::State>>,
+    observers: Vec<Observer<T>>,
+    // Receives the outputs that `consume`-style calls would discard.
+    sink: Option<OutputSink<T::Output>>,
+    undo: Option<undo::UndoStack<T>>,
+    checkpoints: HashMap<String, undo::Checkpoint<T>>,
+}
+
+impl<T: StateMachineImpl> Default for Extras<T> {
+    fn default() -> Self {
+        Self {
+            queue: VecDeque::new(),
+            persist: None,
+            observers: Vec::new(),
+            sink: None,
+            undo: None,
+            checkpoints: HashMap::new(),
+        }
+    }
+}
+
+impl<T: StateMachineImpl> Extras<T> {
+    /// See [`StateMachine::is_plain`].
+    fn is_plain(&self) -> bool {
+        self.observers.is_empty()
+            && self.sink.is_none()
+            && self.persist.is_none()
+            && self.undo.is_none()
+    }
+
+    fn enqueue(&mut self, input: T::Input, priority: i32) {
+        let position = self.queue.partition_point(|(p, _)| *p >= priority);
+        self.queue.insert(position, (priority, input));
+    }
+
+    // The hooks are kept out of line, so that `fire` stays small enough to be inlined
+    // into machines without them.
+    #[inline(never)]
+    fn before_transition(&mut self, state: &T::State, data: &T) {
+        if let Some(undo) = &mut self.undo {
+            undo.record(state, data);
+        }
+    }
+
+    #[inline(never)]
+    fn after_transition(&mut self, state: &T::State, ids: TransitionIds<T>) {
+        if let Some(persist) = &mut self.persist {
+            persist(state);
+        }
+        for observer in &mut self.observers {
+            observer(&ids);
+        }
+    }
+}
+
+impl<T> Default for StateMachine<T>
+where
+    T: StateMachineImpl + Default,
+    T::InitialState: Default,
+{
+    fn default() -> Self {
+        Self::new_default()
+    }
 }
 
 impl<T> StateMachine<T>
@@ -129,18 +499,141 @@ where
 {
     /// Create a new instance of this wrapper which encapsulates the initial state.
     pub fn new(data: T, initial_state: T::InitialState) -> Self {
+        Self::from_parts(data, initial_state.into())
+    }
+
+    /// Create a machine with the default data, starting in the default initial state.
+    pub fn new_default() -> Self
+    where
+        T: Default,
+        T::InitialState: Default,
+    {
+        Self::new(T::default(), T::InitialState::default())
+    }
+
+    /// Start building a machine, see [`builder::Builder`].
+    pub fn builder() -> builder::Builder<T> {
+        builder::Builder::new()
+    }
+
+    /// Call `observer` after every successful transition, in the order the observers were added.
+    pub fn observe(&mut self, observer: impl FnMut(&TransitionIds<T>) + Send + 'static) {
+        self.extras_mut().observers.push(Box::new(observer));
+    }
+
+    /// Deliver the outputs of [`consume`][Self::consume], [`consume_iter`][Self::consume_iter]
+    /// and [`pump`][Self::pump] to `sink` instead of discarding them. `Nothing` is not delivered.
+    ///
+    /// Replaces the previous sink.
+    pub fn set_output_sink(&mut self, sink: impl FnMut(T::Output) + Send + 'static) {
+        self.extras_mut().sink = Some(Box::new(sink));
+    }
+
+    /// Discard the outputs of `consume`-style calls again.
+    pub fn clear_output_sink(&mut self) {
+        if let Some(extras) = self.extras.get_mut() {
+            extras.sink = None;
+        }
+    }
+
+    pub(crate) fn deliver(&mut self, output: T::Output) {
+        if let Some(sink) = self
+            .extras
+            .get_mut()
+            .and_then(|extras| extras.sink.as_mut())
+        {
+            if output.any() {
+                sink(output);
+            }
+        }
+    }
+
+    fn from_parts(data: T, state: T::State) -> Self {
         Self {
-            state: Takeable::new(initial_state.into()),
+            state,
             data,
+            extras: LazyExtras(None),
         }
     }
 
+    /// The queue, hooks, undo history and checkpoints, allocated on first use.
+    pub(crate) fn extras_mut(&mut self) -> &mut Extras<T> {
+        self.extras.get_or_default()
+    }
+
+    /// Overwrite the current state, bypassing the transition function.
+    ///
+    /// Meant for unit tests that need to start mid-flow.
+    #[cfg(feature = "testing")]
+    pub fn force_state(&mut self, state: impl Into<T::State>) {
+        self.state = state.into();
+    }
+
     /// Only change the state, do not accept any input and do not produce any output.
     #[inline]
     pub fn step(&mut self) {
         self.relay::<T::Nothing, T::Nothing>(T::Nothing::default());
     }
 
+    /// Keep stepping while a spontaneous transition is enabled. Returns the number of steps taken.
+    ///
+    /// Stops after a step that stays in the same state, as such self-loops would otherwise
+    /// spin forever (e.g. waiting for a timeout guard).
+    pub fn run_until_blocked(&mut self) -> usize {
+        self.step_while(|_| true)
+    }
+
+    /// Fire spontaneous transitions as long as a timer of the current state has expired.
+    /// Returns the number of steps taken.
+    ///
+    /// Like [`run_until_blocked`][Self::run_until_blocked], stops after a step that stays in the
+    /// same state.
+    pub fn advance_time(&mut self) -> usize
+    where
+        T: Timers,
+    {
+        self.step_while(|sm| {
+            let mut expired = false;
+            sm.data
+                .visit_timers(&sm.state, &mut |timer| expired |= timer.is_timeout());
+            expired
+        })
+    }
+
+    /// Step while `condition` holds and a spontaneous transition is enabled,
+    /// but at most once when the state does not change.
+    fn step_while(&mut self, condition: impl Fn(&Self) -> bool) -> usize {
+        let mut steps = 0;
+        while condition(self) && self.can_step() {
+            let from_id = self.state.enum_id();
+            self.step();
+            steps += 1;
+            if self.state.enum_id() == from_id {
+                break;
+            }
+        }
+        steps
+    }
+
+    /// Fire spontaneous transitions lazily, yielding their outputs and skipping `Nothing`.
+    ///
+    /// Like [`run_until_blocked`][Self::run_until_blocked], stops after a step that stays in the
+    /// same state.
+    pub fn drain_outputs(&mut self) -> impl Iterator<Item = T::Output> + '_ {
+        let mut blocked = false;
+        std::iter::from_fn(move || {
+            while !blocked && self.can_step() {
+                let from_id = self.state.enum_id();
+                let output = self.fire(T::Nothing::default().into());
+                blocked = self.state.enum_id() == from_id;
+                if output.any() {
+                    return Some(output);
+                }
+            }
+            None
+        })
+    }
+
     /// Produce an output, given no input.
     #[inline]
     pub fn produce<O: From<T::Output> + Enumerated<T::Output>>(&mut self) -> O {
@@ -148,48 +641,301 @@ where
     }
 
     /// Consume an input, do not care about the output.
+    ///
+    /// The output goes to the [output sink][Self::set_output_sink], if there is one.
     #[inline]
     pub fn consume<I: Into<T::Input> + Enumerated<T::Input>>(&mut self, input: I) {
-        self.relay::<I, T::Output>(input);
+        let output = self.relay::<I, T::Output>(input);
+        self.deliver(output);
     }
 
     /// Consume an input, produce an output.
     #[inline]
     pub fn relay<I: Into<T::Input> + Enumerated<T::Input>, O: From<T::Output>>(&mut self, input: I) -> O {
-        let enum_input: T::Input = input.into();
+        O::from(self.fire(input.into()))
+    }
+
+    /// Consume an input, produce an output if the transition emitted one of type `O`.
+    ///
+    /// Returns `None` if the transition produced `Nothing` (or a different output symbol).
+    #[inline]
+    pub fn relay_opt<I, O>(&mut self, input: I) -> Option<O>
+    where
+        I: Into<T::Input> + Enumerated<T::Input>,
+        O: From<T::Output> + Enumerated<T::Output>,
+    {
+        let output = self.fire(input.into());
+        (output.enum_id() == O::enum_id()).then(|| O::from(output))
+    }
+
+    /// Whether transitions need no bookkeeping: no observers, output sink, persistence
+    /// or undo history.
+    fn is_plain(&self) -> bool {
+        match self.extras.get() {
+            Some(extras) => extras.is_plain(),
+            None => true,
+        }
+    }
+
+    /// Consume an input through a transition function specialized for its type, behind the
+    /// `consume_*` methods generated with `consume_methods(true)`. Like
+    /// [`consume_slice`][Self::consume_slice], machines that are not plain fall back to
+    /// [`consume`][Self::consume].
+    #[doc(hidden)]
+    #[inline]
+    pub fn consume_direct<I>(
+        &mut self,
+        input: I,
+        transition: impl FnOnce(&mut T, &mut T::State, I) -> EnumId<T::Output>,
+    ) where
+        I: Into<T::Input> + Enumerated<T::Input>,
+    {
+        if !self.is_plain() {
+            self.consume(input);
+            return;
+        }
+        let from_id = self.state.enum_id();
+        let _output_id = transition(&mut self.data, &mut self.state, input);
+        if self.state.is_failure() {
+            Self::invalid_transition(from_id, I::enum_id());
+        }
+        #[cfg(feature = "log-transitions")]
+        Self::log_transition(from_id, I::enum_id(), self.state.enum_id(), _output_id);
+    }
+
+    /// Consume all the inputs in order, do not care about the outputs.
+    ///
+    /// The fast path for machines fed many symbols: plain machines run the transitions in one
+    /// loop, skipping the checks for observers, an output sink, persistence and undo history
+    /// on every input. The transitions are logged like in [`consume`][Self::consume].
+    ///
+    /// # Panics
+    ///
+    /// If there is no enabled transition for an input.
+    pub fn consume_slice(&mut self, inputs: &[T::Input])
+    where
+        T::Input: Clone,
+    {
+        if !self.is_plain() {
+            for input in inputs {
+                let output = self.fire(input.clone());
+                self.deliver(output);
+            }
+            return;
+        }
+        for input in inputs {
+            let from_id = self.state.enum_id();
+            let input_id = input.enum_id();
+            let _output = self.data.transition(&mut self.state, input.clone());
+            if self.state.is_failure() {
+                Self::invalid_transition(from_id, input_id);
+            }
+            #[cfg(feature = "log-transitions")]
+            Self::log_transition(from_id, input_id, self.state.enum_id(), _output.enum_id());
+        }
+    }
+
+    /// Consume all the inputs in order, do not care about the outputs.
+    ///
+    /// Stops at the first input without an enabled transition and returns its position.
+    /// The machine is then left in the state reached by the preceding inputs.
+    pub fn consume_iter<I: Into<T::Input>>(
+        &mut self,
+        inputs: impl IntoIterator<Item = I>,
+    ) -> Result<(), RejectedInput> {
+        self.relay_iter_with(inputs, Self::deliver)
+    }
+
+    /// Consume all the inputs in order, appending the produced outputs to `outputs`.
+    ///
+    /// `Nothing` outputs are skipped. On a rejected input, the outputs of the preceding inputs
+    /// are kept in `outputs`, see [`consume_iter`][Self::consume_iter].
+    pub fn relay_iter<I: Into<T::Input>>(
+        &mut self,
+        inputs: impl IntoIterator<Item = I>,
+        outputs: &mut Vec<T::Output>,
+    ) -> Result<(), RejectedInput> {
+        self.relay_iter_with(inputs, |_, output| {
+            if output.any() {
+                outputs.push(output);
+            }
+        })
+    }
+
+    fn relay_iter_with<I: Into<T::Input>>(
+        &mut self,
+        inputs: impl IntoIterator<Item = I>,
+        mut on_output: impl FnMut(&mut Self, T::Output),
+    ) -> Result<(), RejectedInput> {
+        for (index, input) in inputs.into_iter().enumerate() {
+            let output = self
+                .try_fire(input.into())
+                .map_err(|rejected| RejectedInput { index, ..rejected })?;
+            on_output(self, output);
+        }
+        Ok(())
+    }
+
+    /// Like [`fire`][Self::fire], but reject the input instead of panicking.
+    pub(crate) fn try_fire(&mut self, enum_input: T::Input) -> Result<T::Output, RejectedInput> {
+        self.check_input(enum_input.enum_id())?;
+        Ok(self.fire(enum_input))
+    }
+
+    /// The output of the transition the input would take, or the input rejected.
+    pub(crate) fn check_input(
+        &self,
+        input_id: EnumId<T::Input>,
+    ) -> Result<EnumId<T::Output>, RejectedInput> {
+        match self.data.can_transition(&self.state, input_id) {
+            Some((_, output_id)) => Ok(output_id),
+            None => Err(RejectedInput {
+                index: 0,
+                state: self.state_name(),
+                input: T::Input::get_variant(&input_id),
+            }),
+        }
+    }
+
+    /// Put an input at the back of the internal queue, without processing it yet.
+    pub fn enqueue<I: Into<T::Input>>(&mut self, input: I) {
+        self.enqueue_with_priority(input, 0);
+    }
+
+    /// Put an input into the internal queue ahead of all inputs with a lower priority,
+    /// but behind those with the same or higher priority. [`enqueue`][Self::enqueue] uses priority 0.
+    ///
+    /// E.g. a `Shutdown` input with priority 1 overtakes the buffered work items.
+    pub fn enqueue_with_priority<I: Into<T::Input>>(&mut self, input: I, priority: i32) {
+        self.extras_mut().enqueue(input.into(), priority);
+    }
+
+    /// Process queued inputs in order of priority, FIFO within the same priority,
+    /// until the queue is empty.
+    ///
+    /// Returns the produced outputs in order, skipping the `Nothing` outputs.
+    /// Processing stops at the first input without an enabled transition, which is dropped.
+    /// The error holds its position among the processed inputs and the outputs produced
+    /// before it, the inputs behind it stay queued.
+    ///
+    /// Only the inputs enqueued by the caller are processed, see
+    /// [`process_queue_with_generated`][Self::process_queue_with_generated] to also process
+    /// the inputs generated by the handlers.
+    pub fn process_queue(&mut self) -> QueueResult<T::Output> {
+        self.process_queue_with(|_| {})
+    }
+
+    /// Like [`process_queue`][Self::process_queue], but before every input the inputs generated
+    /// by the handlers are queued as well, so a handler can defer an input or trigger new ones.
+    pub fn process_queue_with_generated(&mut self) -> QueueResult<T::Output>
+    where
+        T: GeneratesInputs,
+    {
+        self.process_queue_with(|sm| {
+            let extras = &mut sm.extras;
+            sm.data.take_generated(&mut |input, priority| {
+                extras.get_or_default().enqueue(input, priority);
+            });
+        })
+    }
+
+    fn process_queue_with(
+        &mut self,
+        mut before_input: impl FnMut(&mut Self),
+    ) -> QueueResult<T::Output> {
+        let mut outputs = Vec::new();
+        let mut index = 0;
+        loop {
+            before_input(self);
+            let Some((_, input)) = self
+                .extras
+                .get_mut()
+                .and_then(|extras| extras.queue.pop_front())
+            else {
+                return Ok(outputs);
+            };
+            match self.try_fire(input) {
+                Ok(output) if output.any() => outputs.push(output),
+                Ok(_) => {}
+                Err(_) => return Err((index, outputs)),
+            }
+            index += 1;
+        }
+    }
+
+    /// Number of inputs waiting in the internal queue.
+    pub fn queue_len(&self) -> usize {
+        self.extras.get().map_or(0, |extras| extras.queue.len())
+    }
+
+    /// Kept out of [`fire`][Self::fire], so that the formatting is not inlined into every caller.
+    #[cold]
+    #[inline(never)]
+    fn invalid_transition(from_id: EnumId<T::State>, input_id: EnumId<T::Input>) -> ! {
+        let from_str = T::State::get_variant(&from_id);
+        let input_str = T::Input::get_variant(&input_id);
+        panic!("Invalid transition from {from_str} using input {input_str}");
+    }
+
+    /// Run a single transition on the already wrapped input, returning the wrapped output.
+    ///
+    /// The typed methods like [`relay`][Self::relay] only convert the symbols and call this one,
+    /// so a machine driven with many symbol types shares a single copy of the transition code.
+    ///
+    /// # Panics
+    ///
+    /// If there is no enabled transition for the input.
+    pub fn fire(&mut self, enum_input: T::Input) -> T::Output {
         // Store only the ids so we don't have to prematurely call `to_string` on the enums.
-        let from_id = self.state.as_ref().enum_id();
+        let from_id = self.state.enum_id();
         let input_id = enum_input.enum_id();
 
-        // Take ownership of the current state
-        let current_state = std::mem::replace(&mut self.state, Takeable::new(T::State::failure()));
-
-        // Call transition with owned state
-        let (next_state, output) = self.data.transition(current_state, enum_input);
+        if let Some(extras) = self.extras.get_mut() {
+            extras.before_transition(&self.state, &self.data);
+        }
 
-        // Update state with the result
-        self.state = next_state;
+        let output = self.data.transition(&mut self.state, enum_input);
 
         if self.state.is_failure() {
-            let from_str = T::State::get_variant(&from_id);
-            let input_str = T::Input::get_variant(&input_id);
-            panic!("Invalid transition from {from_str} using input {input_str}");
-        } else {
-            log::debug!("{}: ({}, {}) -> ({}, {})", 
-                T::name(),
-                T::State::get_variant(&from_id), 
-                T::Input::get_variant(&input_id),
-                T::State::get_variant(&self.state.as_ref().enum_id()),
-                T::Output::get_variant(&output.enum_id()),
-            );
+            Self::invalid_transition(from_id, input_id);
         }
-        O::from(output)
+        #[cfg(feature = "log-transitions")]
+        Self::log_transition(from_id, input_id, self.state.enum_id(), output.enum_id());
+        if let Some(extras) = self.extras.get_mut() {
+            let ids = TransitionIds {
+                from_state: from_id,
+                input: input_id,
+                to_state: self.state.enum_id(),
+                output: output.enum_id(),
+            };
+            extras.after_transition(&self.state, ids);
+        }
+        output
+    }
+
+    /// Kept out of [`fire`][Self::fire] like the hooks, so that `fire` is inlined.
+    #[cfg(feature = "log-transitions")]
+    #[inline(never)]
+    fn log_transition(
+        from: EnumId<T::State>,
+        input: EnumId<T::Input>,
+        to: EnumId<T::State>,
+        output: EnumId<T::Output>,
+    ) {
+        log::debug!(
+            "{}: ({}, {}) -> ({}, {})",
+            T::name(),
+            T::State::get_variant(&from),
+            T::Input::get_variant(&input),
+            T::State::get_variant(&to),
+            T::Output::get_variant(&output),
+        );
     }
 
     #[inline]
     pub fn can_step(&mut self) -> bool {
         let enum_input = T::Nothing::enum_id();
-        let enum_state = self.state.as_ref();
+        let enum_state = &self.state;
         let actual_output = self.data.can_transition(enum_state, enum_input);
         actual_output.is_some()
     }
@@ -200,11 +946,11 @@ where
         O: Enumerated<T::Output>,
     {
         let enum_input = T::Nothing::enum_id();
-        let enum_state = self.state.as_ref();
+        let enum_state = &self.state;
         let actual_output = self.data.can_transition(enum_state, enum_input);
         let expected_enum = O::enum_id();
         match actual_output {
-            Some(enum_output) => enum_output == expected_enum,
+            Some((_, enum_output)) => enum_output == expected_enum,
             None => false,
         }
     }
@@ -215,7 +961,7 @@ where
         I: Enumerated<T::Input>,
     {
         let enum_input = I::enum_id();
-        let enum_state = self.state.as_ref();
+        let enum_state = &self.state;
         let actual_output = self.data.can_transition(enum_state, enum_input);
         actual_output.is_some()
     }
@@ -227,15 +973,90 @@ where
         O: Enumerated<T::Output>,
     {
         let enum_input = I::enum_id();
-        let enum_state = self.state.as_ref();
+        let enum_state = &self.state;
         let actual_output = self.data.can_transition(enum_state, enum_input);
         let expected_enum = O::enum_id();
         match actual_output {
-            Some(enum_output) => enum_output == expected_enum,
+            Some((_, enum_output)) => enum_output == expected_enum,
             None => false,
         }
     }
 
+    /// Inputs that can be consumed in the current state, including `Nothing` if the machine can step.
+    pub fn available_inputs(&self) -> Vec<EnumId<T::Input>> {
+        self.available_transitions()
+            .into_iter()
+            .map(|t| t.input)
+            .collect()
+    }
+
+    /// Transitions enabled in the current state, ordered by the input id.
+    pub fn available_transitions(&self) -> Vec<TransitionIds<T>> {
+        let from_state = self.state.enum_id();
+        (0..T::Input::variant_count())
+            .map(EnumId::new)
+            .filter_map(|input| {
+                self.data
+                    .can_transition(&self.state, input)
+                    .map(|(to_state, output)| TransitionIds {
+                        from_state,
+                        input,
+                        to_state,
+                        output,
+                    })
+            })
+            .collect()
+    }
+
+    /// Id of the current state.
+    pub fn state_id(&self) -> StateId<T> {
+        self.state.enum_id()
+    }
+
+    /// Name of the current state, without allocating.
+    pub fn state_name(&self) -> &'static str {
+        T::State::get_variant(&self.state.enum_id())
+    }
+
+    /// Name of the input symbol with the given id.
+    pub fn input_name(id: EnumId<T::Input>) -> &'static str {
+        T::Input::get_variant(&id)
+    }
+
+    /// Name of the output symbol with the given id.
+    pub fn output_name(id: EnumId<T::Output>) -> &'static str {
+        T::Output::get_variant(&id)
+    }
+
+    /// Check whether the current state is `S`, e.g. `sm.is_in::<states::Open>()`.
+    #[inline]
+    pub fn is_in<S: Enumerated<T::State>>(&self) -> bool {
+        self.state.enum_id() == S::enum_id()
+    }
+
+    /// The current state if it is `S`, e.g. `sm.state_as::<states::Open>()`.
+    ///
+    /// Like the generated `maybe_*` getters, but usable in generic code.
+    pub fn state_as<S: 'static>(&self) -> Option<&S> {
+        self.state.as_any().downcast_ref()
+    }
+
+    /// Run `f` on the current state if it is `S`, without a transition.
+    /// Returns `None` if the machine is in another state.
+    ///
+    /// If the machine persists its state on transitions, it is saved afterwards as well.
+    pub fn with_state_mut<S: 'static, R>(&mut self, f: impl FnOnce(&mut S) -> R) -> Option<R> {
+        let result = f(self.state.as_any_mut().downcast_mut()?);
+        if let Some(persist) = self
+            .extras
+            .get_mut()
+            .and_then(|extras| extras.persist.as_mut())
+        {
+            persist(&self.state);
+        }
+        Some(result)
+    }
+
     /// Returns the current state.
     pub fn state(&self) -> &T::State {
         &self.state
@@ -244,4 +1065,14 @@ where
     pub fn data(&self) -> &T {
         &self.data
     }
+
+    /// Consume the machine, returning the data and the current state.
+    pub fn into_parts(self) -> (T, T::State) {
+        (self.data, self.state)
+    }
+
+    /// Consume the machine, returning only the data.
+    pub fn into_inner(self) -> T {
+        self.data
+    }
 }
@@ -0,0 +1,75 @@
+//! Model-based testing: check a real system against the state machine as its model.
+//!
+//! Both are fed the same accepted input sequences (see [`accepted_inputs`])
+//! and their outputs are compared after every input. Failing sequences are shrunk by `proptest`.
+
+use crate::strategy::accepted_inputs;
+use crate::{StateMachine, StateMachineImpl};
+use ::proptest::test_runner::{Config, TestCaseError, TestError, TestRunner};
+use std::fmt::Debug;
+
+/// The real system, driven with the inputs of the model.
+pub trait SystemUnderTest<T: StateMachineImpl> {
+    /// Apply the input and return the observed output, `Nothing` if there is none.
+    fn apply(&mut self, input: &T::Input) -> T::Output;
+}
+
+/// Runs the model and fresh systems under test on generated input sequences.
+pub struct ModelTest<M, S> {
+    new_model: M,
+    new_system: S,
+    max_len: usize,
+    config: Config,
+}
+
+impl<T, Sut, M, S> ModelTest<M, S>
+where
+    T: StateMachineImpl,
+    T::Input: Debug,
+    T::Output: Debug + PartialEq,
+    Sut: SystemUnderTest<T>,
+    M: Fn() -> StateMachine<T> + Clone,
+    S: Fn() -> Sut,
+{
+    /// Test systems built by `new_system` against models built by `new_model`.
+    pub fn new(new_model: M, new_system: S) -> Self {
+        Self {
+            new_model,
+            new_system,
+            max_len: 32,
+            config: Config::default(),
+        }
+    }
+
+    /// Maximum length of the input sequences, 32 by default.
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+
+    /// The `proptest` configuration, e.g. the number of cases.
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Run the test. On failure, returns the minimal failing input sequence.
+    pub fn run(&self) -> Result<(), TestError<Vec<T::Input>>> {
+        let mut runner = TestRunner::new(self.config.clone());
+        let strategy = accepted_inputs(self.new_model.clone(), self.max_len);
+        runner.run(&strategy, |inputs| {
+            let mut model = (self.new_model)();
+            let mut system = (self.new_system)();
+            for (step, input) in inputs.into_iter().enumerate() {
+                let actual = system.apply(&input);
+                let expected = model.fire(input);
+                if actual != expected {
+                    return Err(TestCaseError::fail(format!(
+                        "output {actual:?} differs from the model output {expected:?} at step {step}"
+                    )));
+                }
+            }
+            Ok(())
+        })
+    }
+}
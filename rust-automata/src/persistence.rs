@@ -0,0 +1,209 @@
+//! Save the state of a machine after every transition, and restore it later.
+//!
+//! The state is stored as a JSON snapshot, so the state enum must implement
+//! `serde::Serialize` and `serde::Deserialize` (add them to the `derive(...)` section).
+//! The machine data is not part of the snapshot, it is supplied again on restore.
+//!
+//! Snapshots record the [schema hash][crate::graph::MachineGraph::schema] of the machine.
+//! A snapshot taken by a different definition of the machine is only restored through
+//! a migration, see [`StateMachine::restore_with`].
+
+use crate::{StateMachine, StateMachineImpl};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::borrow::Cow;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Storage for snapshots, addressed by a key.
+pub trait Persistence {
+    /// Store the snapshot under the key, replacing the previous one.
+    fn save(&mut self, key: &str, snapshot: &[u8]) -> io::Result<()>;
+    /// Read the snapshot stored under the key, or `None` if there is none.
+    fn load(&self, key: &str) -> io::Result<Option<Vec<u8>>>;
+}
+
+/// Stores each snapshot in a file `<key>.json` in a directory.
+///
+/// Keys that are empty, or contain a path separator or `..`, are rejected with
+/// [`io::ErrorKind::InvalidInput`], so that a key never escapes the directory.
+#[derive(Clone, Debug)]
+pub struct FileBackend {
+    dir: PathBuf,
+}
+
+impl FileBackend {
+    /// Store the snapshots in `dir`, creating it if needed.
+    pub fn new(dir: impl AsRef<Path>) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir: dir.as_ref().to_path_buf(),
+        })
+    }
+
+    fn path(&self, key: &str) -> io::Result<PathBuf> {
+        if key.is_empty() || key.contains(['/', '\\', '\0']) || key.contains("..") {
+            let message = format!("invalid snapshot key {key:?}");
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, message));
+        }
+        Ok(self.dir.join(format!("{key}.json")))
+    }
+}
+
+impl Persistence for FileBackend {
+    fn save(&mut self, key: &str, snapshot: &[u8]) -> io::Result<()> {
+        // Write and sync a temporary file first, so a crash never leaves a partial snapshot.
+        let path = self.path(key)?;
+        let tmp = path.with_extension("json.tmp");
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(snapshot)?;
+        file.sync_all()?;
+        fs::rename(tmp, path)?;
+        // Sync the directory so the rename survives a crash. Not every platform can open a
+        // directory as a file, where this is skipped.
+        if let Ok(dir) = fs::File::open(&self.dir) {
+            dir.sync_all()?;
+        }
+        Ok(())
+    }
+
+    fn load(&self, key: &str) -> io::Result<Option<Vec<u8>>> {
+        match fs::read(self.path(key)?) {
+            Ok(snapshot) => Ok(Some(snapshot)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// A state as stored in a snapshot, passed to migrations.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StoredState {
+    /// Name of the state struct.
+    pub name: String,
+    /// The serialized state struct.
+    pub payload: Value,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Snapshot<S> {
+    machine: Cow<'static, str>,
+    // Snapshots without a schema never match the current one.
+    #[serde(default)]
+    schema: u64,
+    state: S,
+}
+
+fn encode<T>(state: &T::State) -> io::Result<Vec<u8>>
+where
+    T: StateMachineImpl,
+    T::State: Serialize,
+{
+    let snapshot = Snapshot {
+        machine: Cow::Borrowed(T::name()),
+        schema: T::graph().schema,
+        state,
+    };
+    Ok(serde_json::to_vec(&snapshot)?)
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+impl<T> StateMachine<T>
+where
+    T: StateMachineImpl,
+{
+    /// Serialize the current state.
+    pub fn snapshot(&self) -> io::Result<Vec<u8>>
+    where
+        T::State: Serialize,
+    {
+        encode::<T>(self.state())
+    }
+
+    /// Rebuild a machine from its data and a snapshot of its state.
+    ///
+    /// Fails if the snapshot was taken by a different definition of the machine.
+    pub fn restore(data: T, snapshot: &[u8]) -> io::Result<Self>
+    where
+        T::State: DeserializeOwned,
+    {
+        Self::restore_with(data, snapshot, |_| {
+            Err(invalid_data(format!(
+                "incompatible snapshot of {}",
+                T::name()
+            )))
+        })
+    }
+
+    /// Like [`restore`][Self::restore], but a snapshot taken by a different definition
+    /// of the machine is first passed to `migrate`, which maps the old state to a new one.
+    pub fn restore_with(
+        data: T,
+        snapshot: &[u8],
+        migrate: impl FnOnce(StoredState) -> io::Result<StoredState>,
+    ) -> io::Result<Self>
+    where
+        T::State: DeserializeOwned,
+    {
+        let snapshot: Snapshot<Value> = serde_json::from_slice(snapshot)?;
+        if snapshot.machine != T::name() {
+            return Err(invalid_data(format!(
+                "snapshot of {} cannot restore {}",
+                snapshot.machine,
+                T::name()
+            )));
+        }
+        let mut state = snapshot.state;
+        if snapshot.schema != T::graph().schema {
+            // The state enum is stored as `{"Name": payload}`.
+            let Value::Object(map) = state else {
+                return Err(invalid_data(format!("malformed state {state}")));
+            };
+            let Some((name, payload)) = map.into_iter().next() else {
+                return Err(invalid_data("missing state".to_string()));
+            };
+            let migrated = migrate(StoredState { name, payload })?;
+            state = Value::Object(Map::from_iter([(migrated.name, migrated.payload)]));
+        }
+        Ok(Self::from_parts(data, serde_json::from_value(state)?))
+    }
+
+    /// Restore the machine stored under `key`, or `None` if there is no snapshot.
+    pub fn load(data: T, backend: &impl Persistence, key: &str) -> io::Result<Option<Self>>
+    where
+        T::State: DeserializeOwned,
+    {
+        backend
+            .load(key)?
+            .map(|snapshot| Self::restore(data, &snapshot))
+            .transpose()
+    }
+
+    /// Save the current state under `key` now and after every transition.
+    ///
+    /// Replaces the previously set backend. Failures to save after a transition are logged.
+    pub fn persist_on_transition<P>(
+        &mut self,
+        mut backend: P,
+        key: impl Into<String>,
+    ) -> io::Result<()>
+    where
+        T::State: Serialize,
+        P: Persistence + Send + 'static,
+    {
+        let key = key.into();
+        backend.save(&key, &self.snapshot()?)?;
+        self.extras_mut().persist = Some(Box::new(move |state| {
+            let result = encode::<T>(state).and_then(|snapshot| backend.save(&key, &snapshot));
+            if let Err(e) = result {
+                log::error!("{}: failed to persist {key}: {e}", T::name());
+            }
+        }));
+        Ok(())
+    }
+}
@@ -0,0 +1,110 @@
+//! Connect the outputs of one machine to the inputs of another.
+//!
+//! A symbol struct can be an output of the producer and an input of the consumer at the same time,
+//! like `events::Take` in the vikings example. The pipeline forwards the symbols registered with
+//! [`Pipeline::forward`], whichever of them the producer emits.
+
+use crate::{EnumId, Enumerable, Enumerated, RejectedInput, StateMachine, StateMachineImpl};
+
+/// An output of the producer that is forwarded, see [`Pipeline::forward`].
+struct Forward<P: StateMachineImpl, C: StateMachineImpl> {
+    output: EnumId<P::Output>,
+    input: EnumId<C::Input>,
+    convert: fn(P::Output) -> C::Input,
+}
+
+/// Forward outputs of the producer as inputs of the consumer.
+///
+/// Only the outputs registered with [`forward`][Self::forward] reach the consumer. The other
+/// outputs go to the producer's [output sink][StateMachine::set_output_sink], `Nothing` is dropped.
+pub struct Pipeline<'a, P: StateMachineImpl, C: StateMachineImpl> {
+    producer: &'a mut StateMachine<P>,
+    consumer: &'a mut StateMachine<C>,
+    forwards: Vec<Forward<P, C>>,
+}
+
+/// Connect two machines, see [`Pipeline`].
+pub fn connect<'a, P, C>(
+    producer: &'a mut StateMachine<P>,
+    consumer: &'a mut StateMachine<C>,
+) -> Pipeline<'a, P, C>
+where
+    P: StateMachineImpl,
+    C: StateMachineImpl,
+{
+    Pipeline {
+        producer,
+        consumer,
+        forwards: Vec::new(),
+    }
+}
+
+impl<P, C> Pipeline<'_, P, C>
+where
+    P: StateMachineImpl,
+    C: StateMachineImpl,
+{
+    /// Forward every `S` the producer emits to the consumer.
+    pub fn forward<S>(mut self) -> Self
+    where
+        S: From<P::Output> + Enumerated<P::Output> + Into<C::Input> + Enumerated<C::Input>,
+    {
+        self.forwards.push(Forward {
+            output: <S as Enumerated<P::Output>>::enum_id(),
+            input: <S as Enumerated<C::Input>>::enum_id(),
+            convert: |output| S::from(output).into(),
+        });
+        self
+    }
+
+    /// Check that the producer accepts `I` and the consumer accepts what is forwarded.
+    pub fn can_relay<I: Enumerated<P::Input>>(&self) -> bool {
+        self.check(I::enum_id()).is_ok()
+    }
+
+    /// Like [`can_relay`][Self::can_relay], for a spontaneous transition of the producer.
+    pub fn can_step(&self) -> bool {
+        self.can_relay::<P::Nothing>()
+    }
+
+    /// Take a spontaneous transition of the producer and forward its output, see
+    /// [`relay`][Self::relay].
+    pub fn step(&mut self) -> Result<(), RejectedInput> {
+        self.relay(P::Nothing::default())
+    }
+
+    /// Relay an input through the producer and forward its output to the consumer.
+    ///
+    /// Both machines are checked first: if either would reject its input, neither transitions.
+    pub fn relay<I: Into<P::Input>>(&mut self, input: I) -> Result<(), RejectedInput> {
+        let input = input.into();
+        let forward = self.check(input.enum_id())?.map(|f| f.convert);
+        let output = self.producer.fire(input);
+        match forward {
+            Some(convert) => {
+                let output = self.consumer.fire(convert(output));
+                self.consumer.deliver(output);
+            }
+            None => self.producer.deliver(output),
+        }
+        Ok(())
+    }
+
+    /// The forward the input leads to, if any, or the input rejected by one of the machines.
+    fn check(&self, input: EnumId<P::Input>) -> Result<Option<&Forward<P, C>>, RejectedInput> {
+        let output = self.producer.check_input(input)?;
+        let Some(forward) = self.forwards.iter().find(|f| f.output == output) else {
+            return Ok(None);
+        };
+        self.consumer.check_input(forward.input)?;
+        Ok(Some(forward))
+    }
+
+    pub fn producer(&self) -> &StateMachine<P> {
+        self.producer
+    }
+
+    pub fn consumer(&self) -> &StateMachine<C> {
+        self.consumer
+    }
+}
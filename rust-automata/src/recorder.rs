@@ -0,0 +1,134 @@
+//! Record a run of a machine and reconstruct it as of any step, or render it as a
+//! [sequence diagram][sequence_diagram].
+//!
+//! With a [`ManualClock`][crate::clock::ManualClock] in the data, timers behave the same
+//! when the run is replayed, as long as the clock is not advanced in between.
+
+use crate::{Enumerable, RejectedInput, StateMachine, StateMachineImpl, TransitionIds};
+use std::fmt::Write;
+
+/// Wraps a machine and records every transition, with a copy of the state and data
+/// every `interval` steps.
+pub struct Recorder<T: StateMachineImpl> {
+    machine: StateMachine<T>,
+    interval: usize,
+    inputs: Vec<T::Input>,
+    transitions: Vec<TransitionIds<T>>,
+    // Step, state and data, oldest first. Step 0 is always present.
+    snapshots: Vec<(usize, T::State, T)>,
+}
+
+impl<T> Recorder<T>
+where
+    T: StateMachineImpl + Clone,
+    T::State: Clone,
+    T::Input: Clone,
+{
+    /// Start recording at the current state of the machine. `interval` of 0 is treated as 1.
+    pub fn new(machine: StateMachine<T>, interval: usize) -> Self {
+        let snapshot = (0, machine.state().clone(), machine.data().clone());
+        Self {
+            machine,
+            interval: interval.max(1),
+            inputs: Vec::new(),
+            transitions: Vec::new(),
+            snapshots: vec![snapshot],
+        }
+    }
+
+    /// Run the transition and record it. Rejected inputs are not recorded.
+    pub fn fire(&mut self, input: impl Into<T::Input>) -> Result<T::Output, RejectedInput> {
+        let input = input.into();
+        let from_state = self.machine.state_id();
+        let input_id = input.enum_id();
+        let output = self.machine.try_fire(input.clone())?;
+        self.inputs.push(input);
+        self.transitions.push(TransitionIds {
+            from_state,
+            input: input_id,
+            to_state: self.machine.state_id(),
+            output: output.enum_id(),
+        });
+        if self.len() % self.interval == 0 {
+            self.snapshots.push((
+                self.len(),
+                self.machine.state().clone(),
+                self.machine.data().clone(),
+            ));
+        }
+        Ok(output)
+    }
+
+    /// Number of recorded steps.
+    pub fn len(&self) -> usize {
+        self.inputs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inputs.is_empty()
+    }
+
+    /// The recorded transitions, in order.
+    pub fn transitions(&self) -> &[TransitionIds<T>] {
+        &self.transitions
+    }
+
+    /// The recorded run as a Mermaid sequence diagram, see [`sequence_diagram`].
+    pub fn sequence_diagram(&self) -> String {
+        sequence_diagram(&self.transitions)
+    }
+
+    /// The machine as of now.
+    pub fn machine(&self) -> &StateMachine<T> {
+        &self.machine
+    }
+
+    /// Stop recording, returning the machine.
+    pub fn into_machine(self) -> StateMachine<T> {
+        self.machine
+    }
+
+    /// Reconstruct the machine as it was after `step` steps, e.g. `seek(0)` before the first one.
+    ///
+    /// Starts from the nearest earlier snapshot and replays the recorded inputs.
+    /// Returns `None` if fewer steps were recorded.
+    pub fn seek(&self, step: usize) -> Option<StateMachine<T>> {
+        if step > self.len() {
+            return None;
+        }
+        let nearest = self.snapshots.partition_point(|(s, _, _)| *s <= step) - 1;
+        let (start, state, data) = &self.snapshots[nearest];
+        let mut machine = StateMachine::from_parts(data.clone(), state.clone());
+        for input in &self.inputs[*start..step] {
+            machine.fire(input.clone());
+        }
+        Some(machine)
+    }
+}
+
+/// Render a run as a Mermaid `sequenceDiagram`, e.g. for an incident report.
+///
+/// The inputs go from the environment to the machine, the outputs back, and every
+/// change of state is a note over the machine. `Nothing` inputs and outputs are left out.
+pub fn sequence_diagram<T: StateMachineImpl>(transitions: &[TransitionIds<T>]) -> String {
+    let machine = T::name();
+    let mut md = String::new();
+    writeln!(md, "sequenceDiagram").unwrap();
+    writeln!(md, "    participant Environment").unwrap();
+    writeln!(md, "    participant {machine}").unwrap();
+    if let Some(first) = transitions.first() {
+        writeln!(md, "    Note over {machine}: {}", first.from_state).unwrap();
+    }
+    for tr in transitions {
+        if tr.input.id != 0 {
+            writeln!(md, "    Environment->>{machine}: {}", tr.input).unwrap();
+        }
+        if tr.output.id != 0 {
+            writeln!(md, "    {machine}-->>Environment: {}", tr.output).unwrap();
+        }
+        if tr.to_state != tr.from_state {
+            writeln!(md, "    Note over {machine}: {}", tr.to_state).unwrap();
+        }
+    }
+    md
+}
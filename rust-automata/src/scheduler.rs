@@ -0,0 +1,126 @@
+//! Simulate a network of timed machines in a reproducible way.
+
+use crate::bus::{Bus, Handle};
+use crate::clock::{Clock, ManualClock};
+use crate::timestamp::Timestamp;
+use crate::{Enumerable, RejectedInput, Timers};
+
+// Step the machine if a spontaneous transition is enabled. Returns whether the state changed.
+type TryStep = Box<dyn Fn(&mut Bus) -> Result<bool, RejectedInput>>;
+// The earliest deadline of the machine's timers after the given time.
+type NextDeadline = Box<dyn Fn(&Bus, Timestamp) -> Option<Timestamp>>;
+
+struct Task {
+    try_step: TryStep,
+    next_deadline: NextDeadline,
+}
+
+/// Owns a [`Bus`] of machines and a [`ManualClock`], and drives the scheduled machines.
+///
+/// Machines are stepped in the order they were [scheduled][Self::schedule], so runs are reproducible.
+pub struct Scheduler {
+    clock: ManualClock,
+    bus: Bus,
+    tasks: Vec<Task>,
+}
+
+impl Scheduler {
+    pub fn new(clock: ManualClock) -> Self {
+        Self {
+            clock,
+            bus: Bus::new(),
+            tasks: Vec::new(),
+        }
+    }
+
+    pub fn clock(&self) -> &ManualClock {
+        &self.clock
+    }
+
+    /// The bus to register machines on and to wire their symbols.
+    pub fn bus(&mut self) -> &mut Bus {
+        &mut self.bus
+    }
+
+    /// Let the scheduler take the spontaneous transitions of the machine and watch its timers.
+    pub fn schedule<T>(&mut self, handle: Handle<T>)
+    where
+        T: Timers + 'static,
+        T::Output: 'static,
+    {
+        self.tasks.push(Task {
+            try_step: Box::new(move |bus| {
+                let machine = bus.machine_mut(handle);
+                if !machine.can_step() {
+                    return Ok(false);
+                }
+                let from_id = machine.state().enum_id();
+                bus.step(handle)?;
+                Ok(bus.machine(handle).state().enum_id() != from_id)
+            }),
+            next_deadline: Box::new(move |bus, now| {
+                let machine = bus.machine(handle);
+                let mut next: Option<Timestamp> = None;
+                machine.data().visit_timers(machine.state(), &mut |timer| {
+                    // The deadline of a paused timer moves with the clock, it is never reached.
+                    let deadline = timer.deadline();
+                    if deadline > now && !timer.is_paused() {
+                        next = Some(next.map_or(deadline, |next| next.min(deadline)));
+                    }
+                });
+                next
+            }),
+        });
+    }
+
+    /// Take enabled spontaneous transitions in rounds over the scheduled machines,
+    /// until a round changes no state. Returns the number of state changes.
+    ///
+    /// Machines that cycle through states spontaneously (like a flip-flop) never settle.
+    pub fn run_enabled(&mut self) -> Result<usize, RejectedInput> {
+        let mut changes = 0;
+        loop {
+            let mut changed = false;
+            for task in &self.tasks {
+                if (task.try_step)(&mut self.bus)? {
+                    changes += 1;
+                    changed = true;
+                }
+            }
+            if !changed {
+                return Ok(changes);
+            }
+        }
+    }
+
+    /// The earliest timer deadline after the current time, across the scheduled machines.
+    pub fn next_deadline(&self) -> Option<Timestamp> {
+        let now = self.clock.now();
+        self.tasks
+            .iter()
+            .filter_map(|task| (task.next_deadline)(&self.bus, now))
+            .min()
+    }
+
+    /// Advance the clock to the next deadline and run the enabled transitions.
+    ///
+    /// Returns the new time, or `None` if no timer is pending.
+    pub fn advance(&mut self) -> Result<Option<Timestamp>, RejectedInput> {
+        let Some(deadline) = self.next_deadline() else {
+            return Ok(None);
+        };
+        self.clock.advance_to(deadline);
+        self.run_enabled()?;
+        Ok(Some(deadline))
+    }
+}
+
+impl std::fmt::Debug for Scheduler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Scheduler")
+            .field("now", &self.clock.now())
+            .field("bus", &self.bus)
+            .field("tasks", &self.tasks.len())
+            .finish()
+    }
+}
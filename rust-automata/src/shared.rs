@@ -0,0 +1,116 @@
+//! Drive one machine from multiple threads.
+
+use crate::{Enumerated, StateMachine, StateMachineImpl};
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// The machine panicked while it was locked, e.g. on an invalid transition.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PoisonedLock;
+
+impl fmt::Display for PoisonedLock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "state machine lock poisoned")
+    }
+}
+
+impl std::error::Error for PoisonedLock {}
+
+/// A [`StateMachine`] behind an `Arc<Mutex<..>>`. Clones share the same machine.
+///
+/// Every call locks the machine for its duration only, so a `can_*` check followed by
+/// a transition is not atomic. Use [`with`][Self::with] to run both under one lock.
+pub struct SharedStateMachine<T: StateMachineImpl> {
+    inner: Arc<Mutex<StateMachine<T>>>,
+}
+
+impl<T: StateMachineImpl> Clone for SharedStateMachine<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<T: StateMachineImpl> From<StateMachine<T>> for SharedStateMachine<T> {
+    fn from(machine: StateMachine<T>) -> Self {
+        Self::new(machine)
+    }
+}
+
+impl<T: StateMachineImpl> SharedStateMachine<T> {
+    pub fn new(machine: StateMachine<T>) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(machine)),
+        }
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, StateMachine<T>>, PoisonedLock> {
+        self.inner.lock().map_err(|_| PoisonedLock)
+    }
+
+    /// Run `f` with the machine locked.
+    pub fn with<R>(&self, f: impl FnOnce(&mut StateMachine<T>) -> R) -> Result<R, PoisonedLock> {
+        Ok(f(&mut *self.lock()?))
+    }
+
+    /// See [`StateMachine::step`].
+    pub fn step(&self) -> Result<(), PoisonedLock> {
+        self.with(|sm| sm.step())
+    }
+
+    /// See [`StateMachine::produce`].
+    pub fn produce<O: From<T::Output> + Enumerated<T::Output>>(&self) -> Result<O, PoisonedLock> {
+        self.with(|sm| sm.produce())
+    }
+
+    /// See [`StateMachine::consume`].
+    pub fn consume<I: Into<T::Input> + Enumerated<T::Input>>(
+        &self,
+        input: I,
+    ) -> Result<(), PoisonedLock> {
+        self.with(|sm| sm.consume(input))
+    }
+
+    /// See [`StateMachine::relay`].
+    pub fn relay<I: Into<T::Input> + Enumerated<T::Input>, O: From<T::Output>>(
+        &self,
+        input: I,
+    ) -> Result<O, PoisonedLock> {
+        self.with(|sm| sm.relay(input))
+    }
+
+    pub fn can_step(&self) -> Result<bool, PoisonedLock> {
+        self.with(|sm| sm.can_step())
+    }
+
+    pub fn can_produce<O: Enumerated<T::Output>>(&self) -> Result<bool, PoisonedLock> {
+        self.with(|sm| sm.can_produce::<O>())
+    }
+
+    pub fn can_consume<I: Enumerated<T::Input>>(&self) -> Result<bool, PoisonedLock> {
+        self.with(|sm| sm.can_consume::<I>())
+    }
+
+    pub fn can_relay<I: Enumerated<T::Input>, O: Enumerated<T::Output>>(
+        &self,
+    ) -> Result<bool, PoisonedLock> {
+        self.with(|sm| sm.can_relay::<I, O>())
+    }
+
+    /// Name of the current state.
+    pub fn state_name(&self) -> Result<&'static str, PoisonedLock> {
+        self.with(|sm| sm.state_name())
+    }
+}
+
+impl<T: StateMachineImpl> fmt::Debug for SharedStateMachine<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut s = f.debug_struct("SharedStateMachine");
+        match self.inner.try_lock() {
+            Ok(sm) => s.field("state", &sm.state_name()),
+            Err(_) => s.field("state", &format_args!("<locked>")),
+        };
+        s.finish()
+    }
+}
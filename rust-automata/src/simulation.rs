@@ -0,0 +1,40 @@
+//! Drive a machine with random inputs, e.g. to smoke-test handlers for panics.
+
+use crate::{Enumerable, StateMachine, StateMachineImpl, TransitionIds};
+use rand::Rng;
+
+impl<T: StateMachineImpl> StateMachine<T> {
+    /// Take up to `steps` transitions, each time consuming a random enabled input.
+    /// Returns the trace of the transitions taken.
+    ///
+    /// Inputs are built with `Default`, so inputs that do not implement it are never picked.
+    /// Stops early if no such input is enabled.
+    pub fn simulate_random<R: Rng + ?Sized>(
+        &mut self,
+        rng: &mut R,
+        steps: usize,
+    ) -> Vec<TransitionIds<T>> {
+        let mut trace = Vec::with_capacity(steps);
+        for _ in 0..steps {
+            let mut candidates: Vec<T::Input> = self
+                .available_inputs()
+                .iter()
+                .filter_map(T::Input::default_of)
+                .collect();
+            if candidates.is_empty() {
+                break;
+            }
+            let input = candidates.swap_remove(rng.random_range(0..candidates.len()));
+            let from_state = self.state.enum_id();
+            let input_id = input.enum_id();
+            let output = self.fire(input);
+            trace.push(TransitionIds {
+                from_state,
+                input: input_id,
+                to_state: self.state.enum_id(),
+                output: output.enum_id(),
+            });
+        }
+        trace
+    }
+}
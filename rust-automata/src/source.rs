@@ -0,0 +1,58 @@
+//! Pull inputs from a source instead of pushing them into the machine.
+//!
+//! Suits polling designs, where the machine drains e.g. a ring buffer on every tick.
+
+use crate::{RejectedInput, StateMachine, StateMachineImpl};
+use std::collections::VecDeque;
+use std::sync::mpsc;
+
+/// Something the machine can pull inputs from, see [`StateMachine::pump`].
+pub trait InputSource<I> {
+    /// The next input, or `None` if there is none available right now.
+    fn next_input(&mut self) -> Option<I>;
+}
+
+impl<I> InputSource<I> for VecDeque<I> {
+    fn next_input(&mut self) -> Option<I> {
+        self.pop_front()
+    }
+}
+
+/// Takes the inputs already sent, does not wait for more.
+impl<I> InputSource<I> for mpsc::Receiver<I> {
+    fn next_input(&mut self) -> Option<I> {
+        self.try_recv().ok()
+    }
+}
+
+impl<I, F: FnMut() -> Option<I>> InputSource<I> for F {
+    fn next_input(&mut self) -> Option<I> {
+        self()
+    }
+}
+
+impl<T: StateMachineImpl> StateMachine<T> {
+    /// Consume inputs from `source` until it is empty. Returns the number of consumed inputs.
+    ///
+    /// The outputs go to the [output sink][Self::set_output_sink], if there is one.
+    ///
+    /// Stops at the first input without an enabled transition, which is then dropped.
+    /// Its position in the error is the number of inputs consumed before it.
+    pub fn pump<I: Into<T::Input>>(
+        &mut self,
+        source: &mut impl InputSource<I>,
+    ) -> Result<usize, RejectedInput> {
+        let mut consumed = 0;
+        while let Some(input) = source.next_input() {
+            let output = self
+                .try_fire(input.into())
+                .map_err(|rejected| RejectedInput {
+                    index: consumed,
+                    ..rejected
+                })?;
+            self.deliver(output);
+            consumed += 1;
+        }
+        Ok(consumed)
+    }
+}
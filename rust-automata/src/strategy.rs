@@ -0,0 +1,58 @@
+//! `proptest` strategies for the generated alphabets.
+//!
+//! Inputs are built with `Default`, so inputs that do not implement it are never generated.
+
+use crate::{EnumId, Enumerable, StateMachine, StateMachineImpl};
+use ::proptest::prelude::*;
+use std::fmt::Debug;
+
+/// Ids of the inputs that can be built with `Default`, excluding `Nothing`.
+fn default_input_ids<T: StateMachineImpl>() -> Vec<usize> {
+    (1..T::Input::variant_count())
+        .filter(|&id| T::Input::default_of(&EnumId::new(id)).is_some())
+        .collect()
+}
+
+/// Any input symbol of the machine.
+///
+/// # Panics
+///
+/// If no input implements `Default`.
+pub fn any_input<T>() -> impl Strategy<Value = T::Input>
+where
+    T: StateMachineImpl,
+    T::Input: Debug,
+{
+    prop::sample::select(default_input_ids::<T>())
+        .prop_map(|id| T::Input::default_of(&EnumId::new(id)).unwrap())
+}
+
+/// Sequences of up to `max_len` inputs that are accepted by the machine built by `new_machine`.
+///
+/// At each step, one of the enabled inputs is picked, so the sequence stops early
+/// if the machine has no enabled input left. Shrinking yields shorter sequences.
+pub fn accepted_inputs<T, F>(new_machine: F, max_len: usize) -> impl Strategy<Value = Vec<T::Input>>
+where
+    T: StateMachineImpl,
+    T::Input: Debug,
+    F: Fn() -> StateMachine<T>,
+{
+    prop::collection::vec(any::<usize>(), 0..=max_len).prop_map(move |choices| {
+        let mut machine = new_machine();
+        let mut inputs = Vec::with_capacity(choices.len());
+        for choice in choices {
+            let candidates: Vec<_> = machine
+                .available_inputs()
+                .into_iter()
+                .filter(|id| id.id != 0 && T::Input::default_of(id).is_some())
+                .collect();
+            if candidates.is_empty() {
+                break;
+            }
+            let id = candidates[choice % candidates.len()];
+            machine.fire(T::Input::default_of(&id).unwrap());
+            inputs.push(T::Input::default_of(&id).unwrap());
+        }
+        inputs
+    })
+}
@@ -0,0 +1,103 @@
+//! Adapt a state machine to the `futures` `Sink` and `Stream` traits.
+
+use crate::{Alphabet, RejectedInput, StateMachine, StateMachineImpl};
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use futures_core::Stream;
+use futures_sink::Sink;
+use std::collections::VecDeque;
+
+/// A state machine that consumes inputs as a [`Sink`] and yields outputs as a [`Stream`].
+///
+/// `Nothing` outputs are not yielded. The stream ends once the sink is closed
+/// and all buffered outputs were yielded.
+pub struct StateMachineStream<T: StateMachineImpl> {
+    machine: StateMachine<T>,
+    outputs: VecDeque<T::Output>,
+    closed: bool,
+    waker: Option<Waker>,
+}
+
+// No field is structurally pinned.
+impl<T: StateMachineImpl> Unpin for StateMachineStream<T> {}
+
+impl<T: StateMachineImpl> StateMachineStream<T> {
+    pub fn new(machine: StateMachine<T>) -> Self {
+        Self {
+            machine,
+            outputs: VecDeque::new(),
+            closed: false,
+            waker: None,
+        }
+    }
+
+    pub fn machine(&self) -> &StateMachine<T> {
+        &self.machine
+    }
+
+    pub fn into_machine(self) -> StateMachine<T> {
+        self.machine
+    }
+
+    fn wake(&mut self) {
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl<T, I> Sink<I> for StateMachineStream<T>
+where
+    T: StateMachineImpl,
+    I: Into<T::Input>,
+{
+    type Error = RejectedInput;
+
+    fn poll_ready(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // The machine processes inputs synchronously, it is always ready.
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: I) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+        let output = this.machine.try_fire(item.into())?;
+        if output.any() {
+            this.outputs.push_back(output);
+            this.wake();
+        }
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        this.closed = true;
+        this.wake();
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T: StateMachineImpl> Stream for StateMachineStream<T> {
+    type Item = T::Output;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        match this.outputs.pop_front() {
+            Some(output) => Poll::Ready(Some(output)),
+            None if this.closed => Poll::Ready(None),
+            None => {
+                this.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<T: StateMachineImpl> From<StateMachine<T>> for StateMachineStream<T> {
+    fn from(machine: StateMachine<T>) -> Self {
+        Self::new(machine)
+    }
+}
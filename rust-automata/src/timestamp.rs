@@ -1,17 +1,20 @@
 //! Provide timestamp and timestamp delta types.
 //!
 //! Useful for internal representation of time, and exposes methods for conversion to and from `DateTime`.
+use crate::clock::{Clock, SystemClock};
+#[cfg(feature = "chrono")]
 use chrono::{DateTime, Local, TimeDelta, TimeZone, Utc};
 use core::fmt;
 use std::fmt::Display;
-use std::num::ParseIntError;
-use std::ops::{Add, Sub};
+use std::num::TryFromIntError;
+use std::ops::{Add, Div, Mul, Neg, Sub};
 use std::str::FromStr;
+use std::time::Duration;
 
 /// A timestamp in nanoseconds in the UTC timezone.
 ///
 /// Use this type for internal timestamps and for nice date formatting
-/// use [`DateTime<Local>`].
+/// use `DateTime<Local>` (with the `chrono` feature).
 ///
 /// The dates that can be represented as nanoseconds are between
 /// 1677-09-21T00:12:43.145224192 and 2262-04-11T23:47:16.854775807.
@@ -50,6 +53,14 @@ impl Timestamp {
     pub const fn zero() -> Self {
         Self(0)
     }
+    /// The current system time, see [`SystemClock`].
+    pub fn now() -> Self {
+        SystemClock.now()
+    }
+    /// The current time of the clock.
+    pub fn now_with(clock: &dyn Clock) -> Self {
+        clock.now()
+    }
     pub const fn as_secs(&self) -> i64 {
         self.0 / 1_000_000_000
     }
@@ -62,32 +73,90 @@ impl Timestamp {
     pub const fn as_nanos(&self) -> i64 {
         self.0
     }
+    #[cfg(feature = "chrono")]
     pub fn local(&self) -> DateTime<Local> {
         DateTime::<Local>::from(*self)
     }
+    #[cfg(feature = "chrono")]
     pub fn utc(&self) -> DateTime<Utc> {
         DateTime::<Utc>::from(*self)
     }
 
-    // TODO: bounds check
+    // The `from_*` constructors panic if the value does not fit, see the `try_from_*` variants.
     pub const fn from_hours(hours: i64) -> Self {
-        Self(hours * 60 * 60 * 1_000_000_000)
+        Self(expect_in_range(scale(hours, 60 * 60 * 1_000_000_000)))
     }
     pub const fn from_minutes(minutes: i64) -> Self {
-        Self(minutes * 60 * 1_000_000_000)
+        Self(expect_in_range(scale(minutes, 60 * 1_000_000_000)))
     }
     pub const fn from_secs(secs: i64) -> Self {
-        Self(secs * 1_000_000_000)
+        Self(expect_in_range(scale(secs, 1_000_000_000)))
     }
     pub const fn from_millis(millis: i64) -> Self {
-        Self(millis * 1_000_000)
+        Self(expect_in_range(scale(millis, 1_000_000)))
     }
     pub const fn from_micros(micros: i64) -> Self {
-        Self(micros * 1_000)
+        Self(expect_in_range(scale(micros, 1_000)))
+    }
+    pub const fn try_from_hours(hours: i64) -> Result<Self, OutOfRangeError> {
+        match scale(hours, 60 * 60 * 1_000_000_000) {
+            Ok(nanos) => Ok(Self(nanos)),
+            Err(e) => Err(e),
+        }
+    }
+    pub const fn try_from_minutes(minutes: i64) -> Result<Self, OutOfRangeError> {
+        match scale(minutes, 60 * 1_000_000_000) {
+            Ok(nanos) => Ok(Self(nanos)),
+            Err(e) => Err(e),
+        }
+    }
+    pub const fn try_from_secs(secs: i64) -> Result<Self, OutOfRangeError> {
+        match scale(secs, 1_000_000_000) {
+            Ok(nanos) => Ok(Self(nanos)),
+            Err(e) => Err(e),
+        }
+    }
+    pub const fn try_from_millis(millis: i64) -> Result<Self, OutOfRangeError> {
+        match scale(millis, 1_000_000) {
+            Ok(nanos) => Ok(Self(nanos)),
+            Err(e) => Err(e),
+        }
+    }
+    pub const fn try_from_micros(micros: i64) -> Result<Self, OutOfRangeError> {
+        match scale(micros, 1_000) {
+            Ok(nanos) => Ok(Self(nanos)),
+            Err(e) => Err(e),
+        }
     }
     pub const fn from_nanos(nanos: i64) -> Self {
         Self(nanos)
     }
+
+    /// `self + delta`, or `None` if the result is out of the representable range.
+    pub const fn checked_add(self, delta: TimestampDelta) -> Option<Self> {
+        match self.0.checked_add(delta.0) {
+            Some(nanos) => Some(Self(nanos)),
+            None => None,
+        }
+    }
+
+    /// `self - delta`, or `None` if the result is out of the representable range.
+    pub const fn checked_sub(self, delta: TimestampDelta) -> Option<Self> {
+        match self.0.checked_sub(delta.0) {
+            Some(nanos) => Some(Self(nanos)),
+            None => None,
+        }
+    }
+
+    /// `self + delta`, clamped to the representable range.
+    pub const fn saturating_add(self, delta: TimestampDelta) -> Self {
+        Self(self.0.saturating_add(delta.0))
+    }
+
+    /// `self - delta`, clamped to the representable range.
+    pub const fn saturating_sub(self, delta: TimestampDelta) -> Self {
+        Self(self.0.saturating_sub(delta.0))
+    }
 }
 
 impl TimestampDelta {
@@ -107,39 +176,174 @@ impl TimestampDelta {
         self.0
     }
 
-    // TODO: bounds check
+    // The `from_*` constructors panic if the value does not fit, see the `try_from_*` variants.
     pub const fn from_hours(hours: i64) -> Self {
-        Self(hours * 60 * 60 * 1_000_000_000)
+        Self(expect_in_range(scale(hours, 60 * 60 * 1_000_000_000)))
     }
     pub const fn from_minutes(minutes: i64) -> Self {
-        Self(minutes * 60 * 1_000_000_000)
+        Self(expect_in_range(scale(minutes, 60 * 1_000_000_000)))
     }
     pub const fn from_secs(secs: i64) -> Self {
-        Self(secs * 1_000_000_000)
+        Self(expect_in_range(scale(secs, 1_000_000_000)))
     }
     pub const fn from_millis(millis: i64) -> Self {
-        Self(millis * 1_000_000)
+        Self(expect_in_range(scale(millis, 1_000_000)))
     }
     pub const fn from_micros(micros: i64) -> Self {
-        Self(micros * 1_000)
+        Self(expect_in_range(scale(micros, 1_000)))
+    }
+    pub const fn try_from_hours(hours: i64) -> Result<Self, OutOfRangeError> {
+        match scale(hours, 60 * 60 * 1_000_000_000) {
+            Ok(nanos) => Ok(Self(nanos)),
+            Err(e) => Err(e),
+        }
+    }
+    pub const fn try_from_minutes(minutes: i64) -> Result<Self, OutOfRangeError> {
+        match scale(minutes, 60 * 1_000_000_000) {
+            Ok(nanos) => Ok(Self(nanos)),
+            Err(e) => Err(e),
+        }
+    }
+    pub const fn try_from_secs(secs: i64) -> Result<Self, OutOfRangeError> {
+        match scale(secs, 1_000_000_000) {
+            Ok(nanos) => Ok(Self(nanos)),
+            Err(e) => Err(e),
+        }
+    }
+    pub const fn try_from_millis(millis: i64) -> Result<Self, OutOfRangeError> {
+        match scale(millis, 1_000_000) {
+            Ok(nanos) => Ok(Self(nanos)),
+            Err(e) => Err(e),
+        }
+    }
+    pub const fn try_from_micros(micros: i64) -> Result<Self, OutOfRangeError> {
+        match scale(micros, 1_000) {
+            Ok(nanos) => Ok(Self(nanos)),
+            Err(e) => Err(e),
+        }
     }
     pub const fn from_nanos(nanos: i64) -> Self {
         Self(nanos)
     }
+
+    /// `self + rhs`, or `None` on overflow.
+    pub const fn checked_add(self, rhs: TimestampDelta) -> Option<Self> {
+        match self.0.checked_add(rhs.0) {
+            Some(nanos) => Some(Self(nanos)),
+            None => None,
+        }
+    }
+
+    /// `self - rhs`, or `None` on overflow.
+    pub const fn checked_sub(self, rhs: TimestampDelta) -> Option<Self> {
+        match self.0.checked_sub(rhs.0) {
+            Some(nanos) => Some(Self(nanos)),
+            None => None,
+        }
+    }
+
+    /// `self * rhs`, or `None` on overflow.
+    pub const fn checked_mul(self, rhs: i64) -> Option<Self> {
+        match self.0.checked_mul(rhs) {
+            Some(nanos) => Some(Self(nanos)),
+            None => None,
+        }
+    }
+
+    /// The absolute value. Overflows for the minimal delta, like `i64::abs`.
+    pub const fn abs(self) -> Self {
+        Self(self.0.abs())
+    }
+
+    pub const fn is_negative(self) -> bool {
+        self.0 < 0
+    }
+
+    pub const fn is_positive(self) -> bool {
+        self.0 > 0
+    }
+
+    /// `-1`, `0` or `1`, depending on the sign.
+    pub const fn signum(self) -> i64 {
+        self.0.signum()
+    }
+
+    /// `self / rhs`, or `None` if `rhs` is zero or on overflow.
+    pub const fn checked_div(self, rhs: i64) -> Option<Self> {
+        match self.0.checked_div(rhs) {
+            Some(nanos) => Some(Self(nanos)),
+            None => None,
+        }
+    }
+
+    /// `self + rhs`, clamped to the representable range.
+    pub const fn saturating_add(self, rhs: TimestampDelta) -> Self {
+        Self(self.0.saturating_add(rhs.0))
+    }
+
+    /// `self - rhs`, clamped to the representable range.
+    pub const fn saturating_sub(self, rhs: TimestampDelta) -> Self {
+        Self(self.0.saturating_sub(rhs.0))
+    }
 }
 
+/// RFC 3339 in UTC, e.g. `2025-04-26T12:00:00.5Z`. Use `{:?}` or [`as_nanos`][Self::as_nanos]
+/// for the raw nanoseconds.
 impl Display for Timestamp {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.0)
+        const DAY: i64 = 86_400_000_000_000;
+        let (days, nanos) = (self.0.div_euclid(DAY), self.0.rem_euclid(DAY));
+        let (year, month, day) = civil_from_days(days);
+        let secs = nanos / 1_000_000_000;
+        let (hours, minutes, secs) = (secs / 3600, secs / 60 % 60, secs % 60);
+        write!(
+            f,
+            "{year:04}-{month:02}-{day:02}T{hours:02}:{minutes:02}:{secs:02}"
+        )?;
+        match nanos % 1_000_000_000 {
+            0 => {}
+            frac if frac % 1_000_000 == 0 => write!(f, ".{:03}", frac / 1_000_000)?,
+            frac if frac % 1_000 == 0 => write!(f, ".{:06}", frac / 1_000)?,
+            frac => write!(f, ".{frac:09}")?,
+        }
+        write!(f, "Z")
     }
 }
 
+/// Day since 1970-01-01 of the given year, month and day, the inverse of [`civil_from_days`].
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    // See http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (i64::from(month) + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Year, month and day of the given day since 1970-01-01, in the proleptic Gregorian calendar.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    // See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
 impl From<i64> for Timestamp {
     fn from(nanos: i64) -> Self {
         Self(nanos)
     }
 }
 
+#[cfg(feature = "chrono")]
 impl Add<TimeDelta> for Timestamp {
     type Output = Timestamp;
 
@@ -162,6 +366,15 @@ impl Add<TimestampDelta> for Timestamp {
     }
 }
 
+impl Add<Duration> for Timestamp {
+    type Output = Timestamp;
+
+    fn add(self, rhs: Duration) -> Self::Output {
+        self + TimestampDelta::from(rhs)
+    }
+}
+
+#[cfg(feature = "chrono")]
 impl Sub<TimeDelta> for Timestamp {
     type Output = TimestampDelta;
 
@@ -177,33 +390,101 @@ impl Sub<Timestamp> for Timestamp {
     }
 }
 
+/// Parse RFC 3339 as printed by `Display`, e.g. `2025-04-26T12:00:00.5Z`, also with an offset
+/// like `2025-04-26T14:00:00+02:00`, or the raw nanoseconds, e.g. `1745668800500000000`.
 impl FromStr for Timestamp {
-    type Err = ParseIntError;
+    type Err = ParseTimestampError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let nanos = i64::from_str(s)?;
-        Ok(Timestamp::from(nanos))
+        if let Ok(nanos) = i64::from_str(s) {
+            return Ok(Timestamp::from(nanos));
+        }
+        parse_rfc3339(s).ok_or_else(|| ParseTimestampError(s.to_string()))
     }
 }
 
+fn parse_rfc3339(s: &str) -> Option<Timestamp> {
+    let number = |from: usize, to: usize| -> Option<i64> {
+        let digits = s.get(from..to)?;
+        digits
+            .bytes()
+            .all(|b| b.is_ascii_digit())
+            .then(|| digits.parse().ok())?
+    };
+    let separators = [(4, b'-'), (7, b'-'), (13, b':'), (16, b':')];
+    let bytes = s.as_bytes();
+    if bytes.len() < 20
+        || !matches!(bytes[10], b'T' | b't')
+        || separators.iter().any(|&(at, sep)| bytes[at] != sep)
+    {
+        return None;
+    }
+    let (year, month, day) = (number(0, 4)?, number(5, 7)?, number(8, 10)?);
+    let (hours, minutes, secs) = (number(11, 13)?, number(14, 16)?, number(17, 19)?);
+    let (month, day) = (u32::try_from(month).ok()?, u32::try_from(day).ok()?);
+    let days = days_from_civil(year, month, day);
+    if civil_from_days(days) != (year, month, day) || hours > 23 || minutes > 59 || secs > 59 {
+        return None;
+    }
+
+    let mut rest = &s[19..];
+    let mut frac = 0;
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let digits = fraction
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(fraction.len());
+        if !(1..=9).contains(&digits) {
+            return None;
+        }
+        frac = format!("{:0<9}", &fraction[..digits]).parse().ok()?;
+        rest = &fraction[digits..];
+    }
+    let offset = match rest.as_bytes() {
+        [b'Z' | b'z'] => 0,
+        [sign @ (b'+' | b'-'), _, _, b':', _, _] => {
+            let (hours, minutes) = (
+                number(s.len() - 5, s.len() - 3)?,
+                number(s.len() - 2, s.len())?,
+            );
+            if hours > 23 || minutes > 59 {
+                return None;
+            }
+            let offset = hours * 3600 + minutes * 60;
+            if *sign == b'-' {
+                -offset
+            } else {
+                offset
+            }
+        }
+        _ => return None,
+    };
+    let secs = days * 86_400 + hours * 3600 + minutes * 60 + secs - offset;
+    let nanos = i128::from(secs) * 1_000_000_000 + i128::from(frac);
+    i64::try_from(nanos).ok().map(Timestamp::from)
+}
+
+#[cfg(feature = "chrono")]
 impl From<DateTime<Utc>> for Timestamp {
     fn from(dt: DateTime<Utc>) -> Self {
         Self(dt.timestamp_nanos_opt().unwrap())
     }
 }
 
+#[cfg(feature = "chrono")]
 impl From<DateTime<Local>> for Timestamp {
     fn from(dt: DateTime<Local>) -> Self {
         Self(dt.with_timezone(&Utc).timestamp_nanos_opt().unwrap())
     }
 }
 
+#[cfg(feature = "chrono")]
 impl From<Timestamp> for DateTime<Utc> {
     fn from(ts: Timestamp) -> Self {
         Utc.timestamp_nanos(ts.0)
     }
 }
 
+#[cfg(feature = "chrono")]
 impl From<Timestamp> for DateTime<Local> {
     fn from(ts: Timestamp) -> Self {
         let utc: DateTime<Utc> = ts.into();
@@ -211,15 +492,44 @@ impl From<Timestamp> for DateTime<Local> {
     }
 }
 
+#[cfg(feature = "chrono")]
 impl From<Timestamp> for TimeDelta {
     fn from(ts: Timestamp) -> Self {
         TimeDelta::nanoseconds(ts.0)
     }
 }
 
+/// Hours, minutes and seconds, e.g. `1h02m03.5s`, or a single unit below a second,
+/// e.g. `250ms`. Use `{:?}` or [`as_nanos`][Self::as_nanos] for the raw nanoseconds.
 impl Display for TimestampDelta {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.0)
+        const SECOND: u64 = 1_000_000_000;
+        if self.0 < 0 {
+            write!(f, "-")?;
+        }
+        let nanos = self.0.unsigned_abs();
+        if nanos < SECOND {
+            return match nanos {
+                0 => write!(f, "0s"),
+                n if n % 1_000_000 == 0 => write!(f, "{}ms", n / 1_000_000),
+                n if n % 1_000 == 0 => write!(f, "{}us", n / 1_000),
+                n => write!(f, "{n}ns"),
+            };
+        }
+        let (secs, frac) = (nanos / SECOND, nanos % SECOND);
+        let (hours, minutes, secs) = (secs / 3600, secs / 60 % 60, secs % 60);
+        if hours > 0 {
+            write!(f, "{hours}h{minutes:02}m{secs:02}")?;
+        } else if minutes > 0 {
+            write!(f, "{minutes}m{secs:02}")?;
+        } else {
+            write!(f, "{secs}")?;
+        }
+        if frac > 0 {
+            let frac = format!("{frac:09}");
+            write!(f, ".{}", frac.trim_end_matches('0'))?;
+        }
+        write!(f, "s")
     }
 }
 
@@ -229,6 +539,7 @@ impl From<i64> for TimestampDelta {
     }
 }
 
+#[cfg(feature = "chrono")]
 impl Add<TimeDelta> for TimestampDelta {
     type Output = TimestampDelta;
 
@@ -244,6 +555,7 @@ impl Add<TimestampDelta> for TimestampDelta {
     }
 }
 
+#[cfg(feature = "chrono")]
 impl Sub<TimeDelta> for TimestampDelta {
     type Output = TimestampDelta;
 
@@ -259,14 +571,435 @@ impl Sub<TimestampDelta> for TimestampDelta {
     }
 }
 
+/// A time that does not fit into the nanoseconds of a [`Timestamp`] or [`TimestampDelta`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OutOfRangeError;
+
+impl Display for OutOfRangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Time out of range of i64 nanoseconds")
+    }
+}
+
+impl std::error::Error for OutOfRangeError {}
+
+const fn scale(value: i64, nanos_per_unit: i64) -> Result<i64, OutOfRangeError> {
+    match value.checked_mul(nanos_per_unit) {
+        Some(nanos) => Ok(nanos),
+        None => Err(OutOfRangeError),
+    }
+}
+
+const fn expect_in_range(nanos: Result<i64, OutOfRangeError>) -> i64 {
+    match nanos {
+        Ok(nanos) => nanos,
+        Err(_) => panic!("Time out of range of i64 nanoseconds"),
+    }
+}
+
+/// The string is not a timestamp, see [`Timestamp::from_str`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseTimestampError(String);
+
+impl Display for ParseTimestampError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid timestamp {:?}", self.0)
+    }
+}
+
+impl std::error::Error for ParseTimestampError {}
+
+/// The string is not a duration, see [`TimestampDelta::from_str`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseDeltaError(String);
+
+impl Display for ParseDeltaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid duration {:?}", self.0)
+    }
+}
+
+impl std::error::Error for ParseDeltaError {}
+
+/// Parse durations like `"500ms"`, `"1.5s"`, `"-2m 30s"` or `"1h02m03.5s"` as printed by `Display`.
+///
+/// A duration is a sequence of numbers with units `ns`, `us`, `ms`, `s`, `m`, `h` and `d`,
+/// optionally separated by spaces and preceded by `-`. A unit is required, except for `"0"`.
+/// Numbers may have a fraction, as long as the result is a whole number of nanoseconds.
+impl FromStr for TimestampDelta {
+    type Err = ParseDeltaError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseDeltaError(s.to_string());
+        let (negative, mut rest) = match s.trim().strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s.trim()),
+        };
+        if rest == "0" {
+            return Ok(TimestampDelta::zero());
+        }
+        if rest.is_empty() {
+            return Err(error());
+        }
+        let digits = |s: &str| s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        // Summed in `i128`, so that the fractions and `i64::MIN` need no special cases.
+        let mut nanos: i128 = 0;
+        while !rest.is_empty() {
+            let whole = digits(rest);
+            let value: i64 = rest[..whole].parse().map_err(|_| error())?;
+            rest = &rest[whole..];
+            // The fraction as a numerator over a power of ten.
+            let (mut numerator, mut denominator): (i64, i128) = (0, 1);
+            if let Some(after_point) = rest.strip_prefix('.') {
+                let len = digits(after_point);
+                if !(1..=18).contains(&len) {
+                    return Err(error());
+                }
+                numerator = after_point[..len].parse().map_err(|_| error())?;
+                denominator = 10_i128.pow(len as u32);
+                rest = &after_point[len..];
+            }
+            let unit = rest
+                .find(|c: char| !c.is_ascii_alphabetic())
+                .unwrap_or(rest.len());
+            let scale: i128 = match &rest[..unit] {
+                "ns" => 1,
+                "us" => 1_000,
+                "ms" => 1_000_000,
+                "s" => 1_000_000_000,
+                "m" => 60 * 1_000_000_000,
+                "h" => 3600 * 1_000_000_000,
+                "d" => 24 * 3600 * 1_000_000_000,
+                _ => return Err(error()),
+            };
+            rest = rest[unit..].trim_start();
+            let fraction = i128::from(numerator) * scale;
+            if fraction % denominator != 0 {
+                return Err(error());
+            }
+            nanos = nanos
+                .checked_add(i128::from(value) * scale + fraction / denominator)
+                .ok_or_else(error)?;
+        }
+        let nanos = i64::try_from(if negative { -nanos } else { nanos }).map_err(|_| error())?;
+        Ok(TimestampDelta::from(nanos))
+    }
+}
+
+impl Neg for TimestampDelta {
+    type Output = TimestampDelta;
+
+    fn neg(self) -> Self::Output {
+        TimestampDelta::from(-self.0)
+    }
+}
+
+impl Mul<i64> for TimestampDelta {
+    type Output = TimestampDelta;
+
+    fn mul(self, rhs: i64) -> Self::Output {
+        TimestampDelta::from(self.0 * rhs)
+    }
+}
+
+/// Rounded to the nearest nanosecond, saturating at the representable range.
+impl Mul<f64> for TimestampDelta {
+    type Output = TimestampDelta;
+
+    fn mul(self, rhs: f64) -> Self::Output {
+        TimestampDelta::from((self.0 as f64 * rhs).round() as i64)
+    }
+}
+
+impl Div<i64> for TimestampDelta {
+    type Output = TimestampDelta;
+
+    fn div(self, rhs: i64) -> Self::Output {
+        TimestampDelta::from(self.0 / rhs)
+    }
+}
+
+#[cfg(feature = "chrono")]
 impl From<TimeDelta> for TimestampDelta {
     fn from(delta: TimeDelta) -> Self {
         TimestampDelta::from(delta.num_nanoseconds().unwrap())
     }
 }
 
+#[cfg(feature = "chrono")]
 impl From<TimestampDelta> for TimeDelta {
     fn from(delta: TimestampDelta) -> Self {
         TimeDelta::nanoseconds(delta.0)
     }
 }
+
+/// Durations longer than about 292 years are clamped to the maximum delta.
+impl From<Duration> for TimestampDelta {
+    fn from(duration: Duration) -> Self {
+        TimestampDelta::from(i64::try_from(duration.as_nanos()).unwrap_or(i64::MAX))
+    }
+}
+
+/// Fails for negative deltas.
+impl TryFrom<TimestampDelta> for Duration {
+    type Error = TryFromIntError;
+
+    fn try_from(delta: TimestampDelta) -> Result<Self, Self::Error> {
+        Ok(Duration::from_nanos(u64::try_from(delta.0)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checked_arithmetic() {
+        let max = Timestamp::from_nanos(i64::MAX);
+        let second = TimestampDelta::from_secs(1);
+        assert_eq!(max.checked_add(second), None);
+        assert_eq!(
+            max.checked_sub(second),
+            Some(Timestamp::from_nanos(i64::MAX - 1_000_000_000))
+        );
+        assert_eq!(Timestamp::from_nanos(i64::MIN).checked_sub(second), None);
+
+        assert_eq!(
+            second.checked_add(second),
+            Some(TimestampDelta::from_secs(2))
+        );
+        assert_eq!(
+            TimestampDelta::from_nanos(i64::MIN).checked_sub(second),
+            None
+        );
+        assert_eq!(second.checked_mul(-3), Some(TimestampDelta::from_secs(-3)));
+        assert_eq!(second.checked_mul(i64::MAX), None);
+    }
+
+    #[test]
+    fn duration_interop() {
+        let delta = TimestampDelta::from(Duration::from_millis(1500));
+        assert_eq!(delta, TimestampDelta::from_millis(1500));
+        assert_eq!(Duration::try_from(delta), Ok(Duration::from_millis(1500)));
+        assert!(Duration::try_from(TimestampDelta::from_secs(-1)).is_err());
+        assert_eq!(
+            TimestampDelta::from(Duration::MAX),
+            TimestampDelta::from_nanos(i64::MAX)
+        );
+        assert_eq!(
+            Timestamp::from_secs(1) + Duration::from_secs(2),
+            Timestamp::from_secs(3)
+        );
+    }
+
+    #[test]
+    fn parse_delta() {
+        let parse = |s: &str| s.parse::<TimestampDelta>();
+        assert_eq!(parse("500ms"), Ok(TimestampDelta::from_millis(500)));
+        assert_eq!(parse("5s"), Ok(TimestampDelta::from_secs(5)));
+        assert_eq!(parse("2m30s"), Ok(TimestampDelta::from_secs(150)));
+        assert_eq!(
+            parse(" 1h 1us "),
+            Ok(TimestampDelta::from_micros(3_600_000_001))
+        );
+        assert_eq!(parse("-1d"), Ok(TimestampDelta::from_hours(-24)));
+        assert_eq!(parse("0"), Ok(TimestampDelta::zero()));
+        assert_eq!(parse("1.5s"), Ok(TimestampDelta::from_millis(1500)));
+        assert_eq!(parse("0.25h"), Ok(TimestampDelta::from_minutes(15)));
+        assert_eq!(
+            parse("1.000000001s"),
+            Ok(TimestampDelta::from_nanos(1_000_000_001))
+        );
+        let invalid = [
+            "",
+            "-",
+            "5",
+            "s",
+            "5 s",
+            "5sec",
+            "1.s",
+            ".5s",
+            "1.5ns",
+            "1.5.5s",
+            "--5s",
+            "99999999999d",
+        ];
+        for s in invalid {
+            assert!(parse(s).is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(Timestamp::zero().to_string(), "1970-01-01T00:00:00Z");
+        assert_eq!(
+            Timestamp::from_millis(1_745_668_800_500).to_string(),
+            "2025-04-26T12:00:00.500Z"
+        );
+        assert_eq!(
+            Timestamp::from_nanos(-1).to_string(),
+            "1969-12-31T23:59:59.999999999Z"
+        );
+        assert_eq!(
+            Timestamp::from_secs(951_782_400).to_string(),
+            "2000-02-29T00:00:00Z"
+        );
+        assert_eq!(format!("{:?}", Timestamp::from_nanos(5)), "Timestamp(5)");
+
+        let display = |nanos: i64| TimestampDelta::from_nanos(nanos).to_string();
+        assert_eq!(display(0), "0s");
+        assert_eq!(display(1), "1ns");
+        assert_eq!(display(1_500), "1500ns");
+        assert_eq!(display(250_000_000), "250ms");
+        assert_eq!(display(-2_000), "-2us");
+        assert_eq!(display(5_000_000_000), "5s");
+        assert_eq!(display(150_000_000_000), "2m30s");
+        assert_eq!(display(3_723_500_000_000), "1h02m03.5s");
+        assert_eq!(display(-86_400_000_000_001), "-24h00m00.000000001s");
+    }
+
+    #[test]
+    fn parse_display() {
+        for nanos in [
+            0,
+            1,
+            -1,
+            1_500,
+            250_000_000,
+            5_000_000_000,
+            3_723_500_000_000,
+            -86_400_000_000_001,
+            i64::MAX,
+            i64::MIN,
+        ] {
+            let delta = TimestampDelta::from_nanos(nanos);
+            assert_eq!(delta.to_string().parse(), Ok(delta), "{delta}");
+            let ts = Timestamp::from_nanos(nanos);
+            assert_eq!(ts.to_string().parse(), Ok(ts), "{ts}");
+        }
+
+        let parse = |s: &str| s.parse::<Timestamp>();
+        let noon = Ok(Timestamp::from_millis(1_745_668_800_500));
+        assert_eq!(parse("2025-04-26T12:00:00.5Z"), noon);
+        assert_eq!(parse("2025-04-26t14:00:00.500+02:00"), noon);
+        assert_eq!(parse("2025-04-26T11:30:00.5-00:30"), noon);
+        assert_eq!(parse("1745668800500000000"), noon);
+        let invalid = [
+            "",
+            "2025-04-26",
+            "2025-04-26T12:00:00",
+            "2025-04-26 12:00:00Z",
+            "2025-02-29T12:00:00Z",
+            "2025-04-26T24:00:00Z",
+            "2025-04-26T12:00:00.Z",
+            "2025-04-26T12:00:00.0000000001Z",
+            "2025-04-26T12:00:00+2:00",
+            "2262-04-12T00:00:00Z",
+        ];
+        for s in invalid {
+            assert!(parse(s).is_err(), "{s}");
+        }
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn display_matches_chrono() {
+        use chrono::SecondsFormat;
+
+        for nanos in [
+            0,
+            -1,
+            1_000,
+            1_745_668_800_500_000_000,
+            -6_000_000_000_000_000_000,
+        ] {
+            let ts = Timestamp::from_nanos(nanos);
+            let rfc3339 = ts.utc().to_rfc3339_opts(SecondsFormat::AutoSi, true);
+            assert_eq!(ts.to_string(), rfc3339);
+        }
+    }
+
+    #[test]
+    fn now() {
+        use crate::clock::ManualClock;
+
+        let clock = ManualClock::new();
+        clock.advance_to(Timestamp::from_secs(3));
+        assert_eq!(Timestamp::now_with(&clock), Timestamp::from_secs(3));
+        assert!(Timestamp::now() > Timestamp::from_secs(1_745_000_000));
+    }
+
+    #[test]
+    fn scalar_arithmetic() {
+        let base = TimestampDelta::from_millis(100);
+        let retries = 3;
+        assert_eq!(base * 2i64.pow(retries), TimestampDelta::from_millis(800));
+        assert_eq!(base * 1.5, TimestampDelta::from_millis(150));
+        assert_eq!(base * -0.5, TimestampDelta::from_millis(-50));
+        assert_eq!(base * f64::MAX, TimestampDelta::from_nanos(i64::MAX));
+        assert_eq!(base / 3, TimestampDelta::from_nanos(33_333_333));
+        assert_eq!(base.checked_div(0), None);
+        assert_eq!(base.checked_div(4), Some(TimestampDelta::from_millis(25)));
+        assert_eq!(TimestampDelta::from_nanos(i64::MIN).checked_div(-1), None);
+    }
+
+    #[test]
+    fn bounds_checked_constructors() {
+        assert_eq!(
+            TimestampDelta::try_from_hours(2),
+            Ok(TimestampDelta::from_minutes(120))
+        );
+        assert_eq!(
+            Timestamp::try_from_secs(-3),
+            Ok(Timestamp::from_millis(-3000))
+        );
+        assert_eq!(
+            TimestampDelta::try_from_secs(i64::MAX),
+            Err(OutOfRangeError)
+        );
+        assert_eq!(
+            Timestamp::try_from_hours(i64::MIN / 2),
+            Err(OutOfRangeError)
+        );
+        assert!(std::panic::catch_unwind(|| TimestampDelta::from_micros(i64::MAX)).is_err());
+        assert!(std::panic::catch_unwind(|| Timestamp::from_minutes(i64::MIN)).is_err());
+    }
+
+    #[test]
+    fn sign() {
+        let late = Timestamp::from_secs(3) - Timestamp::from_secs(5);
+        assert!(late.is_negative());
+        assert!(!late.is_positive());
+        assert_eq!(late.signum(), -1);
+        assert_eq!(late.abs(), TimestampDelta::from_secs(2));
+        assert_eq!(-late, TimestampDelta::from_secs(2));
+        assert_eq!(TimestampDelta::zero().signum(), 0);
+        assert!(!TimestampDelta::zero().is_negative());
+    }
+
+    #[test]
+    fn saturating_arithmetic() {
+        let max = Timestamp::from_nanos(i64::MAX);
+        let second = TimestampDelta::from_secs(1);
+        assert_eq!(max.saturating_add(second), max);
+        assert_eq!(
+            Timestamp::zero().saturating_sub(second),
+            Timestamp::from_secs(-1)
+        );
+        assert_eq!(
+            Timestamp::from_nanos(i64::MIN).saturating_sub(second),
+            Timestamp::from_nanos(i64::MIN)
+        );
+
+        // Exponential backoff stops growing at the maximum.
+        let mut backoff = second;
+        for _ in 0..100 {
+            backoff = backoff.saturating_add(backoff);
+        }
+        assert_eq!(backoff, TimestampDelta::from_nanos(i64::MAX));
+        assert_eq!(
+            TimestampDelta::from_nanos(i64::MIN).saturating_sub(second),
+            TimestampDelta::from_nanos(i64::MIN)
+        );
+    }
+}
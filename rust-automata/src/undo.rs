@@ -0,0 +1,155 @@
+//! Restore the machine to earlier states: the states before the last transitions,
+//! or named checkpoints.
+
+use crate::{StateMachine, StateMachineImpl};
+use std::collections::VecDeque;
+
+// A copy of the state, and of the data if it is restored as well.
+type Saved<T> = (<T as StateMachineImpl>::State, Option<T>);
+
+/// A saved state (and data), with the function to copy it, chosen where the `Clone` bounds are known.
+pub(crate) struct Checkpoint<T: StateMachineImpl> {
+    saved: Saved<T>,
+    copy: fn(&Saved<T>) -> Saved<T>,
+}
+
+/// The states before the most recent transitions, newest at the back.
+pub(crate) struct UndoStack<T: StateMachineImpl> {
+    saved: VecDeque<Saved<T>>,
+    depth: usize,
+    // Chosen when undo is enabled, where the `Clone` bounds are known.
+    save: fn(&T::State, &T) -> Saved<T>,
+}
+
+impl<T: StateMachineImpl> UndoStack<T> {
+    pub(crate) fn record(&mut self, state: &T::State, data: &T) {
+        if self.depth == 0 {
+            return;
+        }
+        if self.saved.len() == self.depth {
+            self.saved.pop_front();
+        }
+        self.saved.push_back((self.save)(state, data));
+    }
+}
+
+impl<T: StateMachineImpl> StateMachine<T> {
+    /// Remember the states before the last `depth` transitions, so they can be [undone][Self::undo].
+    ///
+    /// Only the state is restored, see [`enable_undo_with_data`][Self::enable_undo_with_data]
+    /// to restore the data as well. Replaces the previous undo history.
+    pub fn enable_undo(&mut self, depth: usize)
+    where
+        T::State: Clone,
+    {
+        self.set_undo(depth, |state, _| (state.clone(), None));
+    }
+
+    /// Like [`enable_undo`][Self::enable_undo], but restore the data as well.
+    pub fn enable_undo_with_data(&mut self, depth: usize)
+    where
+        T: Clone,
+        T::State: Clone,
+    {
+        self.set_undo(depth, |state, data| (state.clone(), Some(data.clone())));
+    }
+
+    fn set_undo(&mut self, depth: usize, save: fn(&T::State, &T) -> Saved<T>) {
+        self.extras_mut().undo = Some(UndoStack {
+            saved: VecDeque::with_capacity(depth),
+            depth,
+            save,
+        });
+    }
+
+    fn undo_stack(&mut self) -> Option<&mut UndoStack<T>> {
+        self.extras.get_mut()?.undo.as_mut()
+    }
+
+    /// Forget the undo history and stop recording it.
+    pub fn disable_undo(&mut self) {
+        if let Some(extras) = self.extras.get_mut() {
+            extras.undo = None;
+        }
+    }
+
+    /// Go back to the state before the last transition. Returns `false` if there is nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        let Some((state, data)) = self.undo_stack().and_then(|undo| undo.saved.pop_back()) else {
+            return false;
+        };
+        self.state = state;
+        if let Some(data) = data {
+            self.data = data;
+        }
+        true
+    }
+
+    /// Number of transitions that can be undone.
+    pub fn undo_len(&self) -> usize {
+        self.extras
+            .get()
+            .and_then(|extras| extras.undo.as_ref())
+            .map_or(0, |undo| undo.saved.len())
+    }
+
+    /// Save the state under `label`, replacing a previous checkpoint with the same label.
+    ///
+    /// Unlike undo, checkpoints are kept until they are [discarded][Self::discard_checkpoint].
+    pub fn checkpoint(&mut self, label: impl Into<String>)
+    where
+        T::State: Clone,
+    {
+        let checkpoint = Checkpoint {
+            saved: (self.state.clone(), None),
+            copy: |(state, _): &Saved<T>| (state.clone(), None),
+        };
+        self.extras_mut()
+            .checkpoints
+            .insert(label.into(), checkpoint);
+    }
+
+    /// Like [`checkpoint`][Self::checkpoint], but save the data as well.
+    pub fn checkpoint_with_data(&mut self, label: impl Into<String>)
+    where
+        T: Clone,
+        T::State: Clone,
+    {
+        let checkpoint = Checkpoint {
+            saved: (self.state.clone(), Some(self.data.clone())),
+            copy: |(state, data): &Saved<T>| (state.clone(), data.clone()),
+        };
+        self.extras_mut()
+            .checkpoints
+            .insert(label.into(), checkpoint);
+    }
+
+    /// Restore the state (and data) saved under `label`. The checkpoint is kept,
+    /// the undo history is cleared. Returns `false` if there is no such checkpoint.
+    pub fn rollback_to(&mut self, label: &str) -> bool {
+        let Some(checkpoint) = self
+            .extras
+            .get()
+            .and_then(|extras| extras.checkpoints.get(label))
+        else {
+            return false;
+        };
+        let (state, data) = (checkpoint.copy)(&checkpoint.saved);
+        self.state = state;
+        if let Some(data) = data {
+            self.data = data;
+        }
+        if let Some(undo) = self.undo_stack() {
+            undo.saved.clear();
+        }
+        true
+    }
+
+    /// Forget the checkpoint. Returns `false` if there is no such checkpoint.
+    pub fn discard_checkpoint(&mut self, label: &str) -> bool {
+        self.extras
+            .get_mut()
+            .and_then(|extras| extras.checkpoints.remove(label))
+            .is_some()
+    }
+}
@@ -0,0 +1,213 @@
+//! Checks that a machine compiles down to plain code, using [`cargo asm`](https://crates.io/crates/cargo-asm).
+//!
+//! Simple machines should cost nothing at runtime: `relay` or `step` on a machine without
+//! allocating payloads is inlined into a few instructions, without calls to the allocator
+//! and without panic branches for the impossible transitions. To keep it that way, put the
+//! machine behind a function of a binary target, and check the function in a test:
+//!
+//! ```no_run
+//! use rust_automata::zero_cost::Asm;
+//!
+//! let asm = Asm::of("my_crate::run").expect("cargo asm failed");
+//! asm.assert_zero_cost();
+//! ```
+//!
+//! `cargo asm` builds the crate in release mode, so the checks are slow and best kept in a
+//! separate test. The checks are heuristics over the symbols called from the function,
+//! they do not look into functions that are not inlined.
+
+use std::fmt::{self, Display};
+use std::process::Command;
+
+/// Symbols of the allocator.
+const ALLOCATION_SYMBOLS: &[&str] = &[
+    "__rust_alloc",
+    "__rust_realloc",
+    "__rdl_alloc",
+    "alloc::alloc::",
+    "malloc",
+    "calloc",
+    "realloc",
+];
+
+/// Symbols of the panic machinery, including the failed `unwrap`s and bounds checks.
+const PANIC_SYMBOLS: &[&str] = &["panic", "_fail"];
+
+/// Error running `cargo asm`.
+#[derive(Debug)]
+pub enum AsmError {
+    /// `cargo asm` could not be started, likely because it is not installed.
+    Io(std::io::Error),
+    /// `cargo asm` failed, with its error output.
+    Failed(String),
+}
+
+impl Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AsmError::Io(e) => write!(f, "cannot run `cargo asm`, is it installed? {e}"),
+            AsmError::Failed(stderr) => write!(f, "`cargo asm` failed: {stderr}"),
+        }
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+/// The assembly of a function.
+#[derive(Debug, Clone)]
+pub struct Asm {
+    /// Path of the function, like `my_crate::run`.
+    pub symbol: String,
+    /// The assembly, as printed by `cargo asm`.
+    pub listing: String,
+}
+
+impl Asm {
+    /// Runs `cargo asm` for the function at `symbol` in the current crate.
+    pub fn of(symbol: &str) -> Result<Self, AsmError> {
+        let output = Command::new("cargo")
+            .args(["asm", "--no-color", symbol])
+            .output()
+            .map_err(AsmError::Io)?;
+        let listing = String::from_utf8_lossy(&output.stdout).into_owned();
+        if !output.status.success() || listing.trim().is_empty() {
+            let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+            return Err(AsmError::Failed(stderr));
+        }
+        Ok(Self::from_listing(symbol, listing))
+    }
+
+    /// The assembly from a listing that was already generated.
+    pub fn from_listing(symbol: &str, listing: impl Into<String>) -> Self {
+        Asm {
+            symbol: symbol.to_string(),
+            listing: listing.into(),
+        }
+    }
+
+    /// Symbols called or jumped to from the function, without the local labels and the calls
+    /// through registers.
+    pub fn calls(&self) -> impl Iterator<Item = &str> {
+        self.listing.lines().filter_map(|line| {
+            let line = line.trim();
+            let (instruction, target) = line.split_once(char::is_whitespace)?;
+            if !matches!(instruction, "call" | "callq" | "jmp" | "jmpq" | "bl" | "b") {
+                return None;
+            }
+            let mut target = target.trim();
+            // Calls through the GOT, like `qword ptr [rip + __rust_alloc@GOTPCREL]`.
+            if let Some(start) = target.find("[rip + ") {
+                let end = target.rfind(']')?;
+                target = &target[start + "[rip + ".len()..end];
+            }
+            let target = target.split('@').next()?;
+            let local = target.starts_with('.') || target.starts_with("LBB");
+            let register = target.len() <= 4 && target.chars().all(|c| c.is_ascii_alphanumeric());
+            (!target.is_empty() && !local && !register && !target.contains('[')).then_some(target)
+        })
+    }
+
+    /// Calls to the allocator.
+    pub fn allocations(&self) -> Vec<&str> {
+        self.calls_to(ALLOCATION_SYMBOLS)
+    }
+
+    /// Calls that panic.
+    pub fn panic_branches(&self) -> Vec<&str> {
+        self.calls_to(PANIC_SYMBOLS)
+    }
+
+    fn calls_to(&self, symbols: &[&str]) -> Vec<&str> {
+        self.calls()
+            .filter(|call| symbols.iter().any(|symbol| call.contains(symbol)))
+            .collect()
+    }
+
+    /// Panics if the function calls the allocator.
+    pub fn assert_no_allocations(&self) {
+        let allocations = self.allocations();
+        assert!(
+            allocations.is_empty(),
+            "{} allocates: {allocations:?}\n{}",
+            self.symbol,
+            self.listing
+        );
+    }
+
+    /// Panics if the function has a branch that panics.
+    pub fn assert_no_panics(&self) {
+        let panics = self.panic_branches();
+        assert!(
+            panics.is_empty(),
+            "{} may panic: {panics:?}\n{}",
+            self.symbol,
+            self.listing
+        );
+    }
+
+    /// Panics if the function allocates or may panic.
+    pub fn assert_zero_cost(&self) {
+        self.assert_no_allocations();
+        self.assert_no_panics();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inlined_machine_is_zero_cost() {
+        let asm = Asm::from_listing(
+            "flip_flop::main",
+            "flip_flop::main:\n xor     eax, eax\n ret\n",
+        );
+        assert_eq!(asm.calls().count(), 0);
+        asm.assert_zero_cost();
+    }
+
+    #[test]
+    fn finds_allocations_and_panics() {
+        let listing = "\
+run:
+ push    rbx
+ call    qword ptr [rip + __rust_alloc@GOTPCREL]
+ call    __rust_alloc
+ test    rax, rax
+ je      .LBB0_2
+ jmp     .LBB0_3
+.LBB0_2:
+ call    alloc::alloc::handle_alloc_error
+ call    core::panicking::panic_fmt
+ call    my_crate::helper
+ call    rax
+";
+        let asm = Asm::from_listing("run", listing);
+        assert_eq!(
+            asm.calls().collect::<Vec<_>>(),
+            [
+                "__rust_alloc",
+                "__rust_alloc",
+                "alloc::alloc::handle_alloc_error",
+                "core::panicking::panic_fmt",
+                "my_crate::helper"
+            ]
+        );
+        assert_eq!(
+            asm.allocations(),
+            [
+                "__rust_alloc",
+                "__rust_alloc",
+                "alloc::alloc::handle_alloc_error"
+            ]
+        );
+        assert_eq!(asm.panic_branches(), ["core::panicking::panic_fmt"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "run may panic")]
+    fn asserts_no_panics() {
+        let asm = Asm::from_listing("run", " call    core::option::unwrap_failed\n");
+        asm.assert_no_panics();
+    }
+}
@@ -9,7 +9,7 @@ fn compile_fail() {
 #[test]
 fn asm_generation() {
     let output = Command::new("cargo")
-        .args(&["asm", "--no-color", "flip_flop::main"])
+        .args(["asm", "--no-color", "flip_flop::main"])
         .output()
         .expect("Failed to execute `cargo asm` -- is it installed?");
 
@@ -18,3 +18,10 @@ fn asm_generation() {
         .expect("Failed to read expected test output");
     assert_eq!(String::from_utf8_lossy(&output.stdout), expected_output);
 }
+
+#[cfg(feature = "zero-cost")]
+#[test]
+fn asm_generation_zero_cost() {
+    let asm = rust_automata::zero_cost::Asm::of("flip_flop::main").unwrap();
+    asm.assert_zero_cost();
+}
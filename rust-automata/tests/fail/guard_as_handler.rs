@@ -0,0 +1,16 @@
+use rust_automata::*;
+
+#[derive(Default)]
+pub struct Idle;
+
+#[state_machine(
+    inputs(),
+    states(Idle),
+    outputs(),
+    transitions(
+        (Idle) -> (Idle) = guard_reset,
+    )
+)]
+pub struct Runner;
+
+fn main() {}
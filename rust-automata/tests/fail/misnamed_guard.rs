@@ -0,0 +1,24 @@
+use rust_automata::*;
+
+#[derive(Default)]
+pub struct Idle;
+#[derive(Default)]
+pub struct Go;
+
+#[state_machine(
+    inputs(Go),
+    states(Idle),
+    outputs(),
+    transitions(
+        (Idle, Go) -> (Idle) : guard_ready,
+    )
+)]
+pub struct Runner;
+
+impl Runner {
+    fn guard_is_ready(&self, _: &Idle) -> bool {
+        true
+    }
+}
+
+fn main() {}
@@ -0,0 +1,24 @@
+use rust_automata::*;
+
+pub mod states {
+    #[derive(Default)]
+    pub struct Idle;
+    #[derive(Default)]
+    pub struct Busy;
+}
+#[derive(Default)]
+pub struct Start;
+#[derive(Default)]
+pub struct Started;
+
+#[state_machine(
+    inputs(Start),
+    states(states::Idle, states::Busy),
+    outputs(Started),
+    transitions(
+        (states::Idle, Start) -> (states::Busy, Started) = handle_start,
+    )
+)]
+pub struct Worker;
+
+fn main() {}
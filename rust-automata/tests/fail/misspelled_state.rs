@@ -0,0 +1,25 @@
+use rust_automata::*;
+
+pub mod states {
+    #[derive(Default)]
+    pub struct Opened;
+    #[derive(Default)]
+    pub struct Closed;
+}
+#[derive(Default)]
+pub struct Push;
+
+#[state_machine(
+    inputs(Push),
+    states(states::Opened, states::Closed),
+    outputs(),
+    transitions(
+        (states::Closed, Push) -> (states::Opend), // typo of `states::Opened`
+        (states::Opened, Push) -> (Closed),        // missing module prefix
+        (states::Closed, Psh) -> (states::Closed), // typo of `Push`
+        (states::Opened, Pull) -> (states::Opened) // nothing close
+    )
+)]
+pub struct Door;
+
+fn main() {}